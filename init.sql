@@ -1,5 +1,6 @@
 -- 初始化瑜伽约课数据库
 -- Initialize yoga booking database
+-- 表结构以 server/migrations 为准，服务启动时会在此基础上执行迁移；本文件用于本地开发和示例数据
 
 -- 创建用户表
 CREATE TABLE IF NOT EXISTS users (
@@ -49,13 +50,14 @@ CREATE TABLE IF NOT EXISTS teachers (
     total_ratings INTEGER DEFAULT 0, -- 总评分次数
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-    is_active BOOLEAN DEFAULT TRUE
+    is_active BOOLEAN DEFAULT TRUE,
+    deleted_at TIMESTAMP WITH TIME ZONE -- 归档（软删除）时间，NULL表示未删除
 );
 
 -- 创建地点/教室表
 CREATE TABLE IF NOT EXISTS locations (
     id SERIAL PRIMARY KEY,
-    name VARCHAR(255) NOT NULL UNIQUE, -- 教室名称，如"A教室", "空中瑜伽室", "普拉提室"
+    name VARCHAR(255) NOT NULL, -- 教室名称，如"A教室", "空中瑜伽室", "普拉提室"，未归档的教室名称唯一
     description TEXT, -- 教室描述
     capacity INTEGER NOT NULL DEFAULT 20, -- 教室容量
     equipment TEXT[], -- 教室设备，如["瑜伽垫", "空中吊带", "普拉提器械"]
@@ -69,6 +71,7 @@ CREATE TABLE IF NOT EXISTS locations (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     is_active BOOLEAN DEFAULT TRUE,
+    deleted_at TIMESTAMP WITH TIME ZONE, -- 归档（软删除）时间，NULL表示未删除
     
     CONSTRAINT valid_capacity CHECK (capacity > 0)
 );
@@ -94,6 +97,9 @@ CREATE TABLE IF NOT EXISTS lessons (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     is_active BOOLEAN DEFAULT TRUE,
+    cancelled_at TIMESTAMP WITH TIME ZONE, -- 场馆取消课程的时间
+    cancellation_reason TEXT, -- 场馆取消课程的原因
+    deleted_at TIMESTAMP WITH TIME ZONE, -- 归档（软删除）时间，NULL表示未删除
     
     -- 添加约束确保时间合理性
     CONSTRAINT valid_time_range CHECK (end_time > start_time),
@@ -312,6 +318,32 @@ CREATE TABLE IF NOT EXISTS membership_card_usage (
     CONSTRAINT check_classes_consumed_not_zero CHECK (classes_consumed != 0)
 );
 
+-- 创建代课记录表 (记录原教师与代课教师)
+CREATE TABLE IF NOT EXISTS lesson_substitutions (
+    id SERIAL PRIMARY KEY,
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id),
+    original_teacher_id INTEGER REFERENCES teachers(id),
+    substitute_teacher_id INTEGER NOT NULL REFERENCES teachers(id),
+    reason TEXT, -- 代课原因，如"老师生病"
+    allow_free_cancel BOOLEAN DEFAULT FALSE, -- 是否允许已预约会员免责取消
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建会员通知表 (待发送的消息队列)
+CREATE TABLE IF NOT EXISTS notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    lesson_id INTEGER REFERENCES lessons(id),
+    booking_id INTEGER REFERENCES bookings(id) ON DELETE SET NULL,
+    notification_type VARCHAR(50) NOT NULL, -- teacher_substituted, lesson_cancelled
+    title VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, sent, failed
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMP WITH TIME ZONE,
+    read_at TIMESTAMP WITH TIME ZONE
+);
+
 -- 创建索引 (优化版)
 CREATE INDEX IF NOT EXISTS idx_users_open_id ON users(open_id);
 CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at);
@@ -325,7 +357,7 @@ CREATE INDEX IF NOT EXISTS idx_teachers_rating ON teachers(average_rating DESC);
 CREATE INDEX IF NOT EXISTS idx_locations_active ON locations(is_active);
 CREATE INDEX IF NOT EXISTS idx_locations_booking_enabled ON locations(booking_enabled);
 CREATE INDEX IF NOT EXISTS idx_locations_capacity ON locations(capacity);
-CREATE INDEX IF NOT EXISTS idx_locations_name ON locations(name);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_locations_name_active ON locations(name) WHERE deleted_at IS NULL;
 
 CREATE INDEX IF NOT EXISTS idx_lessons_start_time ON lessons(start_time);
 CREATE INDEX IF NOT EXISTS idx_lessons_teacher_id ON lessons(teacher_id);
@@ -334,6 +366,7 @@ CREATE INDEX IF NOT EXISTS idx_lessons_type ON lessons(lesson_type);
 CREATE INDEX IF NOT EXISTS idx_lessons_difficulty ON lessons(difficulty_level);
 CREATE INDEX IF NOT EXISTS idx_lessons_active ON lessons(is_active);
 CREATE INDEX IF NOT EXISTS idx_lessons_time_active ON lessons(start_time, is_active);
+CREATE INDEX IF NOT EXISTS idx_lessons_not_deleted ON lessons(start_time) WHERE deleted_at IS NULL;
 
 CREATE INDEX IF NOT EXISTS idx_bookings_user_id ON bookings(user_id);
 CREATE INDEX IF NOT EXISTS idx_bookings_lesson_id ON bookings(lesson_id);
@@ -374,6 +407,11 @@ CREATE INDEX IF NOT EXISTS idx_membership_card_usage_booking ON membership_card_
 CREATE INDEX IF NOT EXISTS idx_membership_card_usage_user ON membership_card_usage(user_id);
 CREATE INDEX IF NOT EXISTS idx_membership_card_usage_used_at ON membership_card_usage(used_at);
 
+-- 代课与通知相关索引
+CREATE INDEX IF NOT EXISTS idx_lesson_substitutions_lesson ON lesson_substitutions(lesson_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_notifications_status ON notifications(status);
+
 -- 插入示例数据
 
 -- 插入地点/教室数据
@@ -384,7 +422,7 @@ INSERT INTO locations (name, description, capacity, equipment, facilities, floor
 ('普拉提器械室', '配备专业普拉提器械的练习室', 8, ARRAY['Reformer床', '稳踏椅', '梯桶', '弹簧床'], ARRAY['储物柜', '毛巾架', '空调'], 3, 'C302', 400.00, ARRAY['pilates_room1.jpg']),
 ('冥想室', '安静舒适的冥想和修复瑜伽练习空间', 20, ARRAY['冥想坐垫', '毛毯', '精油香薰', '柔和灯光'], ARRAY['静音空调', '储物柜'], 1, 'D101', 120.00, ARRAY['meditation_room1.jpg']),
 ('私教室', '一对一私教课程专用房间', 2, ARRAY['全套瑜伽用品', '辅助道具', '音响设备'], ARRAY['独立更衣区', '储物空间'], 1, 'D102', 500.00, ARRAY['private_room1.jpg'])
-ON CONFLICT (name) DO NOTHING;
+ON CONFLICT (name) WHERE deleted_at IS NULL DO NOTHING;
 
 -- 插入教师数据
 INSERT INTO teachers (name, description, avatar_url, bio, certifications, specialties, experience_years) VALUES 
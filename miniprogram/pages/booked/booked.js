@@ -99,6 +99,32 @@ Page({
     utils.navigate(evt);
   },
 
+// 生成预约日历的订阅地址并复制，会员在手机日历中添加订阅即可看到已约课程。
+// 每次生成都会使之前复制的地址失效
+async onSubscribeCalendar() {
+  try {
+    const res = await utils.request(`${app.globalData.host}/yoga/calendar?openid=${app.globalData.openid}`, {
+      method: 'POST'
+    });
+    if (!res.data || !res.data.success) {
+      throw new Error(res.data && res.data.message);
+    }
+    await wx.setClipboardData({
+      data: res.data.webcal_url
+    });
+    wx.showModal({
+      title: '已复制订阅地址',
+      content: '在手机日历中选择“添加订阅日历”并粘贴该地址，预约变化后日历会自动更新。',
+      showCancel: false
+    });
+  } catch (error) {
+    wx.showToast({
+      title: '生成失败',
+      icon: 'error'
+    });
+  }
+},
+
 async onSubmit(evt){
 try {
         const result = await utils.getStringAsync(app, `v1/unbook?id=${evt.currentTarget.dataset.id}`);
@@ -55,22 +55,26 @@ Page({
       loading: true
     })
     try {
-      const response = await shared.request({
-        url: `/yoga/lessons?start=${this.data.selectedTime}&openid=${openid}&class_type=4`,
-        method: 'GET'
-      });
-      
-      if (response && response.statusCode === 200) {
-        const lessons = response.data;
-        // Process lessons to add time and status information
-        const processedLessons = this.processLessons(lessons, openid);
-        this.setData({
-          lessons: processedLessons,
-          loading: false
+      // 只取选中这一天的团课和工作坊，一页放不下时按 next_cursor 继续取
+      const from = this.data.selectedTime;
+      let lessons = [];
+      let cursor = '';
+      do {
+        const response = await shared.request({
+          url: `/yoga/lessons/search?from=${from}&to=${from + 86400}&lesson_type=team,workshop&openid=${openid}&limit=50&cursor=${cursor}`,
+          method: 'GET'
         });
-      } else {
-        throw new Error('Failed to load lessons');
-      }
+        if (!response || response.statusCode !== 200) {
+          throw new Error('Failed to load lessons');
+        }
+        lessons = lessons.concat(response.data.lessons);
+        cursor = response.data.next_cursor || '';
+      } while (cursor);
+
+      this.setData({
+        lessons: this.processLessons(lessons),
+        loading: false
+      });
     } catch (error) {
       console.error('Failed to load lessons:', error);
       this.setData({
@@ -81,67 +85,41 @@ Page({
     }
   },
 
-  // Process lessons to add time formatting and status
-  processLessons(lessons, openid) {
-    if (!Array.isArray(lessons)) return [];
-    
+  // 按上课时间和预约情况设置状态，接口已按开始时间排好序。
+  // 搜索结果不含场馆已取消的课程，这些课程不再显示，已预约的会员会收到取消通知
+  processLessons(lessons) {
+    const now = Date.now() / 1000;
     return lessons.map(lesson => {
-      const now = Date.now() / 1000;
-      const lessonTime = lesson.date_time + lesson.start_time;
-      const endTime = lesson.date_time + lesson.end_time;
-      
-      // Add time formatting
       lesson.time = this.formatTime(lesson.start_time, lesson.end_time);
-      lesson.date = this.formatDate(lesson.date_time);
-      
-      // Add status and mode
-      if (now - endTime > 3600) {
+      lesson.date = this.formatDate(lesson.start_time);
+
+      if (now - lesson.end_time > 3600) {
         lesson.mode = 1;
         lesson.label = "已完成";
-      } else if (now > lessonTime) {
+      } else if (now > lesson.start_time) {
         lesson.mode = 16;
         lesson.label = "正在上课";
-      } else if (lessonTime - now < 3600) {
+      } else if (lesson.start_time - now < 3600) {
         lesson.mode = 8;
         lesson.label = "准备上课";
+      } else if (lesson.is_booked) {
+        lesson.mode = 64;
+        lesson.label = "取消预约";
+      } else if (lesson.available_seats <= 0) {
+        lesson.mode = 2;
+        lesson.label = "已满额";
       } else {
-        const hidden = lesson.hidden || 0;
-        const peoples = lesson.peoples || 0;
-        const users = lesson.users || [];
-        const count = users.length;
-        
-        if (hidden === -1) {
-          lesson.mode = 4;
-          lesson.label = "已取消";
-        } else {
-          const userBooking = users.find(user => user.open_id === openid);
-          if (userBooking) {
-            lesson.mode = 64;
-            lesson.label = "取消预约";
-            lesson.reservation_id = userBooking.reservation_id;
-          } else if (count >= peoples) {
-            lesson.mode = 2;
-            lesson.label = "已满额";
-          } else {
-            lesson.mode = 32;
-            lesson.label = "预约";
-          }
-        }
+        lesson.mode = 32;
+        lesson.label = "预约";
       }
-      
       return lesson;
-    }).sort((a, b) => {
-      const timeA = a.date_time + a.start_time;
-      const timeB = b.date_time + b.start_time;
-      return timeA - timeB;
     });
   },
 
   formatTime(startTime, endTime) {
     const formatSeconds = (seconds) => {
-      const hours = Math.floor(seconds / 3600);
-      const minutes = Math.floor((seconds % 3600) / 60);
-      return `${hours}:${minutes.toString().padStart(2, '0')}`;
+      const date = new Date(seconds * 1000);
+      return `${date.getHours()}:${date.getMinutes().toString().padStart(2, '0')}`;
     };
     return `${formatSeconds(startTime)}-${formatSeconds(endTime)}`;
   },
@@ -205,26 +183,29 @@ Page({
       });
       
       if (response && response.statusCode === 200) {
-        const result = parseFloat(response.data);
-        if (result > 0) {
-          await this.loadData();
-          wx.showToast({
-            title: '预约成功',
-            icon: 'success'
-          });
-        } else {
-          wx.showModal({
-            title: '信息',
-            content: '请您购买会员卡',
-            success: res => {
-              if (res.confirm) {
-                wx.navigateTo({
-                  url: '/pages/membership/membership'
-                });
-              }
+        await this.loadData();
+        wx.showToast({
+          title: '预约成功',
+          icon: 'success'
+        });
+      } else if (response && response.data && response.data.code === 'NO_VALID_CARD') {
+        wx.showModal({
+          title: '信息',
+          content: '请您购买会员卡',
+          success: res => {
+            if (res.confirm) {
+              wx.navigateTo({
+                url: '/pages/membership/membership'
+              });
             }
-          })
-        }
+          }
+        })
+      } else {
+        // 失败时返回 {code, message, data}，message 可以直接展示
+        wx.showToast({
+          title: (response && response.data && response.data.message) || '预约失败',
+          icon: 'none'
+        });
       }
     } catch (error) {
       console.error('预约失败:', error);
@@ -249,6 +230,11 @@ Page({
           title: '取消成功',
           icon: 'success'
         });
+      } else {
+        wx.showToast({
+          title: (response && response.data && response.data.message) || '取消失败',
+          icon: 'none'
+        });
       }
     } catch (error) {
       console.error('取消预约失败:', error);
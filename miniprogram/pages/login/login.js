@@ -45,18 +45,37 @@ Page({
         }
         catch { }
     },
-    onChooseAvatar(e) {
-        // `${app.globalData.host}/yoga/picture`
-        let url = `${app.globalData.host}/yoga/avatar`;
+    async onChooseAvatar(e) {
+        // 本地没有保存 openid 时 getStorage 会失败
+        const openid = app.globalData.openid || (await app.getOpenId().catch(() => null));
+        if (!openid) {
+            wx.showToast({
+                icon: "error",
+                title: "请先登录"
+            })
+            return;
+        }
+        let url = `${app.globalData.host}/yoga/avatar?openid=${encodeURIComponent(openid)}`;
 
         let { avatarUrl } = e.detail
         wx.uploadFile({
-            url, //仅为示例，非真实的接口地址
+            url,
             filePath: avatarUrl,
-            name: 'images',
+            name: 'file',
             success: res => {
+                let data = {};
+                try {
+                    data = JSON.parse(res.data);
+                } catch { }
+                if (res.statusCode !== 200 || !data.avatar_url) {
+                    wx.showToast({
+                        icon: "error",
+                        title: "头像上传失败"
+                    })
+                    return;
+                }
                 this.setData({
-                    avatarUrl: `${app.globalData.host}/images/${res.data}`,
+                    avatarUrl: data.avatar_url,
                 })
             }
         });
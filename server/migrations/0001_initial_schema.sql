@@ -0,0 +1,396 @@
+-- 基础表结构、枚举与索引
+-- Baseline schema: tables, enums and indexes.
+-- 所有语句均可重复执行，已用 init.sql 初始化过的数据库也能直接迁移
+
+-- 创建用户表
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    open_id VARCHAR(255) UNIQUE NOT NULL,
+    nick_name VARCHAR(255),
+    avatar_url TEXT,
+    phone VARCHAR(20),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_admin BOOLEAN DEFAULT FALSE
+);
+
+-- 创建后台管理员表
+CREATE TABLE IF NOT EXISTS admin_users (
+    id SERIAL PRIMARY KEY,
+    username VARCHAR(100) UNIQUE NOT NULL,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT TRUE
+);
+
+-- 创建课程类型枚举
+DO $$ BEGIN
+    CREATE TYPE lesson_type AS ENUM ('team', 'small_class', 'private', 'equipment_small_class', 'workshop');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- 创建难度等级枚举  
+DO $$ BEGIN
+    CREATE TYPE difficulty_level AS ENUM ('beginner', 'intermediate', 'advanced', 'all_levels');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- 创建会员卡类型枚举
+DO $$ BEGIN
+    CREATE TYPE membership_card_type AS ENUM ('unlimited', 'count_based');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- 创建会员卡状态枚举
+DO $$ BEGIN
+    CREATE TYPE membership_card_status AS ENUM ('active', 'expired', 'suspended', 'used_up');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- 创建教师表
+CREATE TABLE IF NOT EXISTS teachers (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    avatar_url TEXT,
+    bio TEXT, -- 教师简历
+    certifications TEXT[], -- 认证资质数组
+    specialties TEXT[], -- 专长领域数组
+    experience_years INTEGER DEFAULT 0, -- 教学经验年数
+    average_rating DECIMAL(2,1) DEFAULT 0.0 CHECK (average_rating >= 0.0 AND average_rating <= 5.0),
+    total_ratings INTEGER DEFAULT 0, -- 总评分次数
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT TRUE
+);
+
+-- 创建地点/教室表
+CREATE TABLE IF NOT EXISTS locations (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE, -- 教室名称，如"A教室", "空中瑜伽室", "普拉提室"
+    description TEXT, -- 教室描述
+    capacity INTEGER NOT NULL DEFAULT 20, -- 教室容量
+    equipment TEXT[], -- 教室设备，如["瑜伽垫", "空中吊带", "普拉提器械"]
+    facilities TEXT[], -- 教室设施，如["更衣室", "淋浴间", "储物柜"]
+    floor_number INTEGER, -- 楼层
+    room_number VARCHAR(50), -- 房间号
+    is_accessible BOOLEAN DEFAULT TRUE, -- 无障碍设施
+    booking_enabled BOOLEAN DEFAULT TRUE, -- 是否允许预订
+    hourly_rate DECIMAL(10,2), -- 每小时租金（如果支持教室租赁）
+    images TEXT[], -- 教室图片
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT TRUE,
+    
+    CONSTRAINT valid_capacity CHECK (capacity > 0)
+);
+
+-- 创建课程表 (增强版)
+CREATE TABLE IF NOT EXISTS lessons (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    teacher_id INTEGER REFERENCES teachers(id),
+    location_id INTEGER REFERENCES locations(id),
+    lesson_type lesson_type NOT NULL DEFAULT 'team',
+    difficulty_level difficulty_level NOT NULL DEFAULT 'all_levels',
+    start_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    end_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    max_students INTEGER NOT NULL,
+    current_students INTEGER DEFAULT 0,
+    price DECIMAL(10,2) DEFAULT 0.00, -- 课程价格
+    equipment_required TEXT[], -- 所需器材
+    prerequisites TEXT, -- 先决条件
+    cancellation_policy TEXT, -- 取消政策
+    notes TEXT, -- 课程备注
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT TRUE,
+    
+    -- 添加约束确保时间合理性
+    CONSTRAINT valid_time_range CHECK (end_time > start_time),
+    CONSTRAINT valid_max_students CHECK (max_students > 0),
+    CONSTRAINT valid_current_students CHECK (current_students >= 0 AND current_students <= max_students)
+);
+
+-- 创建预约状态枚举
+DO $$ BEGIN
+    CREATE TYPE booking_status AS ENUM ('pending', 'confirmed', 'cancelled', 'completed', 'no_show');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- 创建预约表 (增强版)
+CREATE TABLE IF NOT EXISTS bookings (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER REFERENCES users(id),
+    lesson_id INTEGER REFERENCES lessons(id),
+    booking_time TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    status booking_status DEFAULT 'confirmed',
+    notes TEXT,
+    payment_status VARCHAR(20) DEFAULT 'pending', -- pending, paid, refunded
+    payment_amount DECIMAL(10,2),
+    cancellation_reason TEXT,
+    cancelled_at TIMESTAMP WITH TIME ZONE,
+    attended BOOLEAN DEFAULT NULL, -- NULL表示未确定，TRUE/FALSE表示是否出席
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, lesson_id)
+);
+
+-- 创建教师评分表
+CREATE TABLE IF NOT EXISTS teacher_ratings (
+    id SERIAL PRIMARY KEY,
+    teacher_id INTEGER REFERENCES teachers(id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    lesson_id INTEGER REFERENCES lessons(id) ON DELETE CASCADE,
+    rating DECIMAL(2,1) NOT NULL CHECK (rating >= 0.0 AND rating <= 5.0),
+    review TEXT,
+    rating_categories JSONB, -- 存储不同维度的评分，如：{"teaching": 4.5, "communication": 5.0, "professionalism": 4.0}
+    is_anonymous BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    
+    -- 确保每个用户对每个老师的每堂课只能评分一次
+    UNIQUE(user_id, teacher_id, lesson_id)
+);
+
+-- 创建评分标准表
+CREATE TABLE IF NOT EXISTS rating_criteria (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE, -- 评分标准名称，如"教学能力"、"沟通技巧"等
+    description TEXT,
+    weight DECIMAL(3,2) DEFAULT 1.00, -- 权重，用于计算加权平均分
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建通知公告表
+CREATE TABLE IF NOT EXISTS notices (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    author VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    is_active BOOLEAN DEFAULT TRUE,
+    priority INTEGER DEFAULT 0
+);
+
+-- 创建调试日志表（用于记录设备信息）
+CREATE TABLE IF NOT EXISTS debug_logs (
+    id SERIAL PRIMARY KEY,
+    open_id VARCHAR(255),
+    brand VARCHAR(255),
+    model VARCHAR(255),
+    pixel_ratio DECIMAL,
+    screen_height INTEGER,
+    screen_width INTEGER,
+    version VARCHAR(255),
+    sdk_version VARCHAR(255),
+    platform VARCHAR(255),
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建轮播图表 (poster表)
+CREATE TABLE IF NOT EXISTS posters (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255),
+    image VARCHAR(255) NOT NULL, -- 图片文件名
+    link_url TEXT, -- 跳转链接
+    sort_order INTEGER DEFAULT 0,
+    is_active BOOLEAN DEFAULT TRUE,
+    start_date TIMESTAMP WITH TIME ZONE,
+    end_date TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建功能按钮表 (用于首页actions)
+CREATE TABLE IF NOT EXISTS action_buttons (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    icon TEXT, -- 图标链接URL
+    link TEXT NOT NULL, -- 跳转链接，如 /pages/booking/booking
+    sort_order INTEGER DEFAULT 0,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建商城信息表
+CREATE TABLE IF NOT EXISTS market_info (
+    id SERIAL PRIMARY KEY,
+    slogan TEXT NOT NULL,
+    description TEXT,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建会员卡套餐表 (卡的模板)
+CREATE TABLE IF NOT EXISTS membership_plans (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL, -- 套餐名称，如"年卡", "半年卡", "20次卡"
+    description TEXT,
+    card_type membership_card_type NOT NULL, -- unlimited 或 count_based
+    validity_days INTEGER NOT NULL, -- 有效期天数
+    total_classes INTEGER, -- 如果是次数卡，总次数；如果是不限次卡则为NULL
+    price DECIMAL(10,2) NOT NULL, -- 价格
+    original_price DECIMAL(10,2), -- 原价，用于显示优惠
+    applicable_lesson_types lesson_type[], -- 适用的课程类型数组，NULL表示全部适用
+    max_bookings_per_day INTEGER DEFAULT 1, -- 每天最多可约课数
+    transfer_allowed BOOLEAN DEFAULT FALSE, -- 是否允许转让
+    refund_allowed BOOLEAN DEFAULT FALSE, -- 是否允许退款
+    benefits TEXT[], -- 会员卡特权描述
+    restrictions TEXT[], -- 使用限制描述
+    sort_order INTEGER DEFAULT 0,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    
+    -- 约束：次数卡必须有总次数，不限次卡不需要
+    CONSTRAINT check_count_based_has_total_classes CHECK (
+        (card_type = 'count_based' AND total_classes IS NOT NULL AND total_classes > 0) OR 
+        (card_type = 'unlimited' AND total_classes IS NULL)
+    ),
+    CONSTRAINT check_validity_days_positive CHECK (validity_days > 0),
+    CONSTRAINT check_price_non_negative CHECK (price >= 0)
+);
+
+-- 创建用户会员卡表 (用户实际持有的卡)
+CREATE TABLE IF NOT EXISTS user_membership_cards (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    plan_id INTEGER NOT NULL REFERENCES membership_plans(id),
+    card_number VARCHAR(50) UNIQUE NOT NULL, -- 卡号，自动生成
+    status membership_card_status DEFAULT 'active',
+    
+    -- 卡的基本信息 (从plan复制过来，避免plan变更影响已售出的卡)
+    card_type membership_card_type NOT NULL,
+    plan_name VARCHAR(255) NOT NULL, -- 套餐名称快照
+    validity_days INTEGER NOT NULL, -- 有效期天数快照
+    total_classes INTEGER, -- 总次数快照 (仅次数卡)
+    remaining_classes INTEGER, -- 剩余次数 (仅次数卡)
+    
+    -- 时间信息
+    purchased_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP, -- 购买时间
+    activated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP, -- 激活时间
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL, -- 过期时间
+    
+    -- 价格信息
+    purchase_price DECIMAL(10,2) NOT NULL,
+    discount_amount DECIMAL(10,2) DEFAULT 0, -- 优惠金额
+    actual_paid DECIMAL(10,2) NOT NULL, -- 实际支付金额
+    
+    -- 使用限制 (从plan复制)
+    applicable_lesson_types lesson_type[],
+    max_bookings_per_day INTEGER DEFAULT 1,
+    transfer_allowed BOOLEAN DEFAULT FALSE,
+    refund_allowed BOOLEAN DEFAULT FALSE,
+    
+    -- 状态信息
+    suspended_at TIMESTAMP WITH TIME ZONE, -- 暂停时间
+    suspended_reason TEXT, -- 暂停原因
+    notes TEXT, -- 备注
+    
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    
+    -- 约束
+    CONSTRAINT check_remaining_classes_valid CHECK (
+        (card_type = 'unlimited') OR 
+        (card_type = 'count_based' AND remaining_classes >= 0 AND remaining_classes <= total_classes)
+    ),
+    CONSTRAINT check_actual_paid_valid CHECK (actual_paid >= 0),
+    CONSTRAINT check_discount_valid CHECK (discount_amount >= 0),
+    CONSTRAINT check_price_calculation CHECK (actual_paid = purchase_price - discount_amount)
+);
+
+-- 创建会员卡使用记录表
+CREATE TABLE IF NOT EXISTS membership_card_usage (
+    id SERIAL PRIMARY KEY,
+    user_card_id INTEGER NOT NULL REFERENCES user_membership_cards(id) ON DELETE CASCADE,
+    booking_id INTEGER REFERENCES bookings(id) ON DELETE SET NULL, -- 关联的预约记录
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id),
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    
+    -- 使用信息
+    usage_type VARCHAR(20) NOT NULL DEFAULT 'booking', -- booking, refund
+    classes_consumed INTEGER DEFAULT 1, -- 消耗的次数，退款时为负数
+    used_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    
+    -- 使用时卡的状态快照
+    remaining_classes_before INTEGER, -- 使用前剩余次数
+    remaining_classes_after INTEGER, -- 使用后剩余次数
+    
+    notes TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    
+    CONSTRAINT check_classes_consumed_not_zero CHECK (classes_consumed != 0)
+);
+
+-- 创建索引 (优化版)
+CREATE INDEX IF NOT EXISTS idx_users_open_id ON users(open_id);
+CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at);
+
+CREATE INDEX IF NOT EXISTS idx_admin_users_username ON admin_users(username);
+CREATE INDEX IF NOT EXISTS idx_admin_users_active ON admin_users(is_active);
+
+CREATE INDEX IF NOT EXISTS idx_teachers_active ON teachers(is_active);
+CREATE INDEX IF NOT EXISTS idx_teachers_rating ON teachers(average_rating DESC);
+
+CREATE INDEX IF NOT EXISTS idx_locations_active ON locations(is_active);
+CREATE INDEX IF NOT EXISTS idx_locations_booking_enabled ON locations(booking_enabled);
+CREATE INDEX IF NOT EXISTS idx_locations_capacity ON locations(capacity);
+CREATE INDEX IF NOT EXISTS idx_locations_name ON locations(name);
+
+CREATE INDEX IF NOT EXISTS idx_lessons_start_time ON lessons(start_time);
+CREATE INDEX IF NOT EXISTS idx_lessons_teacher_id ON lessons(teacher_id);
+CREATE INDEX IF NOT EXISTS idx_lessons_location_id ON lessons(location_id);
+CREATE INDEX IF NOT EXISTS idx_lessons_type ON lessons(lesson_type);
+CREATE INDEX IF NOT EXISTS idx_lessons_difficulty ON lessons(difficulty_level);
+CREATE INDEX IF NOT EXISTS idx_lessons_active ON lessons(is_active);
+CREATE INDEX IF NOT EXISTS idx_lessons_time_active ON lessons(start_time, is_active);
+
+CREATE INDEX IF NOT EXISTS idx_bookings_user_id ON bookings(user_id);
+CREATE INDEX IF NOT EXISTS idx_bookings_lesson_id ON bookings(lesson_id);
+CREATE INDEX IF NOT EXISTS idx_bookings_status ON bookings(status);
+CREATE INDEX IF NOT EXISTS idx_bookings_created_at ON bookings(created_at);
+CREATE INDEX IF NOT EXISTS idx_bookings_user_lesson ON bookings(user_id, lesson_id);
+
+CREATE INDEX IF NOT EXISTS idx_teacher_ratings_teacher_id ON teacher_ratings(teacher_id);
+CREATE INDEX IF NOT EXISTS idx_teacher_ratings_user_id ON teacher_ratings(user_id);
+CREATE INDEX IF NOT EXISTS idx_teacher_ratings_lesson_id ON teacher_ratings(lesson_id);
+CREATE INDEX IF NOT EXISTS idx_teacher_ratings_created_at ON teacher_ratings(created_at);
+
+CREATE INDEX IF NOT EXISTS idx_notices_created_at ON notices(created_at);
+CREATE INDEX IF NOT EXISTS idx_notices_active ON notices(is_active);
+
+CREATE INDEX IF NOT EXISTS idx_debug_logs_open_id ON debug_logs(open_id);
+CREATE INDEX IF NOT EXISTS idx_debug_logs_created_at ON debug_logs(created_at);
+
+CREATE INDEX IF NOT EXISTS idx_posters_active ON posters(is_active);
+CREATE INDEX IF NOT EXISTS idx_posters_sort ON posters(sort_order);
+
+CREATE INDEX IF NOT EXISTS idx_action_buttons_active ON action_buttons(is_active);
+CREATE INDEX IF NOT EXISTS idx_action_buttons_sort ON action_buttons(sort_order);
+
+-- 会员卡相关索引
+CREATE INDEX IF NOT EXISTS idx_membership_plans_active ON membership_plans(is_active);
+CREATE INDEX IF NOT EXISTS idx_membership_plans_type ON membership_plans(card_type);
+CREATE INDEX IF NOT EXISTS idx_membership_plans_sort ON membership_plans(sort_order);
+
+CREATE INDEX IF NOT EXISTS idx_user_membership_cards_user_id ON user_membership_cards(user_id);
+CREATE INDEX IF NOT EXISTS idx_user_membership_cards_status ON user_membership_cards(status);
+CREATE INDEX IF NOT EXISTS idx_user_membership_cards_expires ON user_membership_cards(expires_at);
+CREATE INDEX IF NOT EXISTS idx_user_membership_cards_card_number ON user_membership_cards(card_number);
+CREATE INDEX IF NOT EXISTS idx_user_membership_cards_user_status ON user_membership_cards(user_id, status);
+
+CREATE INDEX IF NOT EXISTS idx_membership_card_usage_user_card ON membership_card_usage(user_card_id);
+CREATE INDEX IF NOT EXISTS idx_membership_card_usage_booking ON membership_card_usage(booking_id);
+CREATE INDEX IF NOT EXISTS idx_membership_card_usage_user ON membership_card_usage(user_id);
+CREATE INDEX IF NOT EXISTS idx_membership_card_usage_used_at ON membership_card_usage(used_at);
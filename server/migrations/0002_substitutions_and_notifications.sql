@@ -0,0 +1,32 @@
+-- 代课记录与会员通知
+-- Substitute teacher records and the member notification queue.
+
+-- 创建代课记录表 (记录原教师与代课教师)
+CREATE TABLE IF NOT EXISTS lesson_substitutions (
+    id SERIAL PRIMARY KEY,
+    lesson_id INTEGER NOT NULL REFERENCES lessons(id),
+    original_teacher_id INTEGER REFERENCES teachers(id),
+    substitute_teacher_id INTEGER NOT NULL REFERENCES teachers(id),
+    reason TEXT, -- 代课原因，如"老师生病"
+    allow_free_cancel BOOLEAN DEFAULT FALSE, -- 是否允许已预约会员免责取消
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 创建会员通知表 (待发送的消息队列)
+CREATE TABLE IF NOT EXISTS notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    lesson_id INTEGER REFERENCES lessons(id),
+    booking_id INTEGER REFERENCES bookings(id) ON DELETE SET NULL,
+    notification_type VARCHAR(50) NOT NULL, -- teacher_substituted, lesson_cancelled
+    title VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, sent, failed
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMP WITH TIME ZONE,
+    read_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_substitutions_lesson ON lesson_substitutions(lesson_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_notifications_status ON notifications(status);
@@ -0,0 +1,5 @@
+-- 场馆取消课程
+-- Studio-side lesson cancellation.
+
+ALTER TABLE lessons ADD COLUMN IF NOT EXISTS cancelled_at TIMESTAMP WITH TIME ZONE; -- 场馆取消课程的时间
+ALTER TABLE lessons ADD COLUMN IF NOT EXISTS cancellation_reason TEXT; -- 场馆取消课程的原因
@@ -0,0 +1,13 @@
+-- 课程、教师、教室的归档（软删除）
+-- Soft delete for lessons, teachers and locations.
+
+ALTER TABLE teachers ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE locations ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE lessons ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- 教室名称只在未归档的教室之间唯一
+ALTER TABLE locations DROP CONSTRAINT IF EXISTS locations_name_key;
+DROP INDEX IF EXISTS idx_locations_name;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_locations_name_active ON locations(name) WHERE deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_lessons_not_deleted ON lessons(start_time) WHERE deleted_at IS NULL;
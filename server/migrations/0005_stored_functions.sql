@@ -0,0 +1,148 @@
+-- 服务端调用的存储函数与卡号生成
+-- Stored functions called by the server, and generated membership card numbers.
+-- 课程时间统一按 Asia/Hong_Kong 换算为当天零点 (date_time) 与当天秒数 (start_time/end_time)
+
+-- 会员卡卡号：YC + 日期 + 6位流水号
+CREATE SEQUENCE IF NOT EXISTS membership_card_number_seq;
+ALTER TABLE user_membership_cards ALTER COLUMN card_number
+    SET DEFAULT ('YC' || to_char(CURRENT_DATE, 'YYYYMMDD') || lpad(nextval('membership_card_number_seq')::text, 6, '0'));
+
+-- 课程类型与小程序 class_type 位掩码的对应关系：小班 1，私教 2，团课 4
+CREATE OR REPLACE FUNCTION fn_lesson_type_mask(p_type lesson_type)
+RETURNS integer AS $$
+    SELECT CASE p_type
+        WHEN 'small_class' THEN 1
+        WHEN 'equipment_small_class' THEN 1
+        WHEN 'private' THEN 2
+        ELSE 4
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- 本周（周一至周日）课程，用于生成课程表海报
+CREATE OR REPLACE FUNCTION fn_query_week_lessons()
+RETURNS json AS $$
+    SELECT COALESCE(json_agg(
+        json_build_object(
+            'id', l.id,
+            'lesson_name', l.title,
+            'teacher_name', COALESCE(t.name, ''),
+            'date_time', extract(epoch from date_trunc('day', l.start_time AT TIME ZONE 'Asia/Hong_Kong') AT TIME ZONE 'Asia/Hong_Kong')::bigint,
+            'start_time', extract(epoch from (l.start_time AT TIME ZONE 'Asia/Hong_Kong')::time)::integer,
+            'end_time', extract(epoch from (l.end_time AT TIME ZONE 'Asia/Hong_Kong')::time)::integer
+        ) ORDER BY l.start_time
+    ), '[]'::json)
+    FROM lessons l
+    LEFT JOIN teachers t ON l.teacher_id = t.id
+    WHERE l.is_active = true
+      AND l.deleted_at IS NULL
+      AND l.start_time >= date_trunc('week', now() AT TIME ZONE 'Asia/Hong_Kong') AT TIME ZONE 'Asia/Hong_Kong'
+      AND l.start_time < (date_trunc('week', now() AT TIME ZONE 'Asia/Hong_Kong') + INTERVAL '7 days') AT TIME ZONE 'Asia/Hong_Kong';
+$$ LANGUAGE sql STABLE;
+
+-- 某位老师在时间段内的课程及预约会员
+-- p_start/p_end: unix 秒；p_class_type: 位掩码，0 表示全部
+CREATE OR REPLACE FUNCTION fn_teacher_lessons(
+    p_start integer,
+    p_end integer,
+    p_open_id text,
+    p_class_type integer,
+    p_teacher_id integer
+)
+RETURNS json AS $$
+    SELECT json_build_object(
+        'teacher', (
+            SELECT json_build_object(
+                'id', t.id,
+                'name', t.name,
+                'thumbnail', t.avatar_url,
+                'introduction', t.description,
+                'rating', COALESCE(t.average_rating, 0.0),
+                'experience_years', t.experience_years
+            )
+            FROM teachers t
+            WHERE t.id = p_teacher_id
+        ),
+        'lessons', (
+            SELECT json_agg(
+                json_build_object(
+                    'id', l.id,
+                    'title', l.title,
+                    'class_type', fn_lesson_type_mask(l.lesson_type),
+                    'location_name', loc.name,
+                    'date_time', extract(epoch from date_trunc('day', l.start_time AT TIME ZONE 'Asia/Hong_Kong') AT TIME ZONE 'Asia/Hong_Kong')::bigint,
+                    'start_time', extract(epoch from (l.start_time AT TIME ZONE 'Asia/Hong_Kong')::time)::integer,
+                    'end_time', extract(epoch from (l.end_time AT TIME ZONE 'Asia/Hong_Kong')::time)::integer,
+                    'peoples', l.max_students,
+                    'hidden', CASE WHEN l.cancelled_at IS NOT NULL OR l.is_active = false THEN -1 ELSE 0 END,
+                    'reservation_id', (
+                        SELECT b.id FROM bookings b
+                        JOIN users u ON b.user_id = u.id
+                        WHERE b.lesson_id = l.id AND b.status = 'confirmed' AND u.open_id = p_open_id
+                    ),
+                    'users', (
+                        SELECT json_agg(
+                            json_build_object(
+                                'open_id', u.open_id,
+                                'nick_name', u.nick_name,
+                                'avatar_url', u.avatar_url,
+                                'reservation_id', b.id
+                            ) ORDER BY b.booking_time
+                        )
+                        FROM bookings b
+                        JOIN users u ON b.user_id = u.id
+                        WHERE b.lesson_id = l.id AND b.status = 'confirmed'
+                    )
+                ) ORDER BY l.start_time
+            )
+            FROM lessons l
+            LEFT JOIN locations loc ON l.location_id = loc.id
+            WHERE l.teacher_id = p_teacher_id
+              AND l.deleted_at IS NULL
+              AND l.start_time >= to_timestamp(p_start)
+              AND l.start_time < to_timestamp(p_end)
+              AND (p_class_type = 0 OR (fn_lesson_type_mask(l.lesson_type) & p_class_type) <> 0)
+        )
+    );
+$$ LANGUAGE sql STABLE;
+
+-- 小程序管理员排课：在本周指定星期几新增一节课，返回课程 id
+-- p_obj: {"class_type", "start_time", "end_time", "peoples", "date_time", "lesson", "teacher"}
+-- date_time 为星期几 (0 = 周日)，start_time/end_time 为当天秒数，teacher 为老师姓名
+CREATE OR REPLACE FUNCTION fn_admin_lessons_update(p_obj text)
+RETURNS integer AS $$
+DECLARE
+    obj json := p_obj::json;
+    v_teacher_id integer;
+    v_day date;
+    v_type lesson_type;
+    v_id integer;
+BEGIN
+    SELECT id INTO v_teacher_id
+    FROM teachers
+    WHERE name = obj->>'teacher' AND deleted_at IS NULL
+    ORDER BY is_active DESC, id
+    LIMIT 1;
+
+    v_day := date_trunc('week', now() AT TIME ZONE 'Asia/Hong_Kong')::date
+        + ((COALESCE((obj->>'date_time')::integer, 1) + 6) % 7);
+
+    v_type := CASE (obj->>'class_type')::integer
+        WHEN 1 THEN 'small_class'::lesson_type
+        WHEN 2 THEN 'private'::lesson_type
+        ELSE 'team'::lesson_type
+    END;
+
+    INSERT INTO lessons (title, teacher_id, lesson_type, start_time, end_time, max_students)
+    VALUES (
+        obj->>'lesson',
+        v_teacher_id,
+        v_type,
+        (v_day + make_interval(secs => (obj->>'start_time')::integer)) AT TIME ZONE 'Asia/Hong_Kong',
+        (v_day + make_interval(secs => (obj->>'end_time')::integer)) AT TIME ZONE 'Asia/Hong_Kong',
+        GREATEST(COALESCE((obj->>'peoples')::integer, 1), 1)
+    )
+    RETURNING id INTO v_id;
+
+    RETURN v_id;
+END;
+$$ LANGUAGE plpgsql;
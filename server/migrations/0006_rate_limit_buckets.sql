@@ -0,0 +1,42 @@
+-- 接口限流的令牌桶，rate_limit.backend 为 postgres 时多个实例共用
+-- Token buckets for request rate limiting, shared by every instance when rate_limit.backend is postgres.
+
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    key TEXT PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limit_buckets_updated_at ON rate_limit_buckets(updated_at);
+
+-- 从 p_key 对应的桶中取一个令牌。桶最多 p_capacity 个令牌，每秒补充 p_refill_per_sec 个。
+-- 取到时返回 0，否则返回还需等待的秒数
+CREATE OR REPLACE FUNCTION fn_rate_limit_take(
+    p_key TEXT,
+    p_capacity DOUBLE PRECISION,
+    p_refill_per_sec DOUBLE PRECISION
+)
+RETURNS DOUBLE PRECISION AS $$
+DECLARE
+    v_now TIMESTAMP WITH TIME ZONE := clock_timestamp();
+    v_tokens DOUBLE PRECISION;
+BEGIN
+    INSERT INTO rate_limit_buckets (key, tokens, updated_at)
+    VALUES (p_key, p_capacity, v_now)
+    ON CONFLICT (key) DO NOTHING;
+
+    SELECT LEAST(p_capacity, tokens + GREATEST(0, EXTRACT(EPOCH FROM (v_now - updated_at))) * p_refill_per_sec)
+    INTO v_tokens
+    FROM rate_limit_buckets
+    WHERE key = p_key
+    FOR UPDATE;
+
+    IF v_tokens >= 1 THEN
+        UPDATE rate_limit_buckets SET tokens = v_tokens - 1, updated_at = v_now WHERE key = p_key;
+        RETURN 0;
+    END IF;
+
+    UPDATE rate_limit_buckets SET tokens = v_tokens, updated_at = v_now WHERE key = p_key;
+    RETURN (1 - v_tokens) / p_refill_per_sec;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,20 @@
+-- 媒体库：记录每次上传的图片，按内容哈希去重
+-- Media library: one row per uploaded image, deduplicated by content hash.
+
+CREATE TABLE IF NOT EXISTS media (
+    id SERIAL PRIMARY KEY,
+    key VARCHAR(200) NOT NULL UNIQUE, -- 原图在存储中的文件名
+    sha256 CHAR(64) NOT NULL, -- 上传文件内容的哈希
+    content_type VARCHAR(50) NOT NULL,
+    size_bytes BIGINT NOT NULL, -- 原图（重新编码后）的字节数
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    variants JSONB NOT NULL DEFAULT '{}', -- 原图和各尺寸缩略图，如 {"original": {...}, "200": {"key": ..., "width": ..., ...}}
+    original_filename VARCHAR(255), -- 客户端上传时的文件名
+    uploaded_by_admin_id INTEGER REFERENCES admin_users(id) ON DELETE SET NULL,
+    uploaded_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_media_sha256 ON media(sha256);
+CREATE INDEX IF NOT EXISTS idx_media_created_at ON media(created_at DESC);
@@ -0,0 +1,9 @@
+-- 会员预约日历的订阅地址，每个会员一个，重新生成或撤销后原地址失效
+-- Secret calendar feed tokens, one per member; regenerating or revoking invalidates the old URL.
+
+CREATE TABLE IF NOT EXISTS calendar_tokens (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE, -- 订阅地址中令牌的 SHA-256，不保存令牌本身
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP WITH TIME ZONE -- 日历应用最近一次拉取的时间
+);
@@ -0,0 +1,172 @@
+-- 管理员查看会员的存储函数，课程相关函数改为由服务端传入时区（settings 中的 timezone）。
+-- 课表海报已改为在服务端查询课程，不再使用 fn_query_week_lessons
+-- Admin member lookups; lesson functions now take the studio time zone from the server settings.
+
+DROP FUNCTION IF EXISTS fn_query_week_lessons();
+DROP FUNCTION IF EXISTS fn_teacher_lessons(integer, integer, text, integer, integer);
+DROP FUNCTION IF EXISTS fn_admin_lessons_update(text);
+
+-- 某位老师在时间段内的课程及预约会员
+-- p_start/p_end: unix 秒；p_class_type: 位掩码，0 表示全部；p_tz: 换算 date_time 等字段的时区
+CREATE OR REPLACE FUNCTION fn_teacher_lessons(
+    p_start integer,
+    p_end integer,
+    p_open_id text,
+    p_class_type integer,
+    p_teacher_id integer,
+    p_tz text
+)
+RETURNS json AS $$
+    SELECT json_build_object(
+        'teacher', (
+            SELECT json_build_object(
+                'id', t.id,
+                'name', t.name,
+                'thumbnail', t.avatar_url,
+                'introduction', t.description,
+                'rating', COALESCE(t.average_rating, 0.0),
+                'experience_years', t.experience_years
+            )
+            FROM teachers t
+            WHERE t.id = p_teacher_id
+        ),
+        'lessons', (
+            SELECT json_agg(
+                json_build_object(
+                    'id', l.id,
+                    'title', l.title,
+                    'class_type', fn_lesson_type_mask(l.lesson_type),
+                    'location_name', loc.name,
+                    'date_time', extract(epoch from date_trunc('day', l.start_time AT TIME ZONE p_tz) AT TIME ZONE p_tz)::bigint,
+                    'start_time', extract(epoch from (l.start_time AT TIME ZONE p_tz)::time)::integer,
+                    'end_time', extract(epoch from (l.end_time AT TIME ZONE p_tz)::time)::integer,
+                    'peoples', l.max_students,
+                    'hidden', CASE WHEN l.cancelled_at IS NOT NULL OR l.is_active = false THEN -1 ELSE 0 END,
+                    'reservation_id', (
+                        SELECT b.id FROM bookings b
+                        JOIN users u ON b.user_id = u.id
+                        WHERE b.lesson_id = l.id AND b.status = 'confirmed' AND u.open_id = p_open_id
+                    ),
+                    'users', (
+                        SELECT json_agg(
+                            json_build_object(
+                                'open_id', u.open_id,
+                                'nick_name', u.nick_name,
+                                'avatar_url', u.avatar_url,
+                                'reservation_id', b.id
+                            ) ORDER BY b.booking_time
+                        )
+                        FROM bookings b
+                        JOIN users u ON b.user_id = u.id
+                        WHERE b.lesson_id = l.id AND b.status = 'confirmed'
+                    )
+                ) ORDER BY l.start_time
+            )
+            FROM lessons l
+            LEFT JOIN locations loc ON l.location_id = loc.id
+            WHERE l.teacher_id = p_teacher_id
+              AND l.deleted_at IS NULL
+              AND l.start_time >= to_timestamp(p_start)
+              AND l.start_time < to_timestamp(p_end)
+              AND (p_class_type = 0 OR (fn_lesson_type_mask(l.lesson_type) & p_class_type) <> 0)
+        )
+    );
+$$ LANGUAGE sql STABLE;
+
+-- 小程序管理员排课：在本周指定星期几新增一节课，返回课程 id
+-- p_obj: {"class_type", "start_time", "end_time", "peoples", "date_time", "lesson", "teacher"}
+-- date_time 为星期几 (0 = 周日)，start_time/end_time 为 p_tz 时区当天秒数，teacher 为老师姓名
+CREATE OR REPLACE FUNCTION fn_admin_lessons_update(p_obj text, p_tz text)
+RETURNS integer AS $$
+DECLARE
+    obj json := p_obj::json;
+    v_teacher_id integer;
+    v_day date;
+    v_type lesson_type;
+    v_id integer;
+BEGIN
+    SELECT id INTO v_teacher_id
+    FROM teachers
+    WHERE name = obj->>'teacher' AND deleted_at IS NULL
+    ORDER BY is_active DESC, id
+    LIMIT 1;
+
+    v_day := date_trunc('week', now() AT TIME ZONE p_tz)::date
+        + ((COALESCE((obj->>'date_time')::integer, 1) + 6) % 7);
+
+    v_type := CASE (obj->>'class_type')::integer
+        WHEN 1 THEN 'small_class'::lesson_type
+        WHEN 2 THEN 'private'::lesson_type
+        ELSE 'team'::lesson_type
+    END;
+
+    INSERT INTO lessons (title, teacher_id, lesson_type, start_time, end_time, max_students)
+    VALUES (
+        obj->>'lesson',
+        v_teacher_id,
+        v_type,
+        (v_day + make_interval(secs => (obj->>'start_time')::integer)) AT TIME ZONE p_tz,
+        (v_day + make_interval(secs => (obj->>'end_time')::integer)) AT TIME ZONE p_tz,
+        GREATEST(COALESCE((obj->>'peoples')::integer, 1), 1)
+    )
+    RETURNING id INTO v_id;
+
+    RETURN v_id;
+END;
+$$ LANGUAGE plpgsql;
+
+-- 管理员查看的会员资料，creation_time 为注册时间（unix 秒），booked 为已上和待上的课时数；
+-- 会员不存在时返回 NULL
+CREATE OR REPLACE FUNCTION fn_admin_user(p_id integer)
+RETURNS json AS $$
+    SELECT json_build_object(
+        'id', u.id,
+        'open_id', u.open_id,
+        'nick_name', u.nick_name,
+        'avatar_url', u.avatar_url,
+        'phone', u.phone,
+        'creation_time', extract(epoch from u.created_at)::bigint,
+        'booked', (
+            SELECT COUNT(*) FROM bookings b
+            WHERE b.user_id = u.id AND b.status IN ('confirmed', 'completed')
+        )
+    )
+    FROM users u
+    WHERE u.id = p_id;
+$$ LANGUAGE sql STABLE;
+
+-- 会员在时间段内的预约，字段与 fn_teacher_lessons 的课程一致
+-- p_start/p_end: unix 秒；p_tz: 换算 date_time 等字段的时区
+CREATE OR REPLACE FUNCTION fn_admin_user_lessons(
+    p_id integer,
+    p_start bigint,
+    p_end bigint,
+    p_tz text
+)
+RETURNS json AS $$
+    SELECT json_build_object(
+        'lessons', COALESCE(json_agg(
+            json_build_object(
+                'id', l.id,
+                'title', l.title,
+                'class_type', fn_lesson_type_mask(l.lesson_type),
+                'teacher_name', t.name,
+                'location_name', loc.name,
+                'date_time', extract(epoch from date_trunc('day', l.start_time AT TIME ZONE p_tz) AT TIME ZONE p_tz)::bigint,
+                'start_time', extract(epoch from (l.start_time AT TIME ZONE p_tz)::time)::integer,
+                'end_time', extract(epoch from (l.end_time AT TIME ZONE p_tz)::time)::integer,
+                'hidden', CASE WHEN l.cancelled_at IS NOT NULL OR l.is_active = false THEN -1 ELSE 0 END,
+                'reservation_id', b.id,
+                'status', b.status::text
+            ) ORDER BY l.start_time
+        ), '[]'::json)
+    )
+    FROM bookings b
+    JOIN lessons l ON b.lesson_id = l.id
+    LEFT JOIN teachers t ON l.teacher_id = t.id
+    LEFT JOIN locations loc ON l.location_id = loc.id
+    WHERE b.user_id = p_id
+      AND l.deleted_at IS NULL
+      AND l.start_time >= to_timestamp(p_start)
+      AND l.start_time < to_timestamp(p_end);
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,172 @@
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+// /api/v2 统一返回 {code, message, data}，成功时 code 为 "OK"。
+// code 是给小程序判断用的稳定字符串，message 可以直接展示给用户。
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    UserNotFound,
+    LessonNotFound,
+    BookingNotFound,
+    PlanNotFound,
+    LessonFull,
+    LessonCancelled,
+    // 开课前 cancel_cutoff_minutes 分钟内不能取消预约
+    CancelCutoffPassed(String),
+    // 老师或地点还有未开始的课程，不能归档
+    HasUpcomingLessons(i64),
+    NoValidCard,
+    BadRequest(String),
+    InvalidParameter(String),
+    // 上传的文件不是支持的图片格式
+    UnsupportedImage(String),
+    // 图片损坏或像素过多
+    InvalidImage(String),
+    Unauthorized,
+    TooManyRequests,
+    Internal,
+    // 数据库错误只记录日志，不把细节返回给客户端
+    Database(String),
+    Http(Status),
+}
+
+pub type ApiResult<T = Value> = Result<ApiResponse<T>, ApiError>;
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::UserNotFound => "USER_NOT_FOUND",
+            ApiError::LessonNotFound => "LESSON_NOT_FOUND",
+            ApiError::BookingNotFound => "BOOKING_NOT_FOUND",
+            ApiError::PlanNotFound => "PLAN_NOT_FOUND",
+            ApiError::LessonFull => "LESSON_FULL",
+            ApiError::LessonCancelled => "LESSON_CANCELLED",
+            ApiError::CancelCutoffPassed(_) => "CANCEL_CUTOFF_PASSED",
+            ApiError::HasUpcomingLessons(_) => "HAS_UPCOMING_LESSONS",
+            ApiError::NoValidCard => "NO_VALID_CARD",
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::InvalidParameter(_) => "INVALID_PARAMETER",
+            ApiError::UnsupportedImage(_) => "UNSUPPORTED_IMAGE",
+            ApiError::InvalidImage(_) => "INVALID_IMAGE",
+            ApiError::Unauthorized => "UNAUTHORIZED",
+            ApiError::TooManyRequests => "TOO_MANY_REQUESTS",
+            ApiError::Internal | ApiError::Database(_) => "INTERNAL_ERROR",
+            ApiError::Http(_) => "HTTP_ERROR",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::NotFound(_)
+            | ApiError::UserNotFound
+            | ApiError::LessonNotFound
+            | ApiError::BookingNotFound
+            | ApiError::PlanNotFound => Status::NotFound,
+            ApiError::LessonFull
+            | ApiError::LessonCancelled
+            | ApiError::CancelCutoffPassed(_)
+            | ApiError::HasUpcomingLessons(_) => Status::Conflict,
+            ApiError::NoValidCard => Status::PaymentRequired,
+            ApiError::BadRequest(_) => Status::BadRequest,
+            ApiError::InvalidParameter(_) | ApiError::InvalidImage(_) => Status::UnprocessableEntity,
+            ApiError::UnsupportedImage(_) => Status::UnsupportedMediaType,
+            ApiError::Unauthorized => Status::Unauthorized,
+            ApiError::TooManyRequests => Status::TooManyRequests,
+            ApiError::Internal | ApiError::Database(_) => Status::InternalServerError,
+            ApiError::Http(status) => *status,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::BadRequest(message)
+            | ApiError::CancelCutoffPassed(message)
+            | ApiError::InvalidParameter(message)
+            | ApiError::UnsupportedImage(message)
+            | ApiError::InvalidImage(message) => message.clone(),
+            ApiError::UserNotFound => "用户不存在".to_string(),
+            ApiError::LessonNotFound => "课程不存在".to_string(),
+            ApiError::BookingNotFound => "预约不存在".to_string(),
+            ApiError::PlanNotFound => "套餐不存在或已下架".to_string(),
+            ApiError::LessonFull => "课程已约满".to_string(),
+            ApiError::LessonCancelled => "课程已取消".to_string(),
+            ApiError::HasUpcomingLessons(count) => format!("还有 {} 节未开始的课程，请先调整或取消", count),
+            ApiError::NoValidCard => "没有有效的会员卡，请先购买会员卡".to_string(),
+            ApiError::Unauthorized => "未登录或登录已过期".to_string(),
+            ApiError::TooManyRequests => "请求过于频繁，请稍后再试".to_string(),
+            ApiError::Internal | ApiError::Database(_) => "服务器内部错误".to_string(),
+            ApiError::Http(status) => status.reason_lossy().to_string(),
+        }
+    }
+
+    // 模型层用 {"success": false, "code": ..., "message": ...} 表示业务失败，没有 code 时按 400 处理
+    pub fn from_failure(result: &Value) -> Self {
+        let message = result["message"].as_str().unwrap_or_default().to_string();
+        match result["code"].as_str() {
+            Some("NOT_FOUND") => ApiError::NotFound(message),
+            Some("USER_NOT_FOUND") => ApiError::UserNotFound,
+            Some("LESSON_NOT_FOUND") => ApiError::LessonNotFound,
+            Some("BOOKING_NOT_FOUND") => ApiError::BookingNotFound,
+            Some("PLAN_NOT_FOUND") => ApiError::PlanNotFound,
+            Some("LESSON_FULL") => ApiError::LessonFull,
+            Some("LESSON_CANCELLED") => ApiError::LessonCancelled,
+            Some("CANCEL_CUTOFF_PASSED") => ApiError::CancelCutoffPassed(message),
+            Some("HAS_UPCOMING_LESSONS") => ApiError::HasUpcomingLessons(result["upcoming_lessons"].as_i64().unwrap_or_default()),
+            Some("NO_VALID_CARD") => ApiError::NoValidCard,
+            _ => ApiError::BadRequest(message),
+        }
+    }
+
+    pub fn from_status(status: Status) -> Self {
+        match status.code {
+            400 => ApiError::BadRequest("请求格式错误".to_string()),
+            401 => ApiError::Unauthorized,
+            404 => ApiError::NotFound("接口不存在".to_string()),
+            429 => ApiError::TooManyRequests,
+            422 => ApiError::InvalidParameter("缺少参数或参数格式错误".to_string()),
+            500 => ApiError::Internal,
+            _ => ApiError::Http(status),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(error: sqlx::Error) -> Self {
+        ApiError::Database(error.to_string())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        // request_id 和 route 来自请求的 span
+        if let ApiError::Database(error) = &self {
+            tracing::error!(error = %error, "database error");
+        }
+        let body = json!({
+            "code": self.code(),
+            "message": self.message(),
+            "data": null,
+        });
+        response::status::Custom(self.status(), Json(body)).respond_to(request)
+    }
+}
+
+pub struct ApiResponse<T = Value>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for ApiResponse<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = json!({
+            "code": "OK",
+            "message": "ok",
+            "data": self.0,
+        });
+        Json(body).respond_to(request)
+    }
+}
@@ -0,0 +1,9 @@
+use crate::errors::api_error::ApiError;
+use rocket::http::Status;
+use rocket::Request;
+
+// 其余状态码（400、401、422 等）同样返回 {code, message, data}
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> ApiError {
+    ApiError::from_status(status)
+}
@@ -1,4 +1,6 @@
+use crate::errors::api_error::ApiError;
+
 #[catch(500)]
-pub fn internal_error() -> &'static str {
-    "Whoops! Looks like we messed up."
+pub fn internal_error() -> ApiError {
+    ApiError::Internal
 }
@@ -1,3 +1,5 @@
+pub mod api_error;
+pub mod default_catcher;
 pub mod internal_error;
 pub mod not_found;
-
+pub mod too_many_requests;
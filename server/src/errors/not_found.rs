@@ -1,4 +1,6 @@
+use crate::errors::api_error::ApiError;
+
 #[catch(404)]
-pub fn not_found() -> String {
-    String::from("Not Found")
+pub fn not_found() -> ApiError {
+    ApiError::from_status(rocket::http::Status::NotFound)
 }
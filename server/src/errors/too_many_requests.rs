@@ -0,0 +1,20 @@
+use crate::errors::api_error::ApiError;
+use crate::utils::rate_limit::RetryAfter;
+use rocket::http::Header;
+use rocket::Request;
+
+#[derive(Responder)]
+pub struct TooManyRequests {
+    error: ApiError,
+    retry_after: Header<'static>,
+}
+
+// 被限流的请求返回 429，并通过 Retry-After 告知需要等待的秒数
+#[catch(429)]
+pub fn too_many_requests(request: &Request) -> TooManyRequests {
+    let wait = RetryAfter::of(request).unwrap_or_default();
+    TooManyRequests {
+        error: ApiError::TooManyRequests,
+        retry_after: Header::new("Retry-After", RetryAfter::header_value(wait)),
+    }
+}
@@ -2,7 +2,9 @@ use rocket::http::Status;
 use rocket::State;
 use serde_json::json;
 use sqlx::{Pool as sPool, Postgres};
+use crate::errors::api_error::ApiError;
 use crate::models::action_button;
+use tracing::{error, warn};
 
 // 获取所有功能按钮
 #[get("/yoga/action-buttons")]
@@ -10,8 +12,8 @@ pub async fn get_action_buttons(sqlxPool: &State<sPool<Postgres>>) -> Result<Str
     match action_button::get_all_action_buttons(sqlxPool.inner()).await {
         Ok(result) => Ok(json!(result).to_string()),
         Err(error) => {
-            println!("Error querying action buttons: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_action_buttons", error = %error, "error querying action buttons");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -22,8 +24,8 @@ pub async fn get_active_action_buttons(sqlxPool: &State<sPool<Postgres>>) -> Res
     match action_button::get_active_action_buttons(sqlxPool.inner()).await {
         Ok(result) => Ok(result.to_string()),
         Err(error) => {
-            println!("Error querying active action buttons: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_active_action_buttons", error = %error, "error querying active action buttons");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -32,41 +34,28 @@ pub async fn get_active_action_buttons(sqlxPool: &State<sPool<Postgres>>) -> Res
 
 // 更新功能按钮
 #[put("/yoga/action-buttons/<id>", data = "<data>")]
-pub async fn update_action_button(id: i32, data: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+pub async fn update_action_button(id: i32, data: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
     let json_data: serde_json::Value = match serde_json::from_str(&data) {
         Ok(v) => v,
         Err(_) => {
-            println!("Invalid JSON data: {}", data);
-            return Ok(json!({
-                "success": false,
-                "message": "Invalid JSON format"
-            }).to_string());
+            warn!(handler = "update_action_button", data = %data, "invalid JSON data");
+            return Err(ApiError::from_status(Status::BadRequest));
         }
     };
 
-    match action_button::update_action_button(id, json_data, sqlxPool.inner()).await {
-        Ok(result) => Ok(result.to_string()),
-        Err(error) => {
-            println!("Error updating action button: {}", error);
-            Ok(json!({
-                "success": false,
-                "message": "Failed to update action button"
-            }).to_string())
-        }
+    let result = action_button::update_action_button(id, json_data, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
+    Ok(result.to_string())
 }
 
 // 删除功能按钮
 #[delete("/yoga/action-buttons/<id>")]
-pub async fn delete_action_button(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match action_button::delete_action_button(id, sqlxPool.inner()).await {
-        Ok(result) => Ok(result.to_string()),
-        Err(error) => {
-            println!("Error deleting action button: {}", error);
-            Ok(json!({
-                "success": false,
-                "message": "Failed to delete action button"
-            }).to_string())
-        }
+pub async fn delete_action_button(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let result = action_button::delete_action_button(id, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
+    Ok(result.to_string())
 }
\ No newline at end of file
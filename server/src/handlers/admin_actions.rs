@@ -6,6 +6,7 @@ use sqlx::{Pool as sPool, Postgres, FromRow};
 use chrono::NaiveDateTime;
 
 use crate::models::action_button::get_all_action_buttons;
+use tracing::error;
 
 #[derive(Debug,Serialize, Deserialize, FromRow)]
 pub struct Action {
@@ -42,7 +43,7 @@ pub async fn get_actions(sqlxPool: &State<sPool<Postgres>>) -> Result<String, St
             Ok(serde_json::to_string(&actions).unwrap())
         }
         Err(error) => {
-            println!("Error querying actions: {}", error);
+            error!(handler = "get_actions", error = %error, "error querying actions");
             Err(Status::InternalServerError)
         }
     }
@@ -73,7 +74,7 @@ pub async fn create_action(
             Ok(serde_json::to_string(&action).unwrap())
         }
         Err(error) => {
-            println!("Error creating action: {}", error);
+            error!(handler = "create_action", error = %error, "error creating action");
             Err(Status::InternalServerError)
         }
     }
@@ -112,7 +113,7 @@ pub async fn update_action(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Error updating action: {}", error);
+            error!(handler = "update_action", error = %error, "error updating action");
             Err(Status::InternalServerError)
         }
     }
@@ -136,7 +137,7 @@ pub async fn delete_action(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result
             }
         }
         Err(error) => {
-            println!("Error deleting action: {}", error);
+            error!(handler = "delete_action", error = %error, "error deleting action");
             Err(Status::InternalServerError)
         }
     }
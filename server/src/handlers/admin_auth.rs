@@ -5,6 +5,7 @@ use serde_json::json;
 use chrono::Utc;
 use sqlx::{Pool as sPool, Postgres};
 use crate::models::admin_user;
+use tracing::error;
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
@@ -42,7 +43,7 @@ pub async fn admin_login(
             }
             Ok(None) => Err(Status::Unauthorized),
             Err(error) => {
-                println!("Database error: {}", error);
+                error!(handler = "admin_login", error = %error, "database error");
                 Err(Status::InternalServerError)
             }
         }
@@ -1,24 +1,25 @@
-use crate::utils::data::{query_int_with_params, query_json_with_params, Body};
+use crate::models::settings::Settings;
+use crate::utils::data::query_int_with_params;
 use rocket::http::Status;
 use rocket::State;
 use sqlx::{Pool as sPool, Postgres};
+use tracing::error;
 #[post("/api/admin/lessons/update?<open_id>", data = "<obj>")]
 pub async fn admin_lessons_update(
     open_id: String,
     obj: String,
     sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
 ) -> Result<String, Status> {
-    let query = "SELECT * FROM fn_admin_lessons_update($1)";
+    let query = "SELECT * FROM fn_admin_lessons_update($1, $2)";
     
-    match sqlx::query_scalar::<_, i32>(query)
-        .bind(obj)
-        .fetch_one(sqlxPool.inner()).await {
+    match query_int_with_params(query, &[&obj, settings.tz().name()], sqlxPool.inner()).await {
         Ok(result) => {
             Ok(result.to_string())
         }
         Err(error) => {
-            println!("Error: {}", error);
+            error!(handler = "admin_lessons_update", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
-}
\ No newline at end of file
+}
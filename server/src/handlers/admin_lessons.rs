@@ -1,8 +1,11 @@
 use sqlx::{Pool as sPool, Postgres};
+use crate::errors::api_error::ApiError;
 use crate::models::lession::{self, Lesson};
+use crate::models::settings::Settings;
 use rocket::http::Status;
 use rocket::State;
 use serde_json::json;
+use tracing::{error, warn};
 
 #[post("/api/admin/lesson", data = "<data>")]
 pub async fn create_lesson(
@@ -18,7 +21,7 @@ pub async fn create_lesson(
             }).to_string())
         }
         Err(error) => {
-            println!("Database error creating lesson: {}", error);
+            error!(handler = "create_lesson", error = %error, "database error creating lesson");
             Err(Status::InternalServerError)
         }
     }
@@ -40,13 +43,13 @@ pub async fn admin_lessons(
             match serde_json::to_string(&lessons) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "admin_lessons", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         },
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lessons", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -58,14 +61,14 @@ pub async fn admin_lesson(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<
             match serde_json::to_string(&lesson) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "admin_lesson", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         },
         Ok(None) => Err(Status::NotFound),
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lesson", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -82,7 +85,7 @@ pub async fn admin_lesson_hidden(
         Ok(true) => Ok("1".to_string()), // Successfully updated
         Ok(false) => Err(Status::NotFound), // No rows affected
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lesson_hidden", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -90,10 +93,25 @@ pub async fn admin_lesson_hidden(
 #[get("/api/admin/lesson/delete?<id>")]
 pub async fn admin_lesson_delete(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
     match lession::delete_lesson(id, sqlxPool.inner()).await {
-        Ok(true) => Ok("1".to_string()), // Successfully deleted
-        Ok(false) => Err(Status::NotFound), // No rows affected
+        Ok(lession::LessonDeleteResult::Archived) => Ok("1".to_string()), // Successfully archived
+        Ok(lession::LessonDeleteResult::NotFound) => Err(Status::NotFound), // No rows affected
+        Ok(lession::LessonDeleteResult::HasBookings(count)) => {
+            warn!(handler = "admin_lesson_delete", lesson_id = id, upcoming_bookings = count, "refusing to delete lesson with upcoming bookings");
+            Err(Status::Conflict)
+        }
+        Err(error) => {
+            error!(handler = "admin_lesson_delete", error = %error, "database error");
+            Err(Status::InternalServerError)
+        }
+    }
+}
+#[put("/api/admin/lessons/<id>/restore")]
+pub async fn admin_lesson_restore(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match lession::restore_lesson(id, sqlxPool.inner()).await {
+        Ok(true) => Ok(json!({"success": true, "message": "Lesson restored successfully"}).to_string()),
+        Ok(false) => Err(Status::NotFound), // Not archived or missing
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lesson_restore", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -105,13 +123,13 @@ pub async fn admin_lessons_and_teachers(id: i32, sqlxPool: &State<sPool<Postgres
             match serde_json::to_string(&lessons) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "admin_lessons_and_teachers", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         },
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lessons_and_teachers", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -122,7 +140,7 @@ pub async fn admin_lesson_update(data: String, sqlxPool: &State<sPool<Postgres>>
     let update_data: lession::LessonUpdateData = match serde_json::from_str(&data) {
         Ok(parsed) => parsed,
         Err(error) => {
-            println!("JSON parsing error: {}", error);
+            error!(handler = "admin_lesson_update", error = %error, "JSON parsing error");
             return Err(Status::BadRequest);
         }
     };
@@ -131,8 +149,36 @@ pub async fn admin_lesson_update(data: String, sqlxPool: &State<sPool<Postgres>>
         Ok(true) => Ok("1".to_string()), // Successfully updated
         Ok(false) => Err(Status::NotFound), // No rows affected
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_lesson_update", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
-}
\ No newline at end of file
+}
+
+// 代课：更换课程老师并通知已预约的会员。allow_free_cancel 时这些会员过了取消截止时间、
+// 开课前仍可取消，取消原因记为 teacher substituted
+#[post("/api/admin/lesson/substitute", data = "<data>")]
+pub async fn admin_lesson_substitute(
+    data: rocket::serde::json::Json<lession::LessonSubstitutionRequest>,
+    sqlx_pool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<String, ApiError> {
+    let result = lession::substitute_teacher(&data.into_inner(), settings.tz(), sqlx_pool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
+    }
+    Ok(result.to_string())
+}
+
+#[post("/api/admin/lesson/cancel", data = "<data>")]
+pub async fn admin_lesson_cancel(
+    data: rocket::serde::json::Json<lession::LessonCancellationRequest>,
+    sqlx_pool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<String, ApiError> {
+    let result = lession::cancel_lesson_by_studio(&data.into_inner(), settings.tz(), sqlx_pool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
+    }
+    Ok(result.to_string())
+}
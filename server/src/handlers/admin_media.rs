@@ -0,0 +1,139 @@
+use rocket::http::Status;
+use rocket::{delete, get, State};
+use serde_json::json;
+use sqlx::{Pool as sPool, Postgres};
+use tracing::{error, info, warn};
+
+use crate::handlers::upload::upload_response;
+use crate::models::media;
+use crate::utils::media_store::Media;
+
+// 没有指定时，上传超过 24 小时仍未被引用才算孤立文件
+const DEFAULT_MIN_AGE_HOURS: i64 = 24;
+
+// 媒体库：按上传时间倒序，in_use 表示是否被海报、教师、教室、按钮或会员头像引用
+#[get("/api/admin/media?<limit>&<offset>")]
+pub async fn list_media(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    sqlxPool: &State<sPool<Postgres>>,
+    store: &State<Media>,
+) -> Result<String, Status> {
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    let offset = offset.unwrap_or(0).max(0);
+
+    let (items, total) = match media::list_media(limit, offset, sqlxPool.inner()).await {
+        Ok(result) => result,
+        Err(error) => {
+            error!(handler = "list_media", error = %error, "database error");
+            return Err(Status::InternalServerError);
+        }
+    };
+    let referenced = match media::referenced_groups(sqlxPool.inner()).await {
+        Ok(referenced) => referenced,
+        Err(error) => {
+            error!(handler = "list_media", error = %error, "failed to collect media references");
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let data: Vec<_> = items
+        .iter()
+        .map(|item| {
+            let mut entry = upload_response(item, store.inner());
+            entry["id"] = json!(item.id);
+            entry["content_type"] = json!(item.content_type);
+            entry["size"] = json!(item.size_bytes);
+            entry["sha256"] = json!(item.sha256);
+            entry["original_filename"] = json!(item.original_filename);
+            entry["uploaded_by_admin_id"] = json!(item.uploaded_by_admin_id);
+            entry["uploaded_by_user_id"] = json!(item.uploaded_by_user_id);
+            entry["created_at"] = json!(item.created_at);
+            entry["in_use"] = json!(referenced.contains(media::media_group(&item.key)));
+            if let Some(entry) = entry.as_object_mut() {
+                entry.remove("success");
+            }
+            entry
+        })
+        .collect();
+
+    Ok(json!({
+        "success": true,
+        "data": data,
+        "total": total,
+        "limit": limit,
+        "offset": offset
+    })
+    .to_string())
+}
+
+// 列出存储中没有被引用的文件，不删除
+#[get("/api/admin/media/orphans?<min_age_hours>")]
+pub async fn get_orphans(
+    min_age_hours: Option<i64>,
+    sqlxPool: &State<sPool<Postgres>>,
+    store: &State<Media>,
+) -> Result<String, Status> {
+    let min_age = chrono::Duration::hours(min_age_hours.unwrap_or(DEFAULT_MIN_AGE_HOURS).max(0));
+    match media::scan_orphans(store.inner().as_ref(), min_age, sqlxPool.inner()).await {
+        Ok(scan) => Ok(json!({
+            "success": true,
+            "data": scan
+        })
+        .to_string()),
+        Err(error) => {
+            error!(handler = "get_orphans", error = %error, store = store.name(), "failed to scan for orphaned media");
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+// 删除没有被引用的文件及其媒体库记录
+#[delete("/api/admin/media/orphans?<min_age_hours>")]
+pub async fn delete_orphans(
+    min_age_hours: Option<i64>,
+    sqlxPool: &State<sPool<Postgres>>,
+    store: &State<Media>,
+) -> Result<String, Status> {
+    let min_age = chrono::Duration::hours(min_age_hours.unwrap_or(DEFAULT_MIN_AGE_HOURS).max(0));
+    let scan = match media::scan_orphans(store.inner().as_ref(), min_age, sqlxPool.inner()).await {
+        Ok(scan) => scan,
+        Err(error) => {
+            error!(handler = "delete_orphans", error = %error, store = store.name(), "failed to scan for orphaned media");
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let mut deleted = Vec::new();
+    let mut failed = Vec::new();
+    let mut freed_bytes = 0;
+    for orphan in &scan.orphans {
+        match store.delete(&orphan.key).await {
+            Ok(()) => {
+                deleted.push(orphan.key.clone());
+                freed_bytes += orphan.size;
+            }
+            Err(error) => {
+                warn!(handler = "delete_orphans", error = %error, key = %orphan.key, "failed to delete orphaned file");
+                failed.push(orphan.key.clone());
+            }
+        }
+    }
+    let records = match media::delete_media_by_keys(&deleted, sqlxPool.inner()).await {
+        Ok(records) => records,
+        Err(error) => {
+            error!(handler = "delete_orphans", error = %error, "failed to delete media records");
+            return Err(Status::InternalServerError);
+        }
+    };
+    info!(deleted = deleted.len(), failed = failed.len(), records = records, "deleted orphaned media");
+
+    Ok(json!({
+        "success": true,
+        "deleted": deleted,
+        "failed": failed,
+        "deleted_records": records,
+        "freed_bytes": freed_bytes
+    })
+    .to_string())
+}
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use chrono::NaiveDateTime;
 use sqlx::{Pool as sPool, Postgres, FromRow};
+use tracing::error;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct Notice {
@@ -47,7 +48,7 @@ pub async fn get_notices(sqlxPool: &State<sPool<Postgres>>) -> Result<String, St
             Ok(serde_json::to_string(&notices).unwrap())
         }
         Err(error) => {
-            println!("Error querying notices: {}", error);
+            error!(handler = "get_notices", error = %error, "error querying notices");
             Err(Status::InternalServerError)
         }
     }
@@ -75,7 +76,7 @@ pub async fn create_notice(
             Ok(serde_json::to_string(&notice).unwrap())
         }
         Err(error) => {
-            println!("Error creating notice: {}", error);
+            error!(handler = "create_notice", error = %error, "error creating notice");
             Err(Status::InternalServerError)
         }
     }
@@ -114,7 +115,7 @@ pub async fn update_notice(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Error updating notice: {}", error);
+            error!(handler = "update_notice", error = %error, "error updating notice");
             Err(Status::InternalServerError)
         }
     }
@@ -135,7 +136,7 @@ pub async fn delete_notice(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result
             }
         }
         Err(error) => {
-            println!("Error deleting notice: {}", error);
+            error!(handler = "delete_notice", error = %error, "error deleting notice");
             Err(Status::InternalServerError)
         }
     }
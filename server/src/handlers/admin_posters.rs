@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use chrono::NaiveDateTime;
 use sqlx::{Pool as sPool, Postgres, FromRow};
+use tracing::error;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct Poster {
@@ -55,7 +56,7 @@ pub async fn get_posters(sqlxPool: &State<sPool<Postgres>>) -> Result<String, St
             Ok(serde_json::to_string(&posters).unwrap())
         }
         Err(error) => {
-            println!("Error querying posters: {}", error);
+            error!(handler = "get_posters", error = %error, "error querying posters");
             Err(Status::InternalServerError)
         }
     }
@@ -87,7 +88,7 @@ pub async fn create_poster(
             Ok(serde_json::to_string(&poster).unwrap())
         }
         Err(error) => {
-            println!("Error creating poster: {}", error);
+            error!(handler = "create_poster", error = %error, "error creating poster");
             Err(Status::InternalServerError)
         }
     }
@@ -132,7 +133,7 @@ pub async fn update_poster(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Error updating poster: {}", error);
+            error!(handler = "update_poster", error = %error, "error updating poster");
             Err(Status::InternalServerError)
         }
     }
@@ -153,7 +154,7 @@ pub async fn delete_poster(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result
             }
         }
         Err(error) => {
-            println!("Error deleting poster: {}", error);
+            error!(handler = "delete_poster", error = %error, "error deleting poster");
             Err(Status::InternalServerError)
         }
     }
@@ -0,0 +1,14 @@
+use rocket::State;
+use serde_json::json;
+
+use crate::utils::supervisor::JobStatuses;
+
+// 后台任务的执行情况，只反映本实例：其他实例抢到锁执行的记为 skipped
+#[get("/api/admin/tasks")]
+pub fn get_tasks(statuses: &State<JobStatuses>) -> String {
+    json!({
+        "success": true,
+        "data": statuses.snapshot()
+    })
+    .to_string()
+}
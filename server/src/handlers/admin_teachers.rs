@@ -5,7 +5,9 @@ use serde_json::json;
 use chrono::NaiveDateTime;
 use sqlx::{Pool as sPool, Postgres, FromRow};
 use rust_decimal::Decimal;
+use crate::errors::api_error::ApiError;
 use crate::models::teacher;
+use tracing::error;
 
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -47,20 +49,20 @@ pub struct UpdateTeacherRequest {
     pub is_active: Option<bool>,
 }
 
-#[get("/api/admin/teachers")]
-pub async fn get_teachers(sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match teacher::get_all_teachers(sqlxPool.inner()).await {
+#[get("/api/admin/teachers?<archived>")]
+pub async fn get_teachers(archived: Option<bool>, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match teacher::get_all_teachers(archived.unwrap_or(false), sqlxPool.inner()).await {
         Ok(teachers) => {
             match serde_json::to_string(&teachers) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "get_teachers", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "get_teachers", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -87,7 +89,7 @@ pub async fn create_teacher(
             Ok(json!({"success": true, "id": teacher_id, "message": "Teacher created successfully"}).to_string())
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "create_teacher", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -120,21 +122,33 @@ pub async fn update_teacher(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "update_teacher", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
 }
 
 #[delete("/api/admin/teachers/<id>")]
-pub async fn delete_teacher(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match teacher::delete_teacher(id, sqlxPool.inner()).await {
-        Ok(response) => {
-            Ok(response.to_string())
+pub async fn delete_teacher(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let response = teacher::delete_teacher(id, sqlxPool.inner()).await?;
+    if response["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&response));
+    }
+    Ok(response.to_string())
+}
+
+#[put("/api/admin/teachers/<id>/restore")]
+pub async fn restore_teacher(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match teacher::restore_teacher(id, sqlxPool.inner()).await {
+        Ok(true) => {
+            Ok(json!({"success": true, "message": "Teacher restored successfully"}).to_string())
+        }
+        Ok(false) => {
+            Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "restore_teacher", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
-}
\ No newline at end of file
+}
@@ -3,7 +3,10 @@ use rocket::http::Status;
 use rocket::{get, post, put, delete, State};
 use serde::{Deserialize, Serialize};
 use serde_json;
+use crate::errors::api_error::ApiError;
 use crate::models::admin_user as admin_user_model;
+use crate::models::settings::Settings;
+use tracing::error;
 #[get("/yoga/admin/user/lessons?<id>&<start>&<end>&<open_id>")]
 pub async fn admin_user_lessons(
     id: i32,
@@ -11,11 +14,12 @@ pub async fn admin_user_lessons(
     end: i64,
     open_id: String,
     sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
 ) -> Result<String, Status> {
-    match admin_user_model::get_admin_user_lessons(id, start, end, sqlxPool.inner()).await {
+    match admin_user_model::get_admin_user_lessons(id, start, end, settings.tz().name(), sqlxPool.inner()).await {
         Ok(result) => Ok(result.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_user_lessons", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -25,8 +29,8 @@ pub async fn admin_users_all(open_id: String, sqlxPool: &State<sPool<Postgres>>)
     match admin_user_model::get_users_with_stats(sqlxPool.inner()).await {
         Ok(result) => Ok(result.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string()) // Return empty array as fallback
+            error!(handler = "admin_users_all", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -36,9 +40,10 @@ pub async fn admin_user(id:i32,
                         sqlxPool: &State<sPool<Postgres>>,
                         ) -> Result<String, Status> {
     match admin_user_model::get_admin_user_details(id, sqlxPool.inner()).await {
-        Ok(result) => Ok(result.to_string()),
+        Ok(Some(result)) => Ok(result.to_string()),
+        Ok(None) => Err(Status::NotFound),
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "admin_user", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -88,13 +93,13 @@ pub async fn get_users(sqlxPool: &State<sPool<Postgres>>) -> Result<String, Stat
             match serde_json::to_string(&response_users) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "get_users", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "get_users", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -129,13 +134,13 @@ pub async fn create_user(
             match serde_json::to_string(&response_user) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "create_user", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "create_user", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -171,7 +176,7 @@ pub async fn update_user(
             match serde_json::to_string(&response_user) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "update_user", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
@@ -180,21 +185,17 @@ pub async fn update_user(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "update_user", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
 }
 
 #[delete("/api/admin/users/<id>")]
-pub async fn delete_user(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match admin_user_model::delete_user(id, sqlxPool.inner()).await {
-        Ok(response) => {
-            Ok(response.to_string())
-        }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Err(Status::InternalServerError)
-        }
+pub async fn delete_user(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let response = admin_user_model::delete_user(id, sqlxPool.inner()).await?;
+    if response["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&response));
     }
+    Ok(response.to_string())
 }
\ No newline at end of file
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use chrono::NaiveDateTime;
 use sqlx::{Pool as sPool, Postgres, FromRow};
+use crate::errors::api_error::ApiError;
 use crate::models::admin_user;
+use tracing::error;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct AdminUser {
@@ -46,13 +48,13 @@ pub async fn get_admin_users(sqlxPool: &State<sPool<Postgres>>) -> Result<String
             match serde_json::to_string(&response_users) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "get_admin_users", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "get_admin_users", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -84,13 +86,13 @@ pub async fn create_admin_user(
             match serde_json::to_string(&response_user) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "create_admin_user", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "create_admin_user", error = %error, "database error");
             if error.to_string().contains("duplicate key") {
                 Err(Status::Conflict)
             } else {
@@ -105,7 +107,7 @@ pub async fn update_admin_user(
     id: i32,
     admin_user_request: rocket::serde::json::Json<UpdateAdminUserRequest>,
     sqlxPool: &State<sPool<Postgres>>,
-) -> Result<String, Status> {
+) -> Result<String, ApiError> {
     // Convert handler request to model request
     let update_request = admin_user::AdminUserUpdateRequest {
         id,
@@ -129,35 +131,28 @@ pub async fn update_admin_user(
             match serde_json::to_string(&response_user) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
-                    Err(Status::InternalServerError)
+                    error!(handler = "update_admin_user", error = %error, "JSON serialization error");
+                    Err(ApiError::Internal)
                 }
             }
         }
         Ok(None) => {
             // Either not found or protected from deactivation
             if id == 1 && admin_user_request.is_active == Some(false) {
-                Ok(json!({"error": "Default admin user cannot be deactivated"}).to_string())
+                Err(ApiError::BadRequest("默认管理员不能停用".to_string()))
             } else {
-                Err(Status::NotFound)
+                Err(ApiError::NotFound("管理员不存在".to_string()))
             }
         }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Err(Status::InternalServerError)
-        }
+        Err(error) => Err(error.into()),
     }
 }
 
 #[delete("/api/admin/admin-users/<id>")]
-pub async fn delete_admin_user(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match admin_user::delete_admin_user(id, sqlxPool.inner()).await {
-        Ok(response) => {
-            Ok(response.to_string())
-        }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Err(Status::InternalServerError)
-        }
+pub async fn delete_admin_user(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let response = admin_user::delete_admin_user(id, sqlxPool.inner()).await?;
+    if response["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&response));
     }
+    Ok(response.to_string())
 }
\ No newline at end of file
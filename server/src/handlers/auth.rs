@@ -1,17 +1,20 @@
 use std::{collections::HashMap, error::Error};
 use rocket::{http::Status, State};
 use crate::models::settings::Settings;
+use crate::utils::metrics::METRICS;
+use crate::utils::rate_limit::RateLimit;
+use tracing::{error, warn};
 async fn login_we_chat(
     settings: &State<Settings>,
     js_code: String,
 ) -> Result<String, Box<dyn Error>> {
     // println!(
     //     "login_we_chat: {}\n{}\n{}",
-    //     settings.appid, settings.secret, js_code
+    //     settings.wechat.appid, settings.wechat.secret, js_code
     // );
     let mut params = HashMap::new();
-    params.insert("appid", settings.appid.clone());
-    params.insert("secret", settings.secret.clone());
+    params.insert("appid", settings.wechat.appid.clone());
+    params.insert("secret", settings.wechat.secret.clone());
     params.insert("js_code", js_code);
     params.insert("grant_type", "authorization_code".to_string());
     let client = reqwest::Client::new();
@@ -24,12 +27,30 @@ async fn login_we_chat(
     Ok(json)
 }
 #[post("/yoga/auth", data = "<code>")]
-pub async fn auth(code: String, settings: &State<Settings>) -> Result<String, Status> {
+pub async fn auth(code: String, _limit: RateLimit, settings: &State<Settings>) -> Result<String, Status> {
     let json = login_we_chat(settings, code).await;
     match json {
-        Ok(v) => Ok(v),
+        Ok(v) => {
+            // 微信接口出错时仍返回 200，错误码放在 errcode 中
+            let errcode = serde_json::from_str::<serde_json::Value>(&v)
+                .ok()
+                .and_then(|body| body["errcode"].as_i64())
+                .unwrap_or(0);
+            if errcode != 0 {
+                METRICS
+                    .wechat_call_failures_total
+                    .with_label_values(&["code2session", "errcode"])
+                    .inc();
+                warn!(handler = "auth", errcode, "WeChat code2session returned an error");
+            }
+            Ok(v)
+        }
         Err(err) => {
-            println!("auth: {}", err);
+            METRICS
+                .wechat_call_failures_total
+                .with_label_values(&["code2session", "request"])
+                .inc();
+            error!(handler = "auth", error = %err, "WeChat code2session request failed");
             Err(Status::InternalServerError)
         }
     }
@@ -1,9 +1,13 @@
 use rocket::http::Status;
 use rocket::State;
-use serde_json::{json, Value};
+use serde_json::Value;
 use sqlx::{Pool as sPool, Postgres, FromRow};
 use chrono::NaiveDateTime;
+use crate::errors::api_error::ApiError;
 use crate::models::booking;
+use crate::models::settings::Settings;
+use crate::utils::rate_limit::RateLimit;
+use tracing::error;
 
 // Using structs from model layer
 
@@ -18,58 +22,31 @@ pub async fn lessons(
         Ok(Some(lessons)) => Ok(lessons.to_string()),
         Ok(None) => Ok("[]".to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "lessons", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
+// 成功时返回预约 id，失败时返回 {code, message, data} 和对应的状态码，如 LESSON_FULL、NO_VALID_CARD
 #[get("/yoga/book?<id>&<openid>")]
-pub async fn book(id: i32, openid: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match booking::create_booking(id, &openid, sqlxPool.inner()).await {
-        Ok(result) => {
-            // Check if the result indicates success and extract booking ID or return appropriate response
-            if let Some(success) = result.get("success").and_then(|v| v.as_bool()) {
-                if success {
-                    if let Some(booking_id) = result.get("booking_id").and_then(|v| v.as_i64()) {
-                        Ok(booking_id.to_string())
-                    } else {
-                        Ok("1".to_string()) // Success but no booking ID
-                    }
-                } else {
-                    Ok(result.to_string()) // Return error message
-                }
-            } else {
-                Ok("0".to_string()) // Default fallback
-            }
-        }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Ok("0".to_string())
-        }
+pub async fn book(id: i32, openid: String, _limit: RateLimit, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let result = booking::create_booking(id, &openid, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
+    Ok(result["booking_id"].as_i64().unwrap_or(1).to_string())
 }
+// 成功时返回取消的预约 id，预约不存在时返回 404 BOOKING_NOT_FOUND，已过取消截止时间时返回 409 CANCEL_CUTOFF_PASSED
 #[get("/yoga/unbook?<id>&<openid>")]
-pub async fn unbook(id: i32, openid: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match booking::cancel_booking(id, &openid, sqlxPool.inner()).await {
-        Ok(result) => {
-            // Check if the result indicates success and extract cancelled ID or return appropriate response
-            if let Some(success) = result.get("success").and_then(|v| v.as_bool()) {
-                if success {
-                    if let Some(cancelled_id) = result.get("cancelled_id").and_then(|v| v.as_i64()) {
-                        Ok(cancelled_id.to_string())
-                    } else {
-                        Ok("1".to_string()) // Success but no cancelled ID
-                    }
-                } else {
-                    Ok("0".to_string()) // Booking not found or cancellation failed
-                }
-            } else {
-                Ok("0".to_string()) // Default fallback
-            }
-        }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Ok("0".to_string())
-        }
+pub async fn unbook(
+    id: i32,
+    openid: String,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<String, ApiError> {
+    let result = booking::cancel_booking(id, &openid, settings.calendar.cancel_cutoff_minutes, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
-}
\ No newline at end of file
+    Ok(result["cancelled_id"].as_i64().unwrap_or(1).to_string())
+}
@@ -0,0 +1,111 @@
+use chrono::Duration;
+use rocket::http::{ContentType, Status};
+use rocket::{delete, get, post, State};
+use serde_json::json;
+use sqlx::{Pool as sPool, Postgres};
+use tracing::{error, info};
+
+use crate::errors::api_error::ApiError;
+use crate::handlers::schedule::ScheduleFormat;
+use crate::models::booking::{self, BookingWithLessonInfo};
+use crate::models::calendar_token;
+use crate::models::settings::Settings;
+use crate::utils::icalendar::{self, Calendar, CalendarEvent};
+
+fn member(openid: Option<&str>) -> Result<&str, Status> {
+    openid.filter(|openid| !openid.trim().is_empty()).ok_or(Status::Unauthorized)
+}
+
+// 生成会员预约日历的订阅地址，原来的地址随即失效
+#[post("/yoga/calendar?<openid>")]
+pub async fn issue_calendar_token(
+    openid: Option<&str>,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<String, ApiError> {
+    let openid = member(openid).map_err(ApiError::from_status)?;
+    let token = calendar_token::issue_token(openid, sqlxPool.inner())
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+    let url = format!("{}/yoga/calendar/{}/bookings.ics", settings.server_url(), token);
+    // 日历应用通过 webcal:// 识别为订阅
+    let webcal_url = format!("webcal://{}", url.split_once("://").map(|(_, rest)| rest).unwrap_or(&url));
+    info!(member = %openid, "issued calendar token");
+    Ok(json!({
+        "success": true,
+        "url": url,
+        "webcal_url": webcal_url
+    })
+    .to_string())
+}
+
+// 撤销订阅地址，之后日历应用无法再拉取
+#[delete("/yoga/calendar?<openid>")]
+pub async fn revoke_calendar_token(openid: Option<&str>, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let openid = member(openid).map_err(ApiError::from_status)?;
+    let revoked = calendar_token::revoke_token(openid, sqlxPool.inner()).await?;
+    Ok(json!({"success": true, "revoked": revoked}).to_string())
+}
+
+// 会员已确认且尚未结束的预约，每次拉取时按当前的预约生成，取消的预约随之从日历中消失
+#[get("/yoga/calendar/<token>/bookings.ics")]
+pub async fn bookings_feed(
+    token: &str,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<(ContentType, String), Status> {
+    let user_id = match calendar_token::find_user_by_token(token, sqlxPool.inner()).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            error!(handler = "bookings_feed", error = %error, "database error");
+            return Err(Status::InternalServerError);
+        }
+    };
+    let bookings = match booking::list_upcoming_bookings(user_id, sqlxPool.inner()).await {
+        Ok(bookings) => bookings,
+        Err(error) => {
+            error!(handler = "bookings_feed", error = %error, "database error");
+            return Err(Status::InternalServerError);
+        }
+    };
+
+    let server_url = settings.server_url();
+    let domain = icalendar::uid_domain(&server_url);
+    let calendar = Calendar {
+        name: "我的预约".to_string(),
+        timezone: settings.tz().name().to_string(),
+        refresh_minutes: Some(settings.calendar.refresh_minutes),
+        events: bookings
+            .iter()
+            .map(|booking| CalendarEvent {
+                uid: format!("booking-{}@{}", booking.id, domain),
+                start: booking.start_time,
+                end: booking.end_time,
+                summary: booking.lesson_title.clone(),
+                location: booking.location_name.clone(),
+                description: Some(booking_description(booking, settings)),
+                updated_at: Some(booking.booking_time),
+            })
+            .collect(),
+    };
+    Ok((ScheduleFormat::Ics.content_type(), calendar.to_ics()))
+}
+
+// 老师、教室和取消截止时间，每项一行
+fn booking_description(booking: &BookingWithLessonInfo, settings: &Settings) -> String {
+    let mut lines = Vec::new();
+    if let Some(teacher) = booking.teacher_name.as_deref().filter(|name| !name.is_empty()) {
+        lines.push(format!("老师：{}", teacher));
+    }
+    if let Some(location) = booking.location_name.as_deref().filter(|name| !name.is_empty()) {
+        lines.push(format!("教室：{}", location));
+    }
+    let cutoff = booking.start_time - Duration::minutes(settings.calendar.cancel_cutoff_minutes as i64);
+    lines.push(format!(
+        "取消截止：{}（开课前 {} 分钟）",
+        cutoff.with_timezone(&settings.tz()).format("%Y-%m-%d %H:%M"),
+        settings.calendar.cancel_cutoff_minutes
+    ));
+    lines.join("\n")
+}
@@ -1,12 +1,15 @@
 use crate::utils::client_real_addr::ClientRealAddr;
+use crate::utils::rate_limit::RateLimit;
 use sqlx::{Pool as sPool, Postgres};
 use rocket::http::Status;
 use rocket::State;
 use serde_json::Value;
+use tracing::{error, warn};
 
 #[post("/yoga/debug", data = "<data>")]
 pub async fn debug(
     client_addr: &ClientRealAddr,
+    _limit: RateLimit,
     data: String,
     sqlxPool: &State<sPool<Postgres>>,
 ) -> Result<String, Status> {
@@ -14,8 +17,8 @@ pub async fn debug(
     let json_data: Value = match serde_json::from_str(&data) {
         Ok(v) => v,
         Err(_) => {
-            println!("Invalid JSON data: {}", data);
-            return Ok("0".to_string());
+            warn!(handler = "debug", data = %data, "invalid JSON data");
+            return Err(Status::BadRequest);
         }
     };
     
@@ -53,8 +56,9 @@ pub async fn debug(
         .execute(sqlxPool.inner()).await {
         Ok(_) => Ok("1".to_string()),
         Err(error) => {
-            println!("Error inserting debug log: {}", error);
-            Ok("0".to_string()) // 返回 0 表示插入失败，但不影响主流程
+            // 小程序忽略这个接口的结果，失败不影响主流程
+            error!(handler = "debug", error = %error, "error inserting debug log");
+            Err(Status::InternalServerError)
         }
     }
 }
\ No newline at end of file
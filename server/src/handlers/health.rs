@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde_json::{json, Value};
+use sqlx::{Pool as sPool, Postgres};
+
+use crate::models::settings::Settings;
+use crate::utils::media_store::Media;
+use crate::utils::migrations;
+use crate::utils::poster::Poster;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 进程存活即返回 200，不检查依赖
+#[get("/healthz")]
+pub fn healthz() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+// 任一依赖异常时返回 503，负载均衡据此摘除实例
+#[get("/readyz")]
+pub async fn readyz(
+    sqlxPool: &State<sPool<Postgres>>,
+    store: &State<Media>,
+    poster: &State<Poster>,
+    settings: &State<Settings>,
+) -> (Status, Json<Value>) {
+    let checks = [
+        ("database", check_database(sqlxPool.inner()).await),
+        ("migrations", check_migrations(sqlxPool.inner()).await),
+        ("media_store", check_media_store(store.inner()).await),
+        ("schedule_poster", check_schedule_poster(poster.inner(), settings.inner())),
+    ];
+
+    let ready = checks.iter().all(|(_, check)| check["status"] == "ok");
+    let body = json!({
+        "status": if ready { "ok" } else { "degraded" },
+        "checks": checks.into_iter().map(|(name, check)| (name.to_string(), check)).collect::<serde_json::Map<_, _>>(),
+    });
+    let status = if ready { Status::Ok } else { Status::ServiceUnavailable };
+    (status, Json(body))
+}
+
+fn failed(error: impl ToString) -> Value {
+    json!({ "status": "error", "error": error.to_string() })
+}
+
+async fn check_database(pool: &sPool<Postgres>) -> Value {
+    let started_at = Instant::now();
+    let query = sqlx::query_scalar::<_, i32>("SELECT 1").fetch_one(pool);
+    match tokio::time::timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(_)) => json!({
+            "status": "ok",
+            "latency_ms": started_at.elapsed().as_millis() as u64,
+            "connections": pool.size(),
+            "idle_connections": pool.num_idle(),
+        }),
+        Ok(Err(error)) => failed(error),
+        Err(_) => failed("timed out"),
+    }
+}
+
+async fn check_migrations(pool: &sPool<Postgres>) -> Value {
+    match tokio::time::timeout(CHECK_TIMEOUT, migrations::status(pool)).await {
+        Ok(Ok(status)) => json!({
+            "status": if status.is_current() { "ok" } else { "error" },
+            "applied_version": status.applied_version,
+            "expected_version": status.expected_version,
+            "detail": status.describe(),
+        }),
+        Ok(Err(error)) => failed(error),
+        Err(_) => failed("timed out"),
+    }
+}
+
+// 只读检查，探针频繁调用时不会在存储中留下或删除文件
+async fn check_media_store(store: &Media) -> Value {
+    match tokio::time::timeout(CHECK_TIMEOUT, store.check()).await {
+        Ok(Ok(())) => json!({ "status": "ok", "backend": store.name() }),
+        Ok(Err(error)) => json!({ "status": "error", "backend": store.name(), "error": error }),
+        Err(_) => json!({ "status": "error", "backend": store.name(), "error": "timed out" }),
+    }
+}
+
+// 模板、底图和字体在启动时已加载并检查，这里只报告使用的模板
+fn check_schedule_poster(poster: &Poster, settings: &Settings) -> Value {
+    let (width, height) = poster.dimensions();
+    json!({
+        "status": "ok",
+        "template": poster.source(),
+        "width": width,
+        "height": height,
+        "timezone": settings.tz().name(),
+    })
+}
@@ -4,6 +4,7 @@ use serde_json::json;
 use sqlx::{Pool as sPool, Postgres, FromRow};
 use chrono::NaiveDateTime;
 use crate::models::index as index_model;
+use tracing::error;
 
 // Using structs from model layer
 
@@ -21,7 +22,7 @@ async fn index_handler(openid: Option<String>, sqlxPool: &State<sPool<Postgres>>
     match index_model::get_index_data(openid, sqlxPool.inner()).await {
         Ok(result) => Ok(result.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "index_handler", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
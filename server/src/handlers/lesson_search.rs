@@ -0,0 +1,195 @@
+use chrono::{DateTime, Duration, Utc};
+use rocket::{get, FromForm, State};
+use serde_json::json;
+use sqlx::{Pool as sPool, Postgres};
+
+use crate::errors::api_error::ApiError;
+use crate::models::lession::{DIFFICULTY_LEVELS, LESSON_TYPES};
+use crate::models::lesson_search::{self, LessonCursor, LessonSearch};
+use crate::models::settings::Settings;
+use crate::utils::time_of_day::parse_time_of_day;
+
+// 不指定 to 时搜索的天数，以及一次最多搜索的天数
+const DEFAULT_DAYS: i64 = 14;
+const MAX_DAYS: i64 = 92;
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 50;
+
+// 搜索课程的查询参数，空字符串按未填写处理
+#[derive(Debug, Default, FromForm)]
+pub struct LessonSearchQuery {
+    pub openid: Option<String>,
+    // 多个值用逗号分隔，如 team,workshop
+    pub lesson_type: Option<String>,
+    pub difficulty_level: Option<String>,
+    pub teacher_id: Option<i32>,
+    pub location_id: Option<i32>,
+    // Unix 时间戳（秒）
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    // 当地时间 HH:MM
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub has_seats: Option<bool>,
+    pub bookable: Option<bool>,
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+impl LessonSearchQuery {
+    // 检查参数并补上默认值，出错时返回可以直接给客户端看的说明
+    pub fn parse(self, now: DateTime<Utc>) -> Result<LessonSearch, String> {
+        let openid = non_empty(self.openid);
+        let lesson_types = values(self.lesson_type, &LESSON_TYPES, "lesson_type")?;
+        let difficulty_levels = values(self.difficulty_level, &DIFFICULTY_LEVELS, "difficulty_level")?;
+
+        let from = match self.from {
+            Some(secs) => DateTime::from_timestamp(secs, 0).ok_or("from 不是有效的时间")?,
+            None => now,
+        };
+        let to = match self.to {
+            Some(secs) => DateTime::from_timestamp(secs, 0).ok_or("to 不是有效的时间")?,
+            None => from + Duration::days(DEFAULT_DAYS),
+        };
+        if to <= from {
+            return Err("to 必须晚于 from".to_string());
+        }
+        if to - from > Duration::days(MAX_DAYS) {
+            return Err(format!("from 到 to 不能超过 {} 天", MAX_DAYS));
+        }
+
+        let after = time_of_day(self.after, "after")?;
+        let before = time_of_day(self.before, "before")?;
+        if let (Some(after), Some(before)) = (after, before) {
+            if after >= before {
+                return Err("after 必须早于 before".to_string());
+            }
+        }
+
+        let bookable = self.bookable.unwrap_or(false);
+        if bookable && openid.is_none() {
+            return Err("bookable 需要 openid".to_string());
+        }
+        let cursor = match non_empty(self.cursor) {
+            Some(cursor) => Some(LessonCursor::decode(&cursor).ok_or("cursor 无效")?),
+            None => None,
+        };
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(format!("limit 必须在 1 到 {} 之间", MAX_LIMIT));
+        }
+
+        Ok(LessonSearch {
+            openid,
+            lesson_types,
+            difficulty_levels,
+            teacher_id: self.teacher_id,
+            location_id: self.location_id,
+            from,
+            to,
+            after,
+            before,
+            has_seats: self.has_seats.unwrap_or(false),
+            bookable,
+            cursor,
+            limit,
+        })
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+fn values(value: Option<String>, allowed: &[&str], name: &str) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    for value in non_empty(value).iter().flat_map(|value| value.split(',')).map(str::trim) {
+        if !allowed.contains(&value) {
+            return Err(format!("{} 只能是 {}", name, allowed.join("、")));
+        }
+        values.push(value.to_string());
+    }
+    Ok(values)
+}
+
+fn time_of_day(value: Option<String>, name: &str) -> Result<Option<u32>, String> {
+    match non_empty(value) {
+        Some(value) => parse_time_of_day(&value)
+            .map(Some)
+            .ok_or_else(|| format!("{} 必须是 00:00 到 24:00 之间的 HH:MM", name)),
+        None => Ok(None),
+    }
+}
+
+// 会员搜索课程，返回 {lessons, next_cursor}，参数不合法时返回 INVALID_PARAMETER
+#[get("/yoga/lessons/search?<query..>")]
+pub async fn search_lessons(
+    query: LessonSearchQuery,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<String, ApiError> {
+    let search = query.parse(Utc::now()).map_err(ApiError::InvalidParameter)?;
+    let (lessons, next_cursor) = lesson_search::search_lessons(&search, settings.tz().name(), sqlxPool.inner()).await?;
+    Ok(json!({"lessons": lessons, "next_cursor": next_cursor}).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        let search = LessonSearchQuery::default().parse(now()).unwrap();
+        assert_eq!(search.from, now());
+        assert_eq!(search.to, now() + Duration::days(DEFAULT_DAYS));
+        assert!(search.lesson_types.is_empty() && search.openid.is_none() && search.cursor.is_none());
+        assert_eq!(search.limit, DEFAULT_LIMIT);
+        assert!(!search.has_seats && !search.bookable);
+    }
+
+    #[test]
+    fn filters() {
+        let query = LessonSearchQuery {
+            openid: Some("o1".to_string()),
+            lesson_type: Some("team, workshop".to_string()),
+            difficulty_level: Some("".to_string()),
+            from: Some(1_792_800_000),
+            to: Some(1_792_886_400),
+            after: Some("18:00".to_string()),
+            before: Some("24:00".to_string()),
+            bookable: Some(true),
+            ..Default::default()
+        };
+        let search = query.parse(now()).unwrap();
+        assert_eq!(search.lesson_types, ["team", "workshop"]);
+        assert!(search.difficulty_levels.is_empty());
+        assert_eq!((search.after, search.before), (Some(18 * 3600), Some(24 * 3600)));
+        assert_eq!(search.to - search.from, Duration::days(1));
+        assert!(search.bookable);
+    }
+
+    #[test]
+    fn invalid_queries() {
+        let invalid = [
+            LessonSearchQuery { lesson_type: Some("team,yin".to_string()), ..Default::default() },
+            LessonSearchQuery { difficulty_level: Some("expert".to_string()), ..Default::default() },
+            LessonSearchQuery { from: Some(1_792_800_000), to: Some(1_792_800_000), ..Default::default() },
+            LessonSearchQuery { to: Some(now().timestamp() + 100 * 86400), ..Default::default() },
+            LessonSearchQuery { after: Some("7pm".to_string()), ..Default::default() },
+            LessonSearchQuery { after: Some("20:00".to_string()), before: Some("08:00".to_string()), ..Default::default() },
+            LessonSearchQuery { bookable: Some(true), openid: Some(" ".to_string()), ..Default::default() },
+            LessonSearchQuery { cursor: Some("garbage".to_string()), ..Default::default() },
+            LessonSearchQuery { limit: Some(0), ..Default::default() },
+            LessonSearchQuery { limit: Some(MAX_LIMIT + 1), ..Default::default() },
+        ];
+        for query in invalid {
+            let description = format!("{:?}", query);
+            assert!(query.parse(now()).is_err(), "{}", description);
+        }
+    }
+}
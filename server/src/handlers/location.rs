@@ -1,9 +1,11 @@
+use crate::errors::api_error::ApiError;
 use crate::models::location::*;
 use sqlx::{Pool as sPool, Postgres, FromRow};
 use rocket::http::Status;
 use rocket::{get, post, put, delete, State};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tracing::{error, warn};
 
 // 获取所有地点列表
 #[get("/yoga/locations")]
@@ -11,8 +13,8 @@ pub async fn get_locations(sqlxPool: &State<sPool<Postgres>>) -> Result<String,
     match crate::models::location::get_all_locations(sqlxPool.inner()).await {
         Ok(locations) => Ok(locations.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_locations", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -23,8 +25,8 @@ pub async fn get_available_locations(sqlxPool: &State<sPool<Postgres>>) -> Resul
     match crate::models::location::get_available_locations(sqlxPool.inner()).await {
         Ok(locations) => Ok(locations.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_available_locations", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -36,60 +38,45 @@ pub async fn check_location_availability(
     start_time: String, // ISO 8601 format
     end_time: String,   // ISO 8601 format
     sqlxPool: &State<sPool<Postgres>>,
-) -> Result<String, Status> {
+) -> Result<String, ApiError> {
     // 解析时间字符串
     let start_time = match chrono::DateTime::parse_from_rfc3339(&start_time) {
         Ok(dt) => dt.with_timezone(&chrono::Utc),
         Err(_) => {
-            return Ok(json!({
-                "success": false,
-                "message": "Invalid start_time format"
-            }).to_string());
+            warn!(handler = "check_location_availability", start_time = %start_time, "invalid time format");
+            return Err(ApiError::InvalidParameter("start_time 必须是 RFC 3339 时间".to_string()));
         }
     };
 
     let end_time = match chrono::DateTime::parse_from_rfc3339(&end_time) {
         Ok(dt) => dt.with_timezone(&chrono::Utc),
         Err(_) => {
-            return Ok(json!({
-                "success": false,
-                "message": "Invalid end_time format"
-            }).to_string());
+            warn!(handler = "check_location_availability", end_time = %end_time, "invalid time format");
+            return Err(ApiError::InvalidParameter("end_time 必须是 RFC 3339 时间".to_string()));
         }
     };
 
-    match crate::models::location::check_location_availability(location_id, start_time, end_time, sqlxPool.inner()).await {
-        Ok(result) => Ok(result.to_string()),
-        Err(error) => {
-            println!("Database error: {}", error);
-            Err(Status::InternalServerError)
-        }
+    let result = crate::models::location::check_location_availability(location_id, start_time, end_time, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
+    Ok(result.to_string())
 }
 
 // 获取地点使用统计
 #[get("/yoga/locations/<id>/stats")]
-pub async fn get_location_stats(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match crate::models::location::get_location_statistics(id, sqlxPool.inner()).await {
-        Ok(Some(stats)) => Ok(stats.to_string()),
-        Ok(None) => {
-            Ok(json!({
-                "success": false,
-                "message": "Location not found"
-            }).to_string())
-        }
-        Err(error) => {
-            println!("Database error: {}", error);
-            Err(Status::InternalServerError)
-        }
+pub async fn get_location_stats(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    match crate::models::location::get_location_statistics(id, sqlxPool.inner()).await? {
+        Some(stats) => Ok(stats.to_string()),
+        None => Err(ApiError::NotFound("地点不存在".to_string())),
     }
 }
 
 // Admin CRUD operations
 
-#[get("/api/locations")]
-pub async fn get_admin_locations(sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match crate::models::location::get_all_admin_locations(sqlxPool.inner()).await {
+#[get("/api/locations?<archived>")]
+pub async fn get_admin_locations(archived: Option<bool>, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match crate::models::location::get_all_admin_locations(archived.unwrap_or(false), sqlxPool.inner()).await {
         Ok(locations) => {
             // Convert to response format
             let response_locations: Vec<LocationAdmin> = locations.into_iter().map(|loc| LocationAdmin {
@@ -108,18 +95,19 @@ pub async fn get_admin_locations(sqlxPool: &State<sPool<Postgres>>) -> Result<St
                 is_active: Some(loc.is_active),
                 created_at: Some(loc.created_at),
                 updated_at: Some(loc.updated_at),
+                deleted_at: loc.deleted_at,
             }).collect();
             
             match serde_json::to_string(&response_locations) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "get_admin_locations", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "get_admin_locations", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -142,6 +130,7 @@ pub struct LocationAdmin {
     pub is_active: Option<bool>,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Deserialize)]
@@ -181,8 +170,8 @@ pub async fn get_locations1(sqlxPool: &State<sPool<Postgres>>) -> Result<String,
     match crate::models::location::get_all_locations(sqlxPool.inner()).await {
         Ok(locations) => Ok(locations.to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_locations1", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -226,18 +215,19 @@ pub async fn create_location(
                 is_active: Some(location.is_active),
                 created_at: Some(location.created_at),
                 updated_at: Some(location.updated_at),
+                deleted_at: location.deleted_at,
             };
             
             match serde_json::to_string(&response_location) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "create_location", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "create_location", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
@@ -285,12 +275,13 @@ pub async fn update_location(
                 is_active: Some(location.is_active),
                 created_at: Some(location.created_at),
                 updated_at: Some(location.updated_at),
+                deleted_at: location.deleted_at,
             };
             
             match serde_json::to_string(&response_location) {
                 Ok(json) => Ok(json),
                 Err(error) => {
-                    println!("JSON serialization error: {}", error);
+                    error!(handler = "update_location", error = %error, "JSON serialization error");
                     Err(Status::InternalServerError)
                 }
             }
@@ -299,21 +290,35 @@ pub async fn update_location(
             Err(Status::NotFound)
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "update_location", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
 }
 
 #[delete("/api/admin/locations/<id>")]
-pub async fn delete_location(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
-    match crate::models::location::delete_location(id, sqlxPool.inner()).await {
-        Ok(response) => {
-            Ok(response.to_string())
+pub async fn delete_location(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, ApiError> {
+    let response = crate::models::location::delete_location(id, sqlxPool.inner()).await?;
+    if response["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&response));
+    }
+    Ok(response.to_string())
+}
+
+#[put("/api/admin/locations/<id>/restore")]
+pub async fn restore_location(id: i32, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match crate::models::location::restore_location(id, sqlxPool.inner()).await {
+        Ok(LocationRestoreResult::Restored) => {
+            Ok(json!({"success": true, "message": "Location restored successfully"}).to_string())
+        }
+        Ok(LocationRestoreResult::NotFound) => Err(Status::NotFound),
+        Ok(LocationRestoreResult::NameTaken) => {
+            warn!(handler = "restore_location", location_id = id, "an active location already uses this name");
+            Err(Status::Conflict)
         }
         Err(error) => {
-            println!("Database error: {}", error);
+            error!(handler = "restore_location", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
-}
\ No newline at end of file
+}
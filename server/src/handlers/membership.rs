@@ -1,10 +1,12 @@
 use rocket::http::Status;
 use rocket::State;
-use serde_json::{json, Value};
+use serde_json::Value;
 use sqlx::{Pool as sPool, Postgres, FromRow};
 use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
+use crate::errors::api_error::ApiError;
 use crate::models::membership;
+use tracing::error;
 
 // Using structs from model layer
 
@@ -15,8 +17,8 @@ pub async fn get_plans(sqlxPool: &State<sPool<Postgres>>) -> Result<String, Stat
         Ok(Some(plans)) => Ok(plans.to_string()),
         Ok(None) => Ok("[]".to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_plans", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
@@ -28,27 +30,25 @@ pub async fn get_user_cards(openid: String, sqlxPool: &State<sPool<Postgres>>) -
         Ok(Some(cards)) => Ok(cards.to_string()),
         Ok(None) => Ok("[]".to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_user_cards", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
 
-// 购买会员卡
+// 购买会员卡，失败时返回 {code, message, data} 和对应的状态码，如 PLAN_NOT_FOUND
 #[post("/yoga/membership/purchase?<openid>&<plan_id>&<paid_amount>")]
 pub async fn purchase_card(
     openid: String, 
     plan_id: i32, 
     paid_amount: Option<f64>,
     sqlxPool: &State<sPool<Postgres>>
-) -> Result<String, Status> {
-    match membership::purchase_membership_card(&openid, plan_id, paid_amount, sqlxPool.inner()).await {
-        Ok(result) => Ok(result.to_string()),
-        Err(error) => {
-            println!("Database error: {}", error);
-            Ok(json!({"success": false, "message": "Database error"}).to_string())
-        }
+) -> Result<String, ApiError> {
+    let result = membership::purchase_membership_card(&openid, plan_id, paid_amount, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
     }
+    Ok(result.to_string())
 }
 
 // 获取会员卡使用记录
@@ -62,8 +62,8 @@ pub async fn get_card_usage(
         Ok(Some(usage)) => Ok(usage.to_string()),
         Ok(None) => Ok("[]".to_string()),
         Err(error) => {
-            println!("Database error: {}", error);
-            Ok("[]".to_string())
+            error!(handler = "get_card_usage", error = %error, "database error");
+            Err(Status::InternalServerError)
         }
     }
 }
\ No newline at end of file
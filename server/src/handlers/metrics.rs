@@ -0,0 +1,14 @@
+use rocket::http::ContentType;
+use rocket::State;
+use sqlx::{Pool as sPool, Postgres};
+
+use crate::utils::metrics::METRICS;
+
+#[get("/metrics")]
+pub async fn metrics(sqlxPool: &State<sPool<Postgres>>) -> (ContentType, String) {
+    METRICS.observe_pool(sqlxPool.inner());
+    (
+        ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+        METRICS.render(),
+    )
+}
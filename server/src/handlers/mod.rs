@@ -3,21 +3,28 @@ pub mod admin_actions;
 pub mod admin_auth;
 pub mod admin_book;
 pub mod admin_lessons;
+pub mod admin_media;
 pub mod admin_notices;
 pub mod admin_posters;
+pub mod admin_tasks;
 pub mod admin_teachers;
 pub mod admin_user;
 pub mod admin_users;
 pub mod auth;
 pub mod booking;
+pub mod calendar;
 pub mod debug;
 pub mod favicon;
+pub mod health;
 pub mod index;
+pub mod lesson_search;
 pub mod location;
 pub mod membership;
+pub mod metrics;
 pub mod models;
+pub mod notification;
 pub mod picture;
 pub mod schedule;
 pub mod teacher;
 pub mod upload;
-pub mod user;
\ No newline at end of file
+pub mod user;pub mod v2;
@@ -0,0 +1,18 @@
+use rocket::http::Status;
+use rocket::State;
+use sqlx::{Pool as sPool, Postgres};
+use crate::models::notification;
+use tracing::error;
+
+// 获取会员的通知列表（代课、停课等）
+#[get("/yoga/notifications?<openid>")]
+pub async fn get_notifications(openid: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
+    match notification::get_user_notifications(&openid, sqlxPool.inner()).await {
+        Ok(Some(notifications)) => Ok(notifications.to_string()),
+        Ok(None) => Ok("[]".to_string()),
+        Err(error) => {
+            error!(handler = "get_notifications", error = %error, "database error");
+            Err(Status::InternalServerError)
+        }
+    }
+}
@@ -1,29 +1,119 @@
-use crate::models::settings::Settings;
-use rocket::form::{Form, FromForm};
-use rocket::fs::TempFile;
+use rocket::form::Form;
 use rocket::State;
-//use image::{GenericImage, GenericImageView, ImageBuffer, RgbImage};
-use std::path::Path;
-#[derive(FromForm)]
-pub struct Upload<'f>(TempFile<'f>);
-#[post("/yoga/picture", data = "<form>")]
+use serde_json::json;
+use sqlx::{Pool as sPool, Postgres};
+use tracing::{error, info, warn};
+
+use crate::errors::api_error::ApiError;
+use crate::handlers::upload::{save_upload, upload_response, upload_result, Pipeline, Upload, Uploader};
+use crate::models::media;
+use crate::models::settings::Settings;
+use crate::models::user::{self, AvatarUpdate};
+use crate::utils::media_store::Media;
+use crate::utils::rate_limit::RateLimit;
+
+// 小程序以 openid 识别会员，没有 openid 的上传直接拒绝
+fn member(openid: Option<String>) -> Result<Uploader, ApiError> {
+    match openid.filter(|openid| !openid.trim().is_empty()) {
+        Some(openid) => Ok(Uploader {
+            admin_id: None,
+            member_openid: Some(openid),
+        }),
+        None => Err(ApiError::Unauthorized),
+    }
+}
+
+// 会员上传图片，与 /api/upload 相同的处理，返回格式也相同
+#[post("/yoga/picture?<openid>", data = "<upload>")]
 pub async fn picture(
-    mut form: Form<Upload<'_>>,
+    openid: Option<String>,
+    _limit: RateLimit,
+    upload: Form<Upload<'_>>,
+    sqlxPool: &State<sPool<Postgres>>,
     settings: &State<Settings>,
-) -> std::io::Result<()> {
-    println!("{}", form.0.path().unwrap().to_str().unwrap());
-    //let img = image::open(form.0.path().unwrap()).unwrap();
-    //println!("{}x{} {}",img.width(),img.height(),form.0.path().unwrap().to_str().unwrap());
-    //form.upload.persist_to("/tmp/complete/file.txt").await?;
-    Ok(())
+    store: &State<Media>,
+) -> Result<String, ApiError> {
+    let uploader = member(openid)?;
+    upload_result(save_upload(&upload, Pipeline::Image, &uploader, sqlxPool, settings, store).await, store)
 }
-#[post("/yoga/avatar", data = "<form>")]
+
+// 会员头像：居中裁剪为正方形后保存，更新 users.avatar_url 并删除原来的头像。
+// 只更新已注册的会员，会员不存在时返回 404
+#[post("/yoga/avatar?<openid>", data = "<upload>")]
 pub async fn avatar(
-    mut form: Form<Upload<'_>>,
+    openid: Option<String>,
+    _limit: RateLimit,
+    upload: Form<Upload<'_>>,
+    sqlxPool: &State<sPool<Postgres>>,
     settings: &State<Settings>,
-) -> std::io::Result<String> {
-    let name = form.0.name().unwrap().to_string()+".avif";
-    let path = Path::new(settings.image_dir.as_str());
-    form.0.persist_to(path.join(name.clone())).await?;
-    Ok(name)
-}
\ No newline at end of file
+    store: &State<Media>,
+) -> Result<String, ApiError> {
+    let uploader = member(openid)?;
+    let openid = uploader.member_openid.as_deref().unwrap_or_default();
+    match user::get_user_by_openid(openid, sqlxPool.inner()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::UserNotFound),
+        Err(e) => {
+            error!(handler = "avatar", error = %e, "failed to look up member");
+            return Err(ApiError::Internal);
+        }
+    }
+    let saved = match save_upload(&upload, Pipeline::Avatar, &uploader, sqlxPool, settings, store).await {
+        Ok(saved) => saved,
+        Err(e) => return Err(e.into()),
+    };
+
+    let avatar_url = store.url(&saved.key);
+    // 上传期间会员被删除时新图片不被引用，由媒体清理回收
+    let previous = match user::set_avatar_url(openid, &avatar_url, sqlxPool.inner()).await {
+        Ok(AvatarUpdate::Updated(previous)) => previous,
+        Ok(AvatarUpdate::UserNotFound) => return Err(ApiError::UserNotFound),
+        Err(e) => {
+            error!(handler = "avatar", error = %e, "failed to update avatar_url");
+            return Err(ApiError::Internal);
+        }
+    };
+    if let Some(previous) = previous.filter(|previous| *previous != avatar_url) {
+        delete_previous_avatar(&previous, sqlxPool.inner(), store).await;
+    }
+
+    let mut response = upload_response(&saved, store);
+    response["avatar_url"] = json!(avatar_url);
+    Ok(response.to_string())
+}
+
+// 原来的头像是媒体库中的图片且已不再被引用时删除，如其他会员上传过同一张图片则保留。
+// 删除失败只记录日志，不影响新头像
+async fn delete_previous_avatar(previous: &str, sqlx_pool: &sPool<Postgres>, store: &Media) {
+    let Some(key) = media::reference_key(previous) else {
+        return;
+    };
+    let existing = match media::find_by_key(key, sqlx_pool).await {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return,
+        Err(e) => {
+            warn!(handler = "avatar", error = %e, key = %key, "failed to look up previous avatar");
+            return;
+        }
+    };
+    match media::referenced_groups(sqlx_pool).await {
+        Ok(referenced) if referenced.contains(media::media_group(&existing.key)) => return,
+        Ok(_) => {}
+        Err(e) => {
+            warn!(handler = "avatar", error = %e, "failed to collect media references");
+            return;
+        }
+    }
+
+    for key in existing.keys() {
+        if let Err(e) = store.delete(&key).await {
+            warn!(handler = "avatar", error = %e, key = %key, store = store.name(), "failed to delete previous avatar");
+            return;
+        }
+    }
+    if let Err(e) = media::delete_media_by_keys(std::slice::from_ref(&existing.key), sqlx_pool).await {
+        warn!(handler = "avatar", error = %e, key = %existing.key, "failed to delete previous avatar record");
+        return;
+    }
+    info!(key = %existing.key, "deleted previous avatar");
+}
@@ -1,112 +1,316 @@
-use chrono::{Datelike, NaiveDateTime, Utc, Weekday};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc, Weekday};
 use chrono_tz::Tz;
+use image::DynamicImage;
+use rocket::http::{Accept, ContentType, Status};
+use rocket::{FromForm, State};
 use sqlx::{Pool as sPool, Postgres};
-use image::io::Reader as ImageReader;
-use image::{DynamicImage, Rgba};
-use imageproc::drawing::draw_text_mut;
-use rocket::http::Status;
-use rocket::serde::json::serde_json;
-use rocket::serde::json::Value;
-use rocket::State;
-use rusttype::{point, Font, Rect, Scale};
 use std::io::Cursor;
-#[get("/yoga/admin/schedule")]
-pub async fn admin_schedule(sqlxPool: &State<sPool<Postgres>>) -> Result<Vec<u8>, Status> {
-    let query = "select * from fn_query_week_lessons()";
-    
-    let obj: Value = match sqlx::query_scalar::<_, serde_json::Value>(query)
-        .fetch_one(sqlxPool.inner()).await {
-        Ok(result) => result,
-        Err(error) => {
-            println!("Error: {}", error);
-            return Err(Status::InternalServerError);
+use tracing::error;
+
+use crate::models::lession::{self, Lesson, ScheduleFilter, LESSON_TYPES};
+use crate::models::settings::Settings;
+use crate::utils::content_disposition::Attachment;
+use crate::utils::icalendar::{self, Calendar, CalendarEvent};
+use crate::utils::pdf;
+use crate::utils::poster::{Poster, PosterLesson};
+
+// 每次订阅时公开课表包含的周数
+const DEFAULT_FEED_WEEKS: i64 = 4;
+const MAX_FEED_WEEKS: i64 = 12;
+
+// 课表的输出格式，由 format 参数指定，没有时按 Accept 请求头选择，都没有时为 PNG
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScheduleFormat {
+    Png,
+    Pdf,
+    Svg,
+    Ics,
+}
+
+impl ScheduleFormat {
+    const ALL: [ScheduleFormat; 4] = [ScheduleFormat::Png, ScheduleFormat::Pdf, ScheduleFormat::Svg, ScheduleFormat::Ics];
+
+    fn from_param(format: &str) -> Option<ScheduleFormat> {
+        ScheduleFormat::ALL
+            .into_iter()
+            .find(|candidate| candidate.extension().eq_ignore_ascii_case(format))
+    }
+
+    // 取权重最高的支持格式，权重相同时按请求头中的顺序
+    fn from_accept(accept: &Accept) -> Option<ScheduleFormat> {
+        let mut best: Option<(ScheduleFormat, f32)> = None;
+        for media_type in accept.iter() {
+            let weight = media_type.weight_or(1.0);
+            let format = ScheduleFormat::ALL
+                .into_iter()
+                .find(|candidate| candidate.content_type().media_type() == media_type.media_type());
+            if let Some(format) = format.filter(|_| weight > 0.0) {
+                if best.is_none_or(|(_, best)| weight > best) {
+                    best = Some((format, weight));
+                }
+            }
         }
-    };
-    let bytes = include_bytes!("pattern.png") as &[u8];
-    // https://docs.rs/rusttype/0.9.2/rusttype/enum.Font.html
-    let font = Vec::from(include_bytes!("PingFang.ttf") as &[u8]);
-    let font = Font::try_from_vec(font).unwrap();
-    // https://docs.rs/serde_json/latest/serde_json/value/enum.Value.html#method.as_array
-    let obj = obj.as_array().unwrap();
-    let mut image = ImageReader::new(Cursor::new(bytes))
-        .with_guessed_format()
-        .unwrap()
-        .decode()
-        .unwrap();
-    for index in 0..obj.len() {
-        let lesson = obj.get(index).unwrap();
-        println!("{:?}", lesson);
-        let timestamp_i64 = lesson["date_time"].as_i64().unwrap();
-        let start_time = lesson["start_time"].as_i64().unwrap();
-        let tz: Tz = "Asia/Hong_Kong".parse().unwrap();
-        // https://docs.diesel.rs/1.4.x/chrono/offset/trait.TimeZone.html#method.timestamp_millis_opt
-        if let Some(dt) = NaiveDateTime::from_timestamp_opt(timestamp_i64, 0) {
-            let utc_time = dt.and_local_timezone(Utc).unwrap();
-            // https://docs.rs/chrono/latest/chrono/struct.DateTime.html#method.with_timezone
-            let hkt = utc_time.with_timezone(&tz);
-            let dt = hkt.date_naive();
-            //if start_time == 32400 {
-            let top = start_time == 32400;
-            draw_item(&font, &mut image, &lesson, dt.weekday() as i32, top);
+        best.map(|(format, _)| format)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ScheduleFormat::Png => "png",
+            ScheduleFormat::Pdf => "pdf",
+            ScheduleFormat::Svg => "svg",
+            ScheduleFormat::Ics => "ics",
+        }
+    }
+
+    pub(crate) fn content_type(self) -> ContentType {
+        match self {
+            ScheduleFormat::Png => ContentType::PNG,
+            ScheduleFormat::Pdf => ContentType::PDF,
+            ScheduleFormat::Svg => ContentType::SVG,
+            ScheduleFormat::Ics => ContentType::new("text", "calendar").with_params(("charset", "utf-8")),
         }
     }
-    // http://127.0.0.1:8002/yoga/admin/schedule
-    let mut bytes: Vec<u8> = Vec::new();
-    let _ = image.write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png);
-    Ok(bytes)
 }
-fn draw_item(font: &Font, image: &mut DynamicImage, lesson: &Value, offset: i32, top: bool) {
-    let y_name = if top { 311 } else { 446 };
-    if top {
-        draw_text(
-            font,
-            image,
-            format!("周{}", "一二三四五六".chars().nth(offset as usize).unwrap()).as_str(),
-            36.4,
-            offset,
-            186,
-        );
+
+// 课表的查询参数：week 为 ISO 周（如 2026-W43），不填时为本周；
+// teacher_id、location_id、lesson_type 为空时不筛选；format 为 png、pdf、svg 或 ics
+#[derive(Debug, Default, FromForm)]
+pub struct ScheduleQuery<'r> {
+    pub week: Option<&'r str>,
+    pub teacher_id: Option<i32>,
+    pub location_id: Option<i32>,
+    pub lesson_type: Option<&'r str>,
+    pub format: Option<&'r str>,
+}
+
+// 一周的课表
+#[get("/yoga/admin/schedule?<query..>")]
+pub async fn admin_schedule(
+    query: ScheduleQuery<'_>,
+    accept: Option<&Accept>,
+    sqlxPool: &State<sPool<Postgres>>,
+    poster: &State<Poster>,
+    settings: &State<Settings>,
+) -> Result<Attachment, Status> {
+    let ScheduleQuery { week, teacher_id, location_id, lesson_type, format } = query;
+    let format = match format.filter(|format| !format.is_empty()) {
+        Some(format) => ScheduleFormat::from_param(format).ok_or(Status::BadRequest)?,
+        None => accept.and_then(ScheduleFormat::from_accept).unwrap_or(ScheduleFormat::Png),
+    };
+    // 周的起止按场馆时区计算，海报上的位置按海报的时区
+    let tz = settings.tz();
+    let monday = match week.filter(|week| !week.is_empty()) {
+        Some(week) => parse_iso_week(week).ok_or(Status::BadRequest)?,
+        None => current_monday(tz),
     };
-    let s = lesson["lesson_name"].as_str().unwrap();
-    let size = if s.chars().count() > 4 { 24f32 } else { 28f32 };
-    draw_text(font, image, s, size, offset, y_name);
-    draw_text(
-        font,
-        image,
-        lesson["teacher_name"].as_str().unwrap(),
-        22.4,
-        offset,
-        y_name + 32,
-    );
+    let filter = schedule_filter(teacher_id, location_id, lesson_type)?;
+
+    let lessons = load_lessons("admin_schedule", tz, monday, 1, &filter, sqlxPool.inner()).await?;
+    let filename = format!("{}.{}", file_stem(monday, &filter), format.extension());
+    let bytes = match format {
+        ScheduleFormat::Ics => lesson_calendar("课表", tz, &lessons, settings).to_ics().into_bytes(),
+        ScheduleFormat::Svg => poster
+            .render_svg(&poster_lessons(poster.tz(), lessons))
+            .map_err(|error| {
+                error!(handler = "admin_schedule", error = %error, "failed to render schedule svg");
+                Status::InternalServerError
+            })?
+            .into_bytes(),
+        ScheduleFormat::Png | ScheduleFormat::Pdf => {
+            let image = poster.render(&poster_lessons(poster.tz(), lessons));
+            if format == ScheduleFormat::Pdf {
+                pdf::image_pdf(&image, &filename)
+            } else {
+                let mut bytes: Vec<u8> = Vec::new();
+                if let Err(error) = DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png) {
+                    error!(handler = "admin_schedule", error = %error, "failed to encode schedule poster");
+                    return Err(Status::InternalServerError);
+                }
+                bytes
+            }
+        }
+    };
+    Ok(Attachment::new(bytes, format.content_type(), filename))
+}
+
+// 公开课表的 iCalendar 订阅，从本周一开始共 weeks 周（默认 4，最多 12）
+#[get("/yoga/schedule.ics?<weeks>&<teacher_id>&<location_id>&<lesson_type>")]
+pub async fn schedule_feed(
+    weeks: Option<i64>,
+    teacher_id: Option<i32>,
+    location_id: Option<i32>,
+    lesson_type: Option<&str>,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> Result<(ContentType, String), Status> {
+    let tz = settings.tz();
+    let weeks = weeks.unwrap_or(DEFAULT_FEED_WEEKS).clamp(1, MAX_FEED_WEEKS);
+    let filter = schedule_filter(teacher_id, location_id, lesson_type)?;
+    let lessons = load_lessons("schedule_feed", tz, current_monday(tz), weeks, &filter, sqlxPool.inner()).await?;
+    Ok((
+        ScheduleFormat::Ics.content_type(),
+        lesson_calendar("课表", tz, &lessons, settings).to_ics(),
+    ))
+}
+
+fn schedule_filter(teacher_id: Option<i32>, location_id: Option<i32>, lesson_type: Option<&str>) -> Result<ScheduleFilter, Status> {
+    let lesson_type = lesson_type.filter(|lesson_type| !lesson_type.is_empty());
+    if lesson_type.is_some_and(|lesson_type| !LESSON_TYPES.contains(&lesson_type)) {
+        return Err(Status::BadRequest);
+    }
+    Ok(ScheduleFilter {
+        teacher_id,
+        location_id,
+        lesson_type: lesson_type.map(str::to_string),
+    })
+}
+
+async fn load_lessons(
+    handler: &str,
+    tz: Tz,
+    monday: NaiveDate,
+    weeks: i64,
+    filter: &ScheduleFilter,
+    sqlx_pool: &sPool<Postgres>,
+) -> Result<Vec<Lesson>, Status> {
+    let (start, _) = week_bounds(tz, monday);
+    let (_, end) = week_bounds(tz, monday + Duration::weeks(weeks - 1));
+    lession::list_schedule_lessons(start, end, filter, sqlx_pool)
+        .await
+        .map_err(|error| {
+            error!(handler = handler, error = %error, "database error");
+            Status::InternalServerError
+        })
+}
+
+fn poster_lessons(tz: Tz, lessons: Vec<Lesson>) -> Vec<PosterLesson> {
+    lessons
+        .into_iter()
+        .map(|lesson| {
+            let start = lesson.start_time.with_timezone(&tz);
+            PosterLesson {
+                weekday: start.weekday(),
+                start_secs: start.num_seconds_from_midnight(),
+                title: lesson.title,
+                subtitle: lesson.teacher.name,
+            }
+        })
+        .collect()
 }
-// 24f32,0, 446,
-// 22.4,0,478
-fn draw_text(font: &Font, image: &mut DynamicImage, text: &str, font_size: f32, x: i32, y: i32) {
-    let scale = Scale {
-        x: font_size,
-        y: font_size,
+
+fn lesson_calendar(name: &str, tz: Tz, lessons: &[Lesson], settings: &Settings) -> Calendar {
+    let server_url = settings.server_url();
+    let domain = icalendar::uid_domain(&server_url);
+    Calendar {
+        name: name.to_string(),
+        timezone: tz.name().to_string(),
+        refresh_minutes: Some(settings.calendar.refresh_minutes),
+        events: lessons
+            .iter()
+            .map(|lesson| CalendarEvent {
+                uid: format!("lesson-{}@{}", lesson.id, domain),
+                start: lesson.start_time,
+                end: lesson.end_time,
+                summary: lesson.title.clone(),
+                location: Some(lesson.location.name.clone()),
+                description: Some(lesson.teacher.name.clone())
+                    .filter(|name| !name.is_empty())
+                    .map(|name| format!("老师：{}", name)),
+                updated_at: Some(lesson.updated_at),
+            })
+            .collect(),
+    }
+}
+
+fn current_monday(tz: Tz) -> NaiveDate {
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    today - Duration::days(today.weekday().num_days_from_monday() as i64)
+}
+
+// 2026-W43 对应的周一，周数超出当年范围时返回 None
+fn parse_iso_week(week: &str) -> Option<NaiveDate> {
+    let (year, week) = week.split_once(['W', 'w'])?;
+    let year: i32 = year.strip_suffix('-')?.parse().ok()?;
+    let week: u32 = week.parse().ok()?;
+    NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+}
+
+// monday 当天零点到下周一零点，按 tz 换算为 UTC。夏令时切换使零点不存在时取之后最早的时刻
+fn week_bounds(tz: Tz, monday: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let midnight = |date: NaiveDate| {
+        let naive = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        (0..24)
+            .find_map(|hour| tz.from_local_datetime(&(naive + Duration::hours(hour))).earliest())
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
     };
-    // https://docs.rs/rusttype/latest/rusttype/struct.VMetrics.html
-    let point = point(0.0, font.v_metrics(scale).ascent);
-    let glyphs: Vec<Rect<i32>> = font
-        .layout(text, scale, point)
-        .map(|g| g.pixel_bounding_box().unwrap())
-        .collect();
-    let first = glyphs.first().unwrap().min;
-    let last = glyphs.last().unwrap().max;
-    let min_y = glyphs.iter().map(|g| g.min.y).min().unwrap();
-    let max_y = glyphs.iter().map(|g| g.max.y).max().unwrap();
-    let height = max_y - min_y;
-    let width = last.x - first.x;
-    // https://docs.rs/imageproc/latest/imageproc/drawing/fn.draw_text_mut.html
-    // https://github.com/search?q=draw_text_mut%20path%3A*.rs&type=code
-    draw_text_mut(
-        image,
-        Rgba([255u8, 255u8, 255u8, 255u8]),
-        202i32 + 135 * x + (100 - width) / 2,
-        y,
-        scale,
-        &font,
-        text,
-    );
-}
\ No newline at end of file
+    (midnight(monday), midnight(monday + Duration::days(7)))
+}
+
+// 下载的文件名：schedule-2026-W43-2026-10-19，带上筛选条件
+fn file_stem(monday: NaiveDate, filter: &ScheduleFilter) -> String {
+    let week = monday.iso_week();
+    let mut stem = format!("schedule-{}-W{:02}-{}", week.year(), week.week(), monday.format("%Y-%m-%d"));
+    if let Some(teacher_id) = filter.teacher_id {
+        stem.push_str(&format!("-teacher-{}", teacher_id));
+    }
+    if let Some(location_id) = filter.location_id {
+        stem.push_str(&format!("-location-{}", location_id));
+    }
+    if let Some(lesson_type) = &filter.lesson_type {
+        stem.push_str(&format!("-{}", lesson_type));
+    }
+    stem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        assert_eq!(ScheduleFormat::from_param("PDF"), Some(ScheduleFormat::Pdf));
+        assert_eq!(ScheduleFormat::from_param("jpg"), None);
+        let accept = |header: &str| ScheduleFormat::from_accept(&header.parse::<Accept>().unwrap());
+        assert_eq!(accept("application/pdf"), Some(ScheduleFormat::Pdf));
+        assert_eq!(accept("text/html, image/svg+xml;q=0.9, image/png;q=0.5"), Some(ScheduleFormat::Svg));
+        assert_eq!(accept("image/png;q=0.4, text/calendar"), Some(ScheduleFormat::Ics));
+        assert_eq!(accept("text/html, */*;q=0.8"), None);
+        assert_eq!(accept("application/pdf;q=0"), None);
+    }
+
+    #[test]
+    fn iso_weeks() {
+        assert_eq!(parse_iso_week("2026-W43"), NaiveDate::from_ymd_opt(2026, 10, 19));
+        assert_eq!(parse_iso_week("2026-w01"), NaiveDate::from_ymd_opt(2025, 12, 29));
+        assert_eq!(parse_iso_week("2020-W53"), NaiveDate::from_ymd_opt(2020, 12, 28));
+        assert_eq!(parse_iso_week("2026-W53"), NaiveDate::from_ymd_opt(2026, 12, 28));
+        assert_eq!(parse_iso_week("2025-W53"), None);
+        assert_eq!(parse_iso_week("2026-W00"), None);
+        assert_eq!(parse_iso_week("2026W43"), None);
+        assert_eq!(parse_iso_week("2026-10-19"), None);
+    }
+
+    #[test]
+    fn week_bounds_follow_time_zone() {
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let (start, end) = week_bounds(chrono_tz::Asia::Hong_Kong, monday);
+        assert_eq!(start.to_rfc3339(), "2026-10-18T16:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2026-10-25T16:00:00+00:00");
+        // 伦敦 10 月 25 日结束夏令时，这一周比 7 天多一小时
+        let (start, end) = week_bounds(chrono_tz::Europe::London, monday);
+        assert_eq!(end - start, Duration::hours(7 * 24 + 1));
+    }
+
+    #[test]
+    fn file_stems() {
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        assert_eq!(file_stem(monday, &ScheduleFilter::default()), "schedule-2026-W43-2026-10-19");
+        let filter = ScheduleFilter {
+            teacher_id: Some(3),
+            location_id: None,
+            lesson_type: Some("team".to_string()),
+        };
+        assert_eq!(file_stem(monday, &filter), "schedule-2026-W43-2026-10-19-teacher-3-team");
+    }
+}
@@ -1,7 +1,9 @@
 use sqlx::{Pool as sPool, Postgres};
 use rocket::http::Status;
 use rocket::State;
+use crate::models::settings::Settings;
 use crate::models::teacher;
+use tracing::error;
 
 #[get("/yoga/teacher/lessons?<start_time>&<end_time>&<open_id>&<class_type>&<teacher_id>")]
 pub async fn teacher_lessons(
@@ -11,11 +13,12 @@ pub async fn teacher_lessons(
     class_type: i32,
     teacher_id: i32,
     sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
 ) -> Result<String, Status> {
-    match teacher::get_teacher_lessons(start_time, end_time, open_id, class_type, teacher_id, sqlxPool.inner()).await {
+    match teacher::get_teacher_lessons(start_time, end_time, open_id, class_type, teacher_id, settings.tz().name(), sqlxPool.inner()).await {
         Ok(result) => Ok(result.to_string()),
         Err(error) => {
-            println!("Error: {}", error);
+            error!(handler = "teacher_lessons", error = %error, "database error");
             Err(Status::InternalServerError)
         }
     }
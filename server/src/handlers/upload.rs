@@ -1,97 +1,358 @@
+use std::convert::Infallible;
+
 use rocket::data::ToByteUnit;
 use rocket::http::Status;
-use rocket::{get, post, State};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::Redirect;
+use rocket::{delete, get, post, State};
 use rocket::form::{Form, FromForm};
-use rocket::fs::{TempFile, NamedFile};
-use serde_json::json;
-use std::fs;
-use std::path::Path;
+use rocket::fs::TempFile;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
 use uuid::Uuid;
+use crate::errors::api_error::ApiError;
+use crate::models::media::{self, MediaModel, NewMedia};
 use crate::models::settings::Settings;
+use crate::utils::http_cache::{self, CachedObject, IfNoneMatch};
+use crate::utils::request_log;
+use crate::utils::rate_limit::RateLimit;
+use crate::utils::images::{self, ImageError};
+use crate::utils::media_store::{self, Media};
+use tracing::{debug, error, warn};
 
 #[derive(FromForm)]
 pub struct Upload<'f> {
-    file: TempFile<'f>,
+    pub(crate) file: TempFile<'f>,
+}
+
+// 上传者只用于媒体库记录：会员以 openid 参数识别，管理员以 token 识别
+pub struct Uploader {
+    pub(crate) admin_id: Option<i32>,
+    pub(crate) member_openid: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Uploader {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        Outcome::Success(Uploader {
+            admin_id: request_log::admin(request),
+            member_openid: request_log::member(request).filter(|openid| !openid.is_empty()),
+        })
+    }
+}
+
+// 图片保留原始比例，头像裁剪为正方形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pipeline {
+    Image,
+    Avatar,
+}
+
+pub(crate) enum SaveError {
+    // 不是支持的图片、像素过多或文件损坏
+    Rejected(ImageError),
+    Internal,
 }
 
 #[post("/api/upload", data = "<upload>")]
 pub async fn upload_file(
-    mut upload: Form<Upload<'_>>,
+    _limit: RateLimit,
+    uploader: Uploader,
+    upload: Form<Upload<'_>>,
+    sqlxPool: &State<Pool<Postgres>>,
     settings: &State<Settings>,
-) -> Result<String, Status> {
-    let file = &mut upload.file;
-    
-    // Check if file has a content type
-    let content_type = match file.content_type() {
-        Some(ct) => ct,
-        None => {
-            return Ok(json!({
-                "error": "No content type provided"
-            }).to_string());
+    store: &State<Media>,
+) -> Result<String, ApiError> {
+    upload_result(save_upload(&upload, Pipeline::Image, &uploader, sqlxPool, settings, store).await, store)
+}
+
+// 不支持的格式返回 415，文件损坏或像素过多返回 422
+pub(crate) fn upload_result(result: Result<MediaModel, SaveError>, store: &Media) -> Result<String, ApiError> {
+    match result {
+        Ok(media) => Ok(upload_response(&media, store).to_string()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+impl From<SaveError> for ApiError {
+    fn from(error: SaveError) -> Self {
+        match error {
+            SaveError::Rejected(error @ ImageError::Unsupported) => ApiError::UnsupportedImage(error.message()),
+            SaveError::Rejected(error @ (ImageError::Corrupt(_) | ImageError::TooManyPixels { .. })) => {
+                ApiError::InvalidImage(error.message())
+            }
+            SaveError::Rejected(ImageError::Encode(_)) | SaveError::Internal => ApiError::Internal,
         }
+    }
+}
+
+// 处理上传的图片并保存原图和缩略图，同样内容的文件已经上传过时返回已有的记录
+pub(crate) async fn save_upload(
+    upload: &Upload<'_>,
+    pipeline: Pipeline,
+    uploader: &Uploader,
+    sqlxPool: &Pool<Postgres>,
+    settings: &Settings,
+    store: &Media,
+) -> Result<MediaModel, SaveError> {
+    let file = &upload.file;
+    debug!(content_type = ?file.content_type(), pipeline = ?pipeline, "upload received");
+
+    // 小文件由 Rocket 缓存在内存中，大文件写在临时目录
+    let bytes = match (file, file.path()) {
+        (TempFile::Buffered { content }, _) => content.as_bytes().to_vec(),
+        (_, Some(path)) => match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!(handler = "upload_file", error = ?e, "failed to read uploaded file");
+                return Err(SaveError::Internal);
+            }
+        },
+        (_, None) => return Err(SaveError::Internal),
     };
-    
-    println!("file content_type: {}", content_type);
-    
-    // Check if content type is an image
-    if content_type.top() != "image" {
-        return Ok(json!({
-            "error": "Only image files are allowed (JPEG, PNG, GIF, WebP)"
-        }).to_string());
-    }
-
-    // Generate unique filename
-    let file_extension = match content_type.sub().as_str() {
-        "jpeg" => "jpg",
-        "png" => "png", 
-        "gif" => "gif",
-        "webp" => "webp",
-        _ => "jpg", // default fallback
+
+    // 同一文件作为头像上传时裁剪结果不同，哈希中加上前缀与普通图片区分
+    let mut hasher = Sha256::new();
+    if pipeline == Pipeline::Avatar {
+        hasher.update(b"avatar:");
+    }
+    hasher.update(&bytes);
+    let sha256 = hex::encode(hasher.finalize());
+    match media::find_by_sha256(&sha256, sqlxPool).await {
+        Ok(Some(existing)) => {
+            debug!(handler = "upload_file", key = %existing.key, "duplicate upload, returning existing media");
+            return Ok(existing);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!(handler = "upload_file", error = %e, "failed to look up media by hash");
+            return Err(SaveError::Internal);
+        }
+    }
+    let original_filename = file
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str().chars().take(255).collect::<String>());
+
+    // 识别格式、解码、生成缩略图比较耗 CPU，放到阻塞线程中执行
+    let max_pixels = settings.storage.max_image_pixels;
+    let thumbnail_sizes = settings.storage.thumbnail_sizes.clone();
+    let avatar_size = settings.storage.avatar_size;
+    let processed = tokio::task::spawn_blocking(move || match pipeline {
+        Pipeline::Image => images::process_upload(&bytes, max_pixels, &thumbnail_sizes),
+        Pipeline::Avatar => images::process_avatar(&bytes, max_pixels, avatar_size, &thumbnail_sizes),
+    })
+    .await;
+    let images = match processed {
+        Ok(Ok(images)) => images,
+        Ok(Err(e)) => {
+            warn!(handler = "upload_file", error = %e, "rejected uploaded image");
+            return Err(SaveError::Rejected(e));
+        }
+        Err(e) => {
+            error!(handler = "upload_file", error = %e, "image processing task failed");
+            return Err(SaveError::Internal);
+        }
     };
-    
-    let filename = format!("{}.{}", Uuid::new_v4(), file_extension);
-    let upload_dir = &settings.image_dir;
-    
-    // Ensure upload directory exists
-    if let Err(_) = fs::create_dir_all(upload_dir) {
-        println!("Failed to create upload directory: {}", upload_dir);
-        return Err(Status::InternalServerError);
+
+    // 原图为 <id>.<ext>，缩略图为 <id>_200.jpg、<id>_200.webp 这样
+    let id = Uuid::new_v4();
+    let mut variants = serde_json::Map::new();
+    let mut stored_keys = Vec::new();
+    for image in images {
+        let filename = if image.name == "original" {
+            format!("{}.{}", id, image.format.extension())
+        } else {
+            format!("{}_{}.{}", id, image.name.trim_end_matches("_webp"), image.format.extension())
+        };
+        let size = image.bytes.len();
+        if let Err(e) = store.put(&filename, image.bytes, &image.format.content_type()).await {
+            error!(handler = "upload_file", error = %e, filename = %filename, store = store.name(), "failed to save uploaded file");
+            delete_keys(&stored_keys, store).await;
+            return Err(SaveError::Internal);
+        }
+        stored_keys.push(filename.clone());
+        variants.insert(image.name.clone(), json!({
+            "key": filename,
+            "width": image.width,
+            "height": image.height,
+            "content_type": image.format.content_type().to_string(),
+            "size": size
+        }));
     }
-    
-    let file_path = Path::new(upload_dir).join(&filename);
-    
-    // Move the temporary file to the target location
-    match file.persist_to(&file_path).await {
-        Ok(_) => {
-            // Return the URL path for accessing uploaded images            
-            let url = format!("{}/api/images/{}", settings.server_url(),filename);
-            Ok(json!({
-                "success": true,
-                "url": url,
-                "filename": filename
-            }).to_string())
+
+    let original = &variants["original"];
+    let new_media = NewMedia {
+        key: &stored_keys[0],
+        sha256: &sha256,
+        content_type: original["content_type"].as_str().unwrap_or_default(),
+        size_bytes: original["size"].as_i64().unwrap_or_default(),
+        width: original["width"].as_i64().unwrap_or_default() as i32,
+        height: original["height"].as_i64().unwrap_or_default() as i32,
+        variants: Value::Object(variants.clone()),
+        original_filename: original_filename.as_deref(),
+        admin_id: uploader.admin_id,
+        member_openid: uploader.member_openid.as_deref(),
+    };
+    match media::insert_media(new_media, sqlxPool).await {
+        Ok(Some(media)) => Ok(media),
+        // 同一文件同时上传了两次，保留先写入的记录
+        Ok(None) => {
+            delete_keys(&stored_keys, store).await;
+            match media::find_by_sha256(&sha256, sqlxPool).await {
+                Ok(Some(existing)) => Ok(existing),
+                Ok(None) => Err(SaveError::Internal),
+                Err(e) => {
+                    error!(handler = "upload_file", error = %e, "failed to look up media by hash");
+                    Err(SaveError::Internal)
+                }
+            }
         }
         Err(e) => {
-            println!("Failed to save file: {:?}", e);
-            Err(Status::InternalServerError)
+            error!(handler = "upload_file", error = %e, "failed to record uploaded media");
+            delete_keys(&stored_keys, store).await;
+            Err(SaveError::Internal)
+        }
+    }
+}
+
+// 上传成功后的响应，媒体库列表也使用同样的格式
+pub(crate) fn upload_response(media: &MediaModel, store: &Media) -> Value {
+    let mut variants = serde_json::Map::new();
+    if let Value::Object(stored) = &media.variants {
+        for (name, variant) in stored {
+            let key = variant["key"].as_str().unwrap_or_default();
+            variants.insert(name.clone(), json!({
+                "url": store.url(key),
+                "filename": key,
+                "width": variant["width"],
+                "height": variant["height"],
+                "content_type": variant["content_type"],
+                "size": variant["size"]
+            }));
+        }
+    }
+    json!({
+        "success": true,
+        "url": store.url(&media.key),
+        "filename": media.key,
+        "width": media.width,
+        "height": media.height,
+        "variants": variants
+    })
+}
+
+// 清理上传失败时已经写入的文件，失败只记录日志
+async fn delete_keys(keys: &[String], store: &Media) {
+    for key in keys {
+        if let Err(e) = store.delete(key).await {
+            warn!(error = %e, key = %key, store = store.name(), "failed to clean up stored file");
         }
     }
 }
 
 #[post("/api/admin/upload", data = "<upload>")]
 pub async fn admin_upload_file(
+    uploader: Uploader,
     upload: Form<Upload<'_>>,
+    sqlxPool: &State<Pool<Postgres>>,
     settings: &State<Settings>,
-) -> Result<String, Status> {
+    store: &State<Media>,
+) -> Result<String, ApiError> {
     // Same implementation as upload_file but for admin routes
-    upload_file(upload, settings).await
+    upload_result(save_upload(&upload, Pipeline::Image, &uploader, sqlxPool, settings, store).await, store)
+}
+
+#[derive(Responder)]
+pub enum ImageResponse {
+    Redirect(Redirect),
+    Object(CachedObject),
 }
 
+// 存储提供签名地址时跳转过去，否则读取后直接返回，支持 ETag、Last-Modified 和 Range。
+// 媒体库中的图片以记录中的哈希作为 ETag，If-None-Match 匹配时不读取文件直接返回 304
 #[get("/api/images/<filename>")]
 pub async fn serve_image(
     filename: &str,
-    settings: &State<Settings>,
-) -> Option<NamedFile> {
-    let file_path = Path::new(&settings.image_dir).join(filename);
-    NamedFile::open(file_path).await.ok()
-}
\ No newline at end of file
+    if_none_match: IfNoneMatch,
+    sqlxPool: &State<Pool<Postgres>>,
+    store: &State<Media>,
+) -> Result<ImageResponse, Status> {
+    // 只接受上传时生成的文件名，拒绝 ../ 等路径
+    if !media_store::is_valid_key(filename) {
+        return Err(Status::BadRequest);
+    }
+    if let Some(url) = store.signed_url(filename) {
+        return Ok(ImageResponse::Redirect(Redirect::temporary(url)));
+    }
+    let immutable = media_store::is_immutable_key(filename);
+
+    // 查不到记录时按文件内容计算 ETag，不影响返回图片
+    let media = match media::find_by_stored_key(filename, sqlxPool).await {
+        Ok(media) => media,
+        Err(e) => {
+            warn!(handler = "serve_image", error = %e, filename = %filename, "failed to look up media record");
+            None
+        }
+    };
+    let known = media
+        .as_ref()
+        .and_then(|media| Some((media.etag(filename)?, media.created_at)));
+    if let Some((etag, created_at)) = &known {
+        if if_none_match.matches(etag) {
+            return Ok(ImageResponse::Object(CachedObject::not_modified(etag.clone(), Some(*created_at), immutable)));
+        }
+    }
+
+    match store.get(filename).await {
+        Ok(Some(object)) => {
+            let (etag, modified_at) = match known {
+                Some((etag, created_at)) => (etag, Some(created_at)),
+                None => (http_cache::strong_etag(&object.bytes), object.modified_at),
+            };
+            Ok(ImageResponse::Object(CachedObject::new(object.bytes, object.content_type, etag, modified_at, immutable)))
+        }
+        Ok(None) => Err(Status::NotFound),
+        Err(e) => {
+            error!(handler = "serve_image", error = %e, filename = %filename, store = store.name(), "failed to read image");
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+// 删除上传的图片及其缩略图。缩略图以媒体库记录为准，没有记录的文件只删除文件本身
+#[delete("/api/admin/images/<filename>")]
+pub async fn delete_image(
+    filename: &str,
+    sqlxPool: &State<Pool<Postgres>>,
+    store: &State<Media>,
+) -> Result<String, Status> {
+    if !media_store::is_valid_key(filename) {
+        return Err(Status::BadRequest);
+    }
+    let keys = match media::find_by_key(filename, sqlxPool).await {
+        Ok(Some(existing)) => existing.keys(),
+        Ok(None) => vec![filename.to_string()],
+        Err(e) => {
+            error!(handler = "delete_image", error = %e, key = %filename, "failed to look up media record");
+            return Err(Status::InternalServerError);
+        }
+    };
+    for key in &keys {
+        if let Err(e) = store.delete(key).await {
+            error!(handler = "delete_image", error = %e, key = %key, store = store.name(), "failed to delete image");
+            return Err(Status::InternalServerError);
+        }
+    }
+    if let Err(e) = media::delete_media_by_keys(&keys[..1], sqlxPool).await {
+        error!(handler = "delete_image", error = %e, key = %filename, "failed to delete media record");
+        return Err(Status::InternalServerError);
+    }
+    Ok(json!({
+        "success": true,
+        "deleted": keys
+    }).to_string())
+}
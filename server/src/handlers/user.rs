@@ -3,6 +3,7 @@ use rocket::State;
 use serde_json::{json, Value};
 use sqlx::{Pool as sPool, Postgres};
 use crate::models::user;
+use tracing::{error, warn};
 
 #[get("/yoga/user/query?<openid>")]
 pub async fn user_query(openid: String, sqlxPool: &State<sPool<Postgres>>) -> Result<String, Status> {
@@ -10,7 +11,7 @@ pub async fn user_query(openid: String, sqlxPool: &State<sPool<Postgres>>) -> Re
         Ok(Some(result)) => Ok(result.to_string()),
         Ok(None) => Ok("null".to_string()),
         Err(error) => {
-            println!("Error querying user: {}", error);
+            error!(handler = "user_query", error = %error, "error querying user");
             Err(Status::NoContent)
         }
     }
@@ -20,7 +21,7 @@ pub async fn register_user(data: String, sqlxPool: &State<sPool<Postgres>>) -> R
     let json_data: Value = match serde_json::from_str(&data) {
         Ok(v) => v,
         Err(_) => {
-            println!("Invalid JSON data: {}", data);
+            warn!(handler = "register_user", data = %data, "invalid JSON data");
             return Err(Status::BadRequest);
         }
     };
@@ -28,7 +29,7 @@ pub async fn register_user(data: String, sqlxPool: &State<sPool<Postgres>>) -> R
     match user::create_or_update_user(json_data, sqlxPool.inner()).await {
         Ok(user_id) => Ok(user_id.to_string()),
         Err(error) => {
-            println!("Error updating user: {}", error);
+            error!(handler = "register_user", error = %error, "error updating user");
             Err(Status::InternalServerError)
         }
     }
@@ -50,7 +51,7 @@ pub async fn user_book_statistics(id: String, sqlxPool: &State<sPool<Postgres>>)
             Ok(empty_stats.to_string())
         }
         Err(error) => {
-            println!("Error querying user statistics: {}", error);
+            error!(handler = "user_book_statistics", error = %error, "error querying user statistics");
             Err(Status::InternalServerError)
         }
     }
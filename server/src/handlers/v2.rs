@@ -0,0 +1,151 @@
+use crate::errors::api_error::{ApiError, ApiResponse, ApiResult};
+use crate::handlers::lesson_search::LessonSearchQuery;
+use crate::models::index as index_model;
+use crate::models::settings::Settings;
+use crate::models::{action_button, booking, lesson_search, location, membership, notification, teacher, user};
+use crate::utils::rate_limit::RateLimit;
+use chrono::Utc;
+use rocket::State;
+use serde_json::{json, Value};
+use sqlx::{Pool as sPool, Postgres};
+
+// 小程序使用的接口，挂载在 /api/v2 下，统一返回 {code, message, data}。
+// /yoga/... 下的旧接口保持原样，等小程序迁移完成后再下线。
+
+#[get("/index?<openid>")]
+pub async fn index(openid: Option<String>, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    Ok(ApiResponse(index_model::get_index_data(openid, sqlxPool.inner()).await?))
+}
+
+#[get("/lessons?<start>&<openid>&<class_type>")]
+pub async fn lessons(
+    start: i32,
+    openid: String,
+    class_type: i32,
+    sqlxPool: &State<sPool<Postgres>>,
+) -> ApiResult {
+    let lessons = booking::get_lessons_with_booking_status(start, &openid, class_type, sqlxPool.inner()).await?;
+    Ok(ApiResponse(lessons.unwrap_or_else(|| json!([]))))
+}
+
+// 参数说明见 handlers/lesson_search.rs，不合法时返回 INVALID_PARAMETER
+#[get("/lessons/search?<query..>")]
+pub async fn search_lessons(
+    query: LessonSearchQuery,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> ApiResult {
+    let search = query.parse(Utc::now()).map_err(ApiError::InvalidParameter)?;
+    let (lessons, next_cursor) = lesson_search::search_lessons(&search, settings.tz().name(), sqlxPool.inner()).await?;
+    Ok(ApiResponse(json!({ "lessons": lessons, "next_cursor": next_cursor })))
+}
+
+#[post("/bookings?<lesson_id>&<openid>")]
+pub async fn book(lesson_id: i32, openid: String, _limit: RateLimit, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let result = booking::create_booking(lesson_id, &openid, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
+    }
+    Ok(ApiResponse(json!({ "booking_id": result["booking_id"] })))
+}
+
+#[delete("/bookings/<id>?<openid>")]
+pub async fn unbook(id: i32, openid: String, sqlxPool: &State<sPool<Postgres>>, settings: &State<Settings>) -> ApiResult {
+    let result = booking::cancel_booking(id, &openid, settings.calendar.cancel_cutoff_minutes, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
+    }
+    Ok(ApiResponse(json!({ "cancelled_id": result["cancelled_id"] })))
+}
+
+#[get("/teacher/lessons?<start_time>&<end_time>&<open_id>&<class_type>&<teacher_id>")]
+pub async fn teacher_lessons(
+    start_time: i32,
+    end_time: i32,
+    open_id: String,
+    class_type: i32,
+    teacher_id: i32,
+    sqlxPool: &State<sPool<Postgres>>,
+    settings: &State<Settings>,
+) -> ApiResult {
+    let lessons = teacher::get_teacher_lessons(start_time, end_time, open_id, class_type, teacher_id, settings.tz().name(), sqlxPool.inner()).await?;
+    Ok(ApiResponse(lessons))
+}
+
+#[get("/membership/plans")]
+pub async fn membership_plans(sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let plans = membership::get_membership_plans(sqlxPool.inner()).await?;
+    Ok(ApiResponse(plans.unwrap_or_else(|| json!([]))))
+}
+
+#[get("/membership/cards?<openid>")]
+pub async fn membership_cards(openid: String, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let cards = membership::get_user_membership_cards(&openid, sqlxPool.inner()).await?;
+    Ok(ApiResponse(cards.unwrap_or_else(|| json!([]))))
+}
+
+#[post("/membership/purchase?<openid>&<plan_id>&<paid_amount>")]
+pub async fn purchase_card(
+    openid: String,
+    plan_id: i32,
+    paid_amount: Option<f64>,
+    sqlxPool: &State<sPool<Postgres>>,
+) -> ApiResult {
+    if paid_amount.is_some_and(|amount| amount < 0.0) {
+        return Err(ApiError::InvalidParameter("paid_amount 不能为负数".to_string()));
+    }
+    let result = membership::purchase_membership_card(&openid, plan_id, paid_amount, sqlxPool.inner()).await?;
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::from_failure(&result));
+    }
+    Ok(ApiResponse(json!({
+        "card_id": result["card_id"],
+        "card_number": result["card_number"]
+    })))
+}
+
+#[get("/membership/usage?<openid>&<card_id>")]
+pub async fn card_usage(openid: String, card_id: Option<i32>, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let usage = membership::get_card_usage(&openid, card_id, sqlxPool.inner()).await?;
+    Ok(ApiResponse(usage.unwrap_or_else(|| json!([]))))
+}
+
+#[get("/notifications?<openid>")]
+pub async fn notifications(openid: String, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let notifications = notification::get_user_notifications(&openid, sqlxPool.inner()).await?;
+    Ok(ApiResponse(notifications.unwrap_or_else(|| json!([]))))
+}
+
+#[get("/locations")]
+pub async fn locations(sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    Ok(ApiResponse(location::get_all_locations(sqlxPool.inner()).await?))
+}
+
+#[get("/action-buttons")]
+pub async fn action_buttons(sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    Ok(ApiResponse(action_button::get_active_action_buttons(sqlxPool.inner()).await?))
+}
+
+#[get("/user?<openid>")]
+pub async fn user_query(openid: String, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    match user::get_user_by_openid(&openid, sqlxPool.inner()).await? {
+        Some(user) => Ok(ApiResponse(user)),
+        None => Err(ApiError::UserNotFound),
+    }
+}
+
+#[post("/user", data = "<data>")]
+pub async fn register_user(data: String, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    let json_data: Value = serde_json::from_str(&data)
+        .map_err(|_| ApiError::BadRequest("用户信息不是合法的 JSON".to_string()))?;
+    let user_id = user::create_or_update_user(json_data, sqlxPool.inner()).await?;
+    Ok(ApiResponse(json!({ "id": user_id })))
+}
+
+#[get("/user/statistics?<openid>")]
+pub async fn user_statistics(openid: String, sqlxPool: &State<sPool<Postgres>>) -> ApiResult {
+    match user::get_user_booking_statistics(&openid, sqlxPool.inner()).await? {
+        Some(statistics) => Ok(ApiResponse(statistics)),
+        None => Err(ApiError::UserNotFound),
+    }
+}
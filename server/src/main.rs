@@ -6,13 +6,17 @@ mod utils;
 use std::env;
 use dotenv::dotenv;
 
-use deadpool_postgres::{ManagerConfig, Runtime};
-use models::settings::Settings;
+use models::settings::{CommandLine, Settings};
 use rocket::data::{Limits, ToByteUnit};
 use rocket::figment::Figment;
-use sqlx::postgres::PgPoolOptions;
-use tokio_postgres::NoTls;
-use crate::utils::content_disposition::ContentDisposition;
+use crate::utils::client_real_addr::TrustedProxies;
+use crate::utils::cors::CORS;
+use crate::utils::metrics::MetricsFairing;
+use crate::utils::request_log::{self, RequestLogger};
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::supervisor::Supervisor;
+use models::settings::RateLimitBackend;
+use std::time::Duration;
 
 #[macro_use]
 extern crate rocket;
@@ -21,49 +25,131 @@ extern crate rocket;
 async fn main() -> Result<(), rocket::Error> {
     // 加载 .env 文件
     dotenv().ok();
-    let yaml_path = "config.yml";
-    let settings = Settings::new(yaml_path).expect("Failed to load settings from config.yml");
-    
+    let command_line = match CommandLine::parse(env::args().skip(1)) {
+        Ok(command_line) => command_line,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("Usage: YogaServer [migrate] [--config <path>] [--<section>.<key>=<value>]...");
+            std::process::exit(2);
+        }
+    };
+    if let Some(command) = command_line.command.as_deref().filter(|c| *c != "migrate") {
+        eprintln!("Unknown command \"{}\"", command);
+        std::process::exit(2);
+    }
+    let settings = match Settings::load(&command_line) {
+        Ok(settings) => settings,
+        Err(errors) => {
+            eprintln!("Invalid configuration:");
+            for error in errors {
+                eprintln!("  - {}", error);
+            }
+            std::process::exit(1);
+        }
+    };
+    utils::logging::init(&settings.logging);
+
     // 配置 PostgreSQL 数据库
-    let url= env::var("DB_URL").expect("DB_URL required");
-    let pool = PgPoolOptions::new().max_connections(10).connect(&url).await.expect("connect db failed");
+    let pool = match utils::database::connect(&settings.database).await {
+        Ok(pool) => pool,
+        Err(message) => {
+            tracing::error!(error = %message, "database configuration error");
+            std::process::exit(1);
+        }
+    };
+
+    // `YogaServer migrate` 只执行数据库迁移后退出
+    if command_line.command.as_deref() == Some("migrate") || settings.database.auto_migrate {
+        if let Err(error) = utils::migrations::run(&pool).await {
+            tracing::error!(error = %error, "failed to run database migrations");
+            std::process::exit(1);
+        }
+    }
+    if command_line.command.as_deref() == Some("migrate") {
+        tracing::info!("database migrations applied");
+        return Ok(());
+    }
+    // 数据库版本落后于程序时直接退出，避免运行时才因缺表或缺函数出错
+    if let Err(message) = utils::migrations::ensure_current(&pool).await {
+        tracing::error!(
+            error = %message,
+            "database schema is not up to date, run `YogaServer migrate` or enable database.auto_migrate"
+        );
+        std::process::exit(1);
+    }
+
+    // 后台定时任务，随 Rocket 启动，收到关闭信号后停止
+    let mut supervisor = Supervisor::new(Duration::from_secs(settings.tasks.jitter_secs));
+    if settings.tasks.enabled {
+        supervisor = supervisor.every(
+            "expire_membership_cards",
+            Duration::from_secs(settings.tasks.expire_cards_interval_secs),
+            |pool| async move {
+                models::membership::expire_membership_cards(&pool)
+                    .await
+                    .map(|count| format!("expired {} cards", count))
+                    .map_err(|error| error.to_string())
+            },
+        );
+    }
+    if settings.rate_limit.enabled && settings.rate_limit.backend == RateLimitBackend::Postgres {
+        supervisor = supervisor.every("prune_rate_limit_buckets", Duration::from_secs(600), |pool| async move {
+            utils::rate_limit::prune_buckets(&pool)
+                .await
+                .map(|count| format!("deleted {} buckets", count))
+                .map_err(|error| error.to_string())
+        });
+    }
+    let job_statuses = supervisor.statuses();
+    let rate_limiter = RateLimiter::new(settings.rate_limit.clone());
+    // 配置已在 Settings::validate 中检查过
+    let trusted_proxies = TrustedProxies::new(&settings.server.trusted_proxies).unwrap_or_default();
+
+    let media_store = match utils::media_store::from_settings(&settings) {
+        Ok(store) => store,
+        Err(message) => {
+            tracing::error!(error = %message, "media storage configuration error");
+            std::process::exit(1);
+        }
+    };
 
-    // 通过环境变量设置数据库公网IP，端口，数据库名称，用户名，密码
-    let mut config = deadpool_postgres::Config::new();
-    config.host = Some(env::var("DB_HOST").expect("Please specify DB_HOST"));
-    config.port = Some(
-        env::var("DB_PORT")
-            .expect("Please specify DB_PORT")
-            .parse::<u16>()
-            .expect("Couldn't parse"),
-    );
-    config.password = Some(env::var("DB_PASSWORD").expect("Please specify DB_PASSWORD"));
-    config.dbname = Some("yoga".to_string());
-    config.user = Some("psycho".to_string());
-    config.manager = Some(ManagerConfig {
-        recycling_method: deadpool_postgres::RecyclingMethod::Fast,
-    });
+    let poster = match utils::poster::Poster::load(&settings) {
+        Ok(poster) => poster,
+        Err(errors) => {
+            for error in errors {
+                tracing::error!(error = %error, "invalid schedule poster template");
+            }
+            std::process::exit(1);
+        }
+    };
 
     let limits = Limits::default().limit("limits.file", 10.megabytes());
 
     let figment = Figment::from(rocket::Config::default())
-        .merge((rocket::Config::ADDRESS, settings.server_host.clone()))
-        .merge((rocket::Config::PORT, 8002))
+        .merge((rocket::Config::ADDRESS, settings.server.address.clone()))
+        .merge((rocket::Config::PORT, settings.server.port))
+        .merge((rocket::Config::CLI_COLORS, false))
+        // 客户端 IP 由 ClientRealAddr 按 server.trusted_proxies 解析，不使用 Rocket 读取的 X-Real-IP
+        .merge(("ip_header", false))
         .merge((rocket::Config::LIMITS, limits));
     // 实例化和启动 rocket
     rocket::build()
         .configure(figment)
-        .attach(ContentDisposition)
+        // CORS 放在最前面，预检请求改写后的状态码会被日志和指标记录
+        .attach(CORS::new(&settings.cors))
+        .attach(RequestLogger)
+        .attach(MetricsFairing)
+        .attach(supervisor)
         .manage(settings)
         .manage(pool)
-        .manage(
-            config
-                .create_pool(Some(Runtime::Tokio1), NoTls)
-                .expect("Can't create pool"),
-        )
+        .manage(job_statuses)
+        .manage(rate_limiter)
+        .manage(trusted_proxies)
+        .manage(media_store)
+        .manage(poster)
         .mount(
             "/",
-            routes![
+            request_log::traced(routes![
                 handlers::admin_auth::admin_login,
                 handlers::admin_auth::admin_verify,
                 handlers::admin_book::admin_lessons_update,
@@ -72,8 +158,11 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::admin_lessons::create_lesson,
                 handlers::admin_lessons::admin_lesson_hidden,
                 handlers::admin_lessons::admin_lesson_delete,
+                handlers::admin_lessons::admin_lesson_restore,
                 handlers::admin_lessons::admin_lessons_and_teachers,
                 handlers::admin_lessons::admin_lesson_update,
+                handlers::admin_lessons::admin_lesson_substitute,
+                handlers::admin_lessons::admin_lesson_cancel,
                 handlers::admin_user::admin_user_lessons,
                 handlers::admin_user::admin_users_all,
                 handlers::admin_user::admin_user,
@@ -81,7 +170,8 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::booking::book,handlers::booking::unbook,
                 handlers::debug::debug,handlers::favicon::favicon,
                 handlers::index::index,handlers::index::index_without_openid,handlers::picture::picture,
-                handlers::picture::avatar,handlers::schedule::admin_schedule,
+                handlers::lesson_search::search_lessons,
+                handlers::picture::avatar,handlers::schedule::admin_schedule,handlers::schedule::schedule_feed,handlers::calendar::issue_calendar_token,handlers::calendar::revoke_calendar_token,handlers::calendar::bookings_feed,
                 handlers::teacher::teacher_lessons,
                 handlers::user::user_query,
                 handlers::user::register_user,
@@ -90,6 +180,10 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::membership::get_user_cards,
                 handlers::membership::purchase_card,
                 handlers::membership::get_card_usage,
+                handlers::metrics::metrics,
+                handlers::health::healthz,
+                handlers::health::readyz,
+                handlers::notification::get_notifications,
                 handlers::admin_notices::get_notices,
                 handlers::admin_notices::create_notice,
                 handlers::admin_notices::update_notice,
@@ -98,10 +192,12 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::admin_posters::create_poster,
                 handlers::admin_posters::update_poster,
                 handlers::admin_posters::delete_poster,
+                handlers::admin_tasks::get_tasks,
                 handlers::admin_teachers::get_teachers,
                 handlers::admin_teachers::create_teacher,
                 handlers::admin_teachers::update_teacher,
                 handlers::admin_teachers::delete_teacher,
+                handlers::admin_teachers::restore_teacher,
                 handlers::action_button::get_action_buttons,
                 handlers::action_button::get_active_action_buttons,
                 handlers::action_button::update_action_button,
@@ -122,6 +218,7 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::location::create_location,
                 handlers::location::update_location,
                 handlers::location::delete_location,
+                handlers::location::restore_location,
                 handlers::admin_user::get_users,
                 handlers::admin_user::create_user,
                 handlers::admin_user::update_user,
@@ -130,13 +227,40 @@ async fn main() -> Result<(), rocket::Error> {
                 handlers::upload::serve_image,
                 handlers::location::get_admin_locations,
                 handlers::upload::admin_upload_file,
-                ],
+                handlers::upload::delete_image,
+                handlers::admin_media::list_media,
+                handlers::admin_media::get_orphans,
+                handlers::admin_media::delete_orphans,
+            ]),
+        )
+        .mount(
+            "/api/v2",
+            request_log::traced(routes![
+                handlers::v2::index,
+                handlers::v2::lessons,
+                handlers::v2::search_lessons,
+                handlers::v2::book,
+                handlers::v2::unbook,
+                handlers::v2::teacher_lessons,
+                handlers::v2::membership_plans,
+                handlers::v2::membership_cards,
+                handlers::v2::purchase_card,
+                handlers::v2::card_usage,
+                handlers::v2::notifications,
+                handlers::v2::locations,
+                handlers::v2::action_buttons,
+                handlers::v2::user_query,
+                handlers::v2::register_user,
+                handlers::v2::user_statistics,
+            ]),
         )
         .register(
             "/",
             catchers![
                 errors::not_found::not_found,
-                errors::internal_error::internal_error
+                errors::too_many_requests::too_many_requests,
+                errors::internal_error::internal_error,
+                errors::default_catcher::default_catcher
             ],
         )
         .launch()
@@ -131,7 +131,8 @@ pub async fn update_action_button(id: i32, data: Value, sqlx_pool: &Pool<Postgre
     } else {
         Ok(json!({
             "success": false,
-            "message": "Action button not found"
+            "code": "NOT_FOUND",
+            "message": "功能按钮不存在"
         }))
     }
 }
@@ -152,7 +153,8 @@ pub async fn delete_action_button(id: i32, sqlx_pool: &Pool<Postgres>) -> Result
     } else {
         Ok(json!({
             "success": false,
-            "message": "Action button not found"
+            "code": "NOT_FOUND",
+            "message": "功能按钮不存在"
         }))
     }
 }
\ No newline at end of file
@@ -154,7 +154,7 @@ pub async fn delete_admin_user(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<se
     if result.rows_affected() > 0 {
         Ok(json!({"success": true, "message": "Admin user deleted successfully"}))
     } else {
-        Ok(json!({"success": false, "message": "Admin user not found"}))
+        Ok(json!({"success": false, "code": "NOT_FOUND", "message": "管理员不存在"}))
     }
 }
 
@@ -231,7 +231,7 @@ pub async fn delete_user(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<serde_js
     if result.rows_affected() > 0 {
         Ok(json!({"success": true, "message": "User deleted successfully"}))
     } else {
-        Ok(json!({"success": false, "message": "User not found"}))
+        Ok(json!({"success": false, "code": "USER_NOT_FOUND", "message": "User not found"}))
     }
 }
 
@@ -261,21 +261,23 @@ pub async fn get_users_with_stats(sqlx_pool: &Pool<Postgres>) -> Result<serde_js
 }
 
 // Stored procedure functions (kept as-is for compatibility)
-pub async fn get_admin_user_lessons(id: i32, start: i64, end: i64, sqlx_pool: &Pool<Postgres>) -> Result<serde_json::Value, sqlx::Error> {
-    let query = "select * from fn_admin_user_lessons($1,$2,$3)";
+pub async fn get_admin_user_lessons(id: i32, start: i64, end: i64, timezone: &str, sqlx_pool: &Pool<Postgres>) -> Result<serde_json::Value, sqlx::Error> {
+    let query = "select * from fn_admin_user_lessons($1,$2,$3,$4)";
     
     sqlx::query_scalar::<_, serde_json::Value>(query)
         .bind(id)
         .bind(start)
         .bind(end)
+        .bind(timezone)
         .fetch_one(sqlx_pool)
         .await
 }
 
-pub async fn get_admin_user_details(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<serde_json::Value, sqlx::Error> {
+// 会员不存在时返回 None
+pub async fn get_admin_user_details(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<Option<serde_json::Value>, sqlx::Error> {
     let query = "select * from fn_admin_user($1)";
     
-    sqlx::query_scalar::<_, serde_json::Value>(query)
+    sqlx::query_scalar::<_, Option<serde_json::Value>>(query)
         .bind(id)
         .fetch_one(sqlx_pool)
         .await
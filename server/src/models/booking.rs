@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Pool, Postgres};
 use serde_json::{json, Value};
+use crate::utils::metrics::METRICS;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct BookingModel {
@@ -99,9 +100,10 @@ pub struct BookingResult {
 
 #[derive(FromRow)]
 pub struct BookingInfo {
-    pub id: i32,
-    pub user_id: i32,
-    pub lesson_id: i32,
+    pub started: bool,
+    pub past_cutoff: bool,
+    // 代课时允许免截止取消，且预约在代课之前
+    pub free_cancel: bool,
 }
 
 // Database operations
@@ -114,38 +116,46 @@ pub async fn get_lessons_with_booking_status(
     let query = r#"
         SELECT json_agg(
             jsonb_build_object(
-                'id', l.id,
-                'title', l.title,
-                'description', l.description,
-                'teacher_id', l.teacher_id,
-                'teacher_name', t.name,
-                'location_name', loc.name,
-                'start_time', extract(epoch from l.start_time)::bigint,
-                'end_time', extract(epoch from l.end_time)::bigint,
-                'date_time', extract(epoch from l.start_time)::bigint,
-                'max_students', l.max_students,
-                'current_students', COUNT(b.id) FILTER (WHERE b.status = 'confirmed'),
-                'is_booked', COUNT(b2.id) FILTER (WHERE b2.user_id = u.id AND b2.status = 'confirmed') > 0,
-                'booking_id', MAX(b2.id) FILTER (WHERE b2.user_id = u.id AND b2.status = 'confirmed')
-            )
+                'id', x.id,
+                'title', x.title,
+                'description', x.description,
+                'teacher_id', x.teacher_id,
+                'teacher_name', x.teacher_name,
+                'location_name', x.location_name,
+                'start_time', extract(epoch from x.start_time)::bigint,
+                'end_time', extract(epoch from x.end_time)::bigint,
+                'date_time', extract(epoch from x.start_time)::bigint,
+                'max_students', x.max_students,
+                'current_students', x.current_students,
+                'is_booked', x.booking_id IS NOT NULL,
+                'booking_id', x.booking_id
+            ) ORDER BY x.start_time ASC
         ) as result
-        FROM lessons l
-        LEFT JOIN teachers t ON l.teacher_id = t.id
-        LEFT JOIN locations loc ON l.location_id = loc.id
-        LEFT JOIN bookings b ON l.id = b.lesson_id AND b.status = 'confirmed'
-        LEFT JOIN users u ON u.open_id = $2
-        LEFT JOIN bookings b2 ON l.id = b2.lesson_id AND b2.user_id = u.id
-        WHERE l.is_active = true
-          AND l.start_time >= to_timestamp($1)
-          AND l.start_time <= to_timestamp($1) + INTERVAL '14 days'
-        GROUP BY l.id, l.title, l.description, l.teacher_id, t.name, loc.name, l.start_time, 
-                 l.end_time, l.max_students, u.id
-        ORDER BY l.start_time ASC
+        FROM (
+            SELECT l.id, l.title, l.description, l.teacher_id, t.name as teacher_name,
+                   loc.name as location_name, l.start_time, l.end_time, l.max_students,
+                   COUNT(b.id) FILTER (WHERE b.status = 'confirmed') as current_students,
+                   MAX(b2.id) FILTER (WHERE b2.user_id = u.id AND b2.status = 'confirmed') as booking_id
+            FROM lessons l
+            LEFT JOIN teachers t ON l.teacher_id = t.id
+            LEFT JOIN locations loc ON l.location_id = loc.id
+            LEFT JOIN bookings b ON l.id = b.lesson_id AND b.status = 'confirmed'
+            LEFT JOIN users u ON u.open_id = $2
+            LEFT JOIN bookings b2 ON l.id = b2.lesson_id AND b2.user_id = u.id
+            WHERE l.is_active = true
+              AND l.deleted_at IS NULL
+              AND l.start_time >= to_timestamp($1)
+              AND l.start_time <= to_timestamp($1) + INTERVAL '14 days'
+              AND ($3 = 0 OR (fn_lesson_type_mask(l.lesson_type) & $3) <> 0)
+            GROUP BY l.id, l.title, l.description, l.teacher_id, t.name, loc.name, l.start_time,
+                     l.end_time, l.max_students, u.id
+        ) x
     "#;
     
     let row = sqlx::query_as::<_, JsonResult>(query)
         .bind(start)
         .bind(openid)
+        .bind(class_type)
         .fetch_one(sqlx_pool)
         .await?;
     
@@ -170,7 +180,7 @@ pub async fn create_booking(
     let user_id = match user_row {
         Some(row) => row.id,
         None => {
-            return Ok(json!({"success": false, "message": "User not found"}));
+            return Ok(json!({"success": false, "code": "USER_NOT_FOUND", "message": "User not found"}));
         }
     };
     
@@ -179,7 +189,7 @@ pub async fn create_booking(
         SELECT l.max_students, COUNT(b.id) as current_bookings
         FROM lessons l
         LEFT JOIN bookings b ON l.id = b.lesson_id AND b.status = 'confirmed'
-        WHERE l.id = $1 AND l.is_active = true
+        WHERE l.id = $1 AND l.is_active = true AND l.deleted_at IS NULL
         GROUP BY l.id, l.max_students
     "#;
     
@@ -191,12 +201,13 @@ pub async fn create_booking(
     let capacity_info = match lesson_capacity {
         Some(row) => row,
         None => {
-            return Ok(json!({"success": false, "message": "Lesson not found"}));
+            return Ok(json!({"success": false, "code": "LESSON_NOT_FOUND", "message": "Lesson not found"}));
         }
     };
     
     if capacity_info.current_bookings >= capacity_info.max_students as i64 {
-        return Ok(json!({"success": false, "message": "Lesson is full"}));
+        METRICS.lessons_full_total.inc();
+        return Ok(json!({"success": false, "code": "LESSON_FULL", "message": "Lesson is full"}));
     }
     
     // Check valid membership card
@@ -227,6 +238,7 @@ pub async fn create_booking(
     if !card_check.has_valid_card {
         return Ok(json!({
             "success": false, 
+            "code": "NO_VALID_CARD",
             "message": "没有有效的会员卡，请先购买会员卡"
         }));
     }
@@ -248,6 +260,7 @@ pub async fn create_booking(
         .await?;
     
     transaction.commit().await?;
+    METRICS.bookings_created_total.inc();
     
     Ok(json!({
         "success": true,
@@ -259,6 +272,7 @@ pub async fn create_booking(
 pub async fn cancel_booking(
     booking_id: i32,
     openid: &str,
+    cancel_cutoff_minutes: u32,
     sqlx_pool: &Pool<Postgres>,
 ) -> Result<Value, sqlx::Error> {
     // Start transaction
@@ -266,9 +280,17 @@ pub async fn cancel_booking(
     
     // Get booking info
     let booking_info_query = r#"
-        SELECT b.id, b.user_id, b.lesson_id
+        SELECT l.start_time <= CURRENT_TIMESTAMP AS started,
+               l.start_time - make_interval(mins => $3) <= CURRENT_TIMESTAMP AS past_cutoff,
+               EXISTS (
+                   SELECT 1 FROM lesson_substitutions ls
+                   WHERE ls.lesson_id = b.lesson_id
+                     AND ls.allow_free_cancel = true
+                     AND b.booking_time <= ls.created_at
+               ) AS free_cancel
         FROM bookings b
         JOIN users u ON b.user_id = u.id
+        JOIN lessons l ON b.lesson_id = l.id
         WHERE b.id = $1 
           AND u.open_id = $2
           AND b.status = 'confirmed'
@@ -277,26 +299,44 @@ pub async fn cancel_booking(
     let booking_info = sqlx::query_as::<_, BookingInfo>(booking_info_query)
         .bind(booking_id)
         .bind(openid)
+        .bind(cancel_cutoff_minutes as i32)
         .fetch_optional(&mut *transaction)
         .await?;
     
-    let (_booking_id, _user_id, _lesson_id) = match booking_info {
-        Some(row) => (row.id, row.user_id, row.lesson_id),
+    let booking_info = match booking_info {
+        Some(row) => row,
         None => {
-            return Ok(json!({"success": false, "message": "Booking not found"}));
+            return Ok(json!({"success": false, "code": "BOOKING_NOT_FOUND", "message": "Booking not found"}));
         }
     };
+
+    // 开课前 cancel_cutoff_minutes 分钟起不能取消。代课时允许免截止取消的，
+    // 代课前已预约的会员在开课前都可以取消
+    if booking_info.started || (booking_info.past_cutoff && !booking_info.free_cancel) {
+        return Ok(json!({
+            "success": false,
+            "code": "CANCEL_CUTOFF_PASSED",
+            "message": format!("开课前 {} 分钟内不能取消预约", cancel_cutoff_minutes)
+        }));
+    }
     
-    // Cancel booking
+    // Cancel booking, recording free cancellations after a substitution
     let cancel_query = r#"
         UPDATE bookings 
-        SET status = 'cancelled'
+        SET status = 'cancelled',
+            cancelled_at = CURRENT_TIMESTAMP,
+            cancellation_reason = CASE
+                WHEN $2 THEN 'teacher substituted'
+                ELSE cancellation_reason
+            END,
+            updated_at = CURRENT_TIMESTAMP
         WHERE id = $1
         RETURNING id
     "#;
     
     let cancelled_booking = sqlx::query_as::<_, BookingResult>(cancel_query)
         .bind(booking_id)
+        .bind(booking_info.free_cancel)
         .fetch_optional(&mut *transaction)
         .await?;
     
@@ -334,6 +374,7 @@ pub async fn cancel_booking(
                 .await;
             
             transaction.commit().await?;
+            METRICS.bookings_cancelled_total.with_label_values(&["member"]).inc();
             
             Ok(json!({
                 "success": true,
@@ -342,7 +383,29 @@ pub async fn cancel_booking(
             }))
         }
         None => {
-            Ok(json!({"success": false, "message": "Failed to cancel booking"}))
+            Ok(json!({"success": false, "code": "BOOKING_NOT_FOUND", "message": "Failed to cancel booking"}))
         }
     }
-}
\ No newline at end of file
+}
+
+// 会员已确认、尚未结束的预约，按上课时间排序
+pub async fn list_upcoming_bookings(user_id: i32, sqlx_pool: &Pool<Postgres>) -> Result<Vec<BookingWithLessonInfo>, sqlx::Error> {
+    let query = r#"
+        SELECT b.id, b.user_id, b.lesson_id, l.title AS lesson_title, t.name AS teacher_name,
+               loc.name AS location_name, l.start_time, l.end_time, b.status::TEXT AS status,
+               b.booking_time, b.notes
+        FROM bookings b
+        JOIN lessons l ON b.lesson_id = l.id
+        LEFT JOIN teachers t ON l.teacher_id = t.id
+        LEFT JOIN locations loc ON l.location_id = loc.id
+        WHERE b.user_id = $1
+          AND b.status = 'confirmed'
+          AND l.deleted_at IS NULL
+          AND l.end_time > CURRENT_TIMESTAMP
+        ORDER BY l.start_time, b.id
+    "#;
+    sqlx::query_as::<_, BookingWithLessonInfo>(query)
+        .bind(user_id)
+        .fetch_all(sqlx_pool)
+        .await
+}
@@ -0,0 +1,72 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+// 订阅地址中的令牌，32 字节随机数的十六进制
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// 为会员生成新的令牌，原来的令牌随即失效。会员不存在时返回 None
+pub async fn issue_token(open_id: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<String>, sqlx::Error> {
+    let token = new_token();
+    let issued = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO calendar_tokens (user_id, token_hash)
+        SELECT id, $2 FROM users WHERE open_id = $1
+        ON CONFLICT (user_id) DO UPDATE
+        SET token_hash = EXCLUDED.token_hash, created_at = CURRENT_TIMESTAMP, last_used_at = NULL
+        RETURNING user_id
+        "#,
+    )
+    .bind(open_id)
+    .bind(token_hash(&token))
+    .fetch_optional(sqlx_pool)
+    .await?;
+    Ok(issued.map(|_| token))
+}
+
+// 撤销会员的令牌，没有令牌时返回 false
+pub async fn revoke_token(open_id: &str, sqlx_pool: &Pool<Postgres>) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM calendar_tokens WHERE user_id = (SELECT id FROM users WHERE open_id = $1)",
+    )
+    .bind(open_id)
+    .execute(sqlx_pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 令牌对应的会员 id，同时记录拉取时间
+pub async fn find_user_by_token(token: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        UPDATE calendar_tokens SET last_used_at = CURRENT_TIMESTAMP
+        WHERE token_hash = $1
+        RETURNING user_id
+        "#,
+    )
+    .bind(token_hash(token))
+    .fetch_optional(sqlx_pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let token = new_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, new_token());
+        assert_eq!(token_hash(&token).len(), 64);
+        assert_ne!(token_hash(&token), token);
+    }
+}
@@ -179,7 +179,7 @@ pub async fn get_featured_teachers(sqlx_pool: &Pool<Postgres>) -> Result<Value,
             ) ORDER BY average_rating DESC NULLS LAST, experience_years DESC
         ), '[]'::json) as teachers
         FROM teachers 
-        WHERE is_active = true 
+        WHERE is_active = true AND deleted_at IS NULL
         LIMIT 5
     "#;
 
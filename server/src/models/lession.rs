@@ -1,10 +1,12 @@
 use std::process::id;
 
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool as sPool, Pool, Postgres, FromRow, Row};
 pub use crate::handlers::models::Lesson;
 use crate::handlers::models::Teacher;
+use crate::utils::metrics::METRICS;
 
 // Helper function to convert a database row to a Lesson struct
 fn row_to_lession(row: &sqlx::postgres::PgRow) -> Lesson {
@@ -130,6 +132,7 @@ pub async fn list_lessons(
         LEFT JOIN teachers t ON l.teacher_id = t.id
         LEFT JOIN locations loc ON l.location_id = loc.id
         WHERE l.is_active = true
+          AND l.deleted_at IS NULL
           AND l.start_time BETWEEN to_timestamp($1) AND to_timestamp($2)
         ORDER BY l.start_time
         LIMIT $3 OFFSET $4
@@ -152,6 +155,72 @@ pub async fn list_lessons(
 }
 
 
+// 课程类型，与数据库中的 lesson_type 枚举一致
+pub const LESSON_TYPES: [&str; 5] = ["team", "small_class", "private", "equipment_small_class", "workshop"];
+
+// 课程难度，与数据库中的 difficulty_level 枚举一致
+pub const DIFFICULTY_LEVELS: [&str; 4] = ["beginner", "intermediate", "advanced", "all_levels"];
+
+// 课表的筛选条件，为 None 时不筛选
+#[derive(Debug, Default)]
+pub struct ScheduleFilter {
+    pub teacher_id: Option<i32>,
+    pub location_id: Option<i32>,
+    pub lesson_type: Option<String>,
+}
+
+// 课表中 [start, end) 之间开始的课程，不含已停用和已删除的课程
+pub async fn list_schedule_lessons(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ScheduleFilter,
+    sqlx_pool: &Pool<Postgres>,
+) -> Result<Vec<Lesson>, sqlx::Error> {
+    let query = r#"
+        SELECT 
+            l.id, l.title, l.description, l.lesson_type::TEXT, l.difficulty_level::TEXT,
+            l.start_time, l.end_time, l.max_students, l.current_students,
+            l.price, l.equipment_required, l.prerequisites, l.cancellation_policy,
+            l.notes, l.created_at, l.updated_at, l.is_active,
+            l.teacher_id, l.location_id,
+            t.name as teacher_name, t.description as teacher_description,
+            t.avatar_url as teacher_avatar_url, t.bio as teacher_bio,
+            t.certifications as teacher_certifications, t.specialties as teacher_specialties,
+            t.experience_years as teacher_experience_years, t.average_rating as teacher_average_rating,
+            t.total_ratings as teacher_total_ratings, t.created_at as teacher_created_at,
+            t.updated_at as teacher_updated_at, t.is_active as teacher_is_active,
+            loc.name as location_name, loc.description as location_description,
+            loc.capacity as location_capacity, loc.equipment as location_equipment,
+            loc.facilities as location_facilities, loc.floor_number as location_floor_number,
+            loc.room_number as location_room_number, loc.is_accessible as location_is_accessible,
+            loc.booking_enabled as location_booking_enabled, loc.hourly_rate as location_hourly_rate,
+            loc.images as location_images, loc.created_at as location_created_at,
+            loc.updated_at as location_updated_at, loc.is_active as location_is_active
+        FROM lessons l
+        LEFT JOIN teachers t ON l.teacher_id = t.id
+        LEFT JOIN locations loc ON l.location_id = loc.id
+        WHERE l.is_active = true
+          AND l.deleted_at IS NULL
+          AND l.start_time >= $1
+          AND l.start_time < $2
+          AND ($3::INTEGER IS NULL OR l.teacher_id = $3)
+          AND ($4::INTEGER IS NULL OR l.location_id = $4)
+          AND ($5::TEXT IS NULL OR l.lesson_type::TEXT = $5)
+        ORDER BY l.start_time, l.id
+    "#;
+
+    let lesson_rows = sqlx::query(query)
+        .bind(start)
+        .bind(end)
+        .bind(filter.teacher_id)
+        .bind(filter.location_id)
+        .bind(filter.lesson_type.as_deref())
+        .fetch_all(sqlx_pool)
+        .await?;
+
+    Ok(lesson_rows.iter().map(row_to_lession).collect())
+}
+
 pub async fn get_lesson_by_id(id: i32, sqlxPool: &sPool<Postgres>) -> Result<Option<Lesson>, sqlx::Error> {
     let query = r#"
         SELECT 
@@ -201,13 +270,64 @@ pub async fn update_lesson_status(id: i32, is_active: bool, sqlxPool: &sPool<Pos
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_lesson(id: i32, sqlxPool: &sPool<Postgres>) -> Result<bool, sqlx::Error> {
-    let query = "DELETE FROM lessons WHERE id = $1";
-    
+// Outcome of archiving a lesson
+#[derive(Debug, PartialEq)]
+pub enum LessonDeleteResult {
+    Archived,
+    NotFound,
+    // The lesson still has confirmed upcoming bookings; cancel it first
+    HasBookings(i64),
+}
+
+// Lessons are never removed: bookings and card usage rows reference them, so
+// deleting archives the lesson by setting `deleted_at`.
+pub async fn delete_lesson(id: i32, sqlxPool: &sPool<Postgres>) -> Result<LessonDeleteResult, sqlx::Error> {
+    let check_bookings_query = r#"
+        SELECT COUNT(b.id)
+        FROM bookings b
+        JOIN lessons l ON b.lesson_id = l.id
+        WHERE l.id = $1
+          AND b.status = 'confirmed'
+          AND l.start_time > CURRENT_TIMESTAMP
+    "#;
+
+    let booking_count = sqlx::query_scalar::<_, i64>(check_bookings_query)
+        .bind(id)
+        .fetch_one(sqlxPool).await?;
+
+    if booking_count > 0 {
+        return Ok(LessonDeleteResult::HasBookings(booking_count));
+    }
+
+    let query = r#"
+        UPDATE lessons
+        SET deleted_at = CURRENT_TIMESTAMP, is_active = false, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NULL
+    "#;
+
     let result = sqlx::query(query)
         .bind(id)
         .execute(sqlxPool).await?;
-    
+
+    if result.rows_affected() > 0 {
+        Ok(LessonDeleteResult::Archived)
+    } else {
+        Ok(LessonDeleteResult::NotFound)
+    }
+}
+
+// Bring an archived lesson back. Lessons cancelled by the studio stay hidden.
+pub async fn restore_lesson(id: i32, sqlxPool: &sPool<Postgres>) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        UPDATE lessons
+        SET deleted_at = NULL, is_active = (cancelled_at IS NULL), updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NOT NULL
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(id)
+        .execute(sqlxPool).await?;
+
     Ok(result.rows_affected() > 0)
 }
 
@@ -236,6 +356,7 @@ pub async fn get_lessons_with_teachers(teacher_id: i32, sqlxPool: &sPool<Postgre
         LEFT JOIN teachers t ON l.teacher_id = t.id
         LEFT JOIN locations loc ON l.location_id = loc.id
         WHERE l.teacher_id = $1
+          AND l.deleted_at IS NULL
         ORDER BY l.start_time
     "#;
     
@@ -355,4 +476,334 @@ pub async fn update_lesson_data(data: &LessonUpdateData, sqlx_pool: &Pool<Postgr
         .await?;
     
     Ok(result.rows_affected() > 0)
-}
\ No newline at end of file
+}
+// 代课请求：将课程的教师替换为代课教师
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LessonSubstitutionRequest {
+    pub lesson_id: i32,
+    pub substitute_teacher_id: i32,
+    pub reason: Option<String>,
+    // 代课前已预约的会员开课前都可以取消，不受取消截止时间限制，取消原因记为 teacher substituted
+    pub allow_free_cancel: Option<bool>,
+}
+
+#[derive(FromRow)]
+pub struct LessonTeacherInfo {
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub teacher_id: Option<i32>,
+    pub teacher_name: Option<String>,
+}
+
+// 通知中的上课时间按场馆时区显示
+fn local_time(time: DateTime<Utc>, tz: Tz) -> String {
+    time.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string()
+}
+
+// Record a substitute teacher for a lesson and notify every booked member,
+// all inside one transaction so the lesson never points at the new teacher
+// without the substitution record and notifications.
+pub async fn substitute_teacher(
+    data: &LessonSubstitutionRequest,
+    tz: Tz,
+    sqlx_pool: &Pool<Postgres>,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let mut transaction = sqlx_pool.begin().await?;
+
+    let lesson_query = r#"
+        SELECT l.title, l.start_time, l.teacher_id, t.name as teacher_name
+        FROM lessons l
+        LEFT JOIN teachers t ON l.teacher_id = t.id
+        WHERE l.id = $1 AND l.deleted_at IS NULL
+        FOR UPDATE OF l
+    "#;
+
+    let lesson = sqlx::query_as::<_, LessonTeacherInfo>(lesson_query)
+        .bind(data.lesson_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+    let lesson = match lesson {
+        Some(lesson) => lesson,
+        None => {
+            return Ok(serde_json::json!({"success": false, "code": "LESSON_NOT_FOUND", "message": "Lesson not found"}));
+        }
+    };
+
+    if lesson.teacher_id == Some(data.substitute_teacher_id) {
+        return Ok(serde_json::json!({
+            "success": false,
+            "message": "代课老师已经是这节课的老师"
+        }));
+    }
+
+    let substitute_query = "SELECT name FROM teachers WHERE id = $1 AND is_active = true AND deleted_at IS NULL";
+    let substitute_name = sqlx::query_scalar::<_, String>(substitute_query)
+        .bind(data.substitute_teacher_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+    let substitute_name = match substitute_name {
+        Some(name) => name,
+        None => {
+            return Ok(serde_json::json!({"success": false, "code": "NOT_FOUND", "message": "代课老师不存在或已停用"}));
+        }
+    };
+
+    let allow_free_cancel = data.allow_free_cancel.unwrap_or(false);
+
+    let insert_query = r#"
+        INSERT INTO lesson_substitutions (
+            lesson_id, original_teacher_id, substitute_teacher_id, reason, allow_free_cancel
+        ) VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+    "#;
+
+    let substitution_id = sqlx::query_scalar::<_, i32>(insert_query)
+        .bind(data.lesson_id)
+        .bind(lesson.teacher_id)
+        .bind(data.substitute_teacher_id)
+        .bind(&data.reason)
+        .bind(allow_free_cancel)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    let update_query = "UPDATE lessons SET teacher_id = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1";
+    sqlx::query(update_query)
+        .bind(data.lesson_id)
+        .bind(data.substitute_teacher_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    let original_name = lesson.teacher_name.unwrap_or_else(|| "原老师".to_string());
+    let mut content = format!(
+        "您预约的课程「{}」({}) 将由{}老师代替{}老师授课。",
+        lesson.title,
+        local_time(lesson.start_time, tz),
+        substitute_name,
+        original_name
+    );
+    if let Some(reason) = data.reason.as_deref().filter(|r| !r.is_empty()) {
+        content.push_str(&format!("原因：{}。", reason));
+    }
+    if allow_free_cancel {
+        content.push_str("如需取消，开课前均可取消，不受取消截止时间限制。");
+    }
+
+    let notified = crate::models::notification::enqueue_for_lesson_bookings(
+        data.lesson_id,
+        "teacher_substituted",
+        "代课通知",
+        &content,
+        &mut transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "substitution_id": substitution_id,
+        "original_teacher_id": lesson.teacher_id,
+        "substitute_teacher_id": data.substitute_teacher_id,
+        "allow_free_cancel": allow_free_cancel,
+        "notified_members": notified,
+        "message": "Teacher substituted successfully"
+    }))
+}
+
+// 场馆取消课程请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LessonCancellationRequest {
+    pub lesson_id: i32,
+    pub reason: Option<String>,
+}
+
+#[derive(FromRow)]
+pub struct LessonCancellationInfo {
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+pub struct RefundableUsage {
+    pub user_card_id: i32,
+    pub booking_id: i32,
+    pub user_id: i32,
+    pub classes_consumed: i32,
+    pub remaining_classes: Option<i32>,
+}
+
+// Cancel a lesson on behalf of the studio: the lesson is hidden, every
+// confirmed booking is cancelled, count-based cards get their classes back
+// with a refund usage row, and booked members are notified. Everything runs
+// in a single transaction so a failure leaves the lesson untouched.
+pub async fn cancel_lesson_by_studio(
+    data: &LessonCancellationRequest,
+    tz: Tz,
+    sqlx_pool: &Pool<Postgres>,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let mut transaction = sqlx_pool.begin().await?;
+
+    let lesson_query = "SELECT title, start_time, cancelled_at FROM lessons WHERE id = $1 AND deleted_at IS NULL FOR UPDATE";
+    let lesson = sqlx::query_as::<_, LessonCancellationInfo>(lesson_query)
+        .bind(data.lesson_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+    let lesson = match lesson {
+        Some(lesson) => lesson,
+        None => {
+            return Ok(serde_json::json!({"success": false, "code": "LESSON_NOT_FOUND", "message": "Lesson not found"}));
+        }
+    };
+
+    if lesson.cancelled_at.is_some() {
+        return Ok(serde_json::json!({"success": false, "code": "LESSON_CANCELLED", "message": "Lesson is already cancelled"}));
+    }
+
+    let update_query = r#"
+        UPDATE lessons
+        SET is_active = false,
+            cancelled_at = CURRENT_TIMESTAMP,
+            cancellation_reason = $2,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+    "#;
+    sqlx::query(update_query)
+        .bind(data.lesson_id)
+        .bind(&data.reason)
+        .execute(&mut *transaction)
+        .await?;
+
+    // Notify while the bookings are still confirmed
+    let mut content = format!(
+        "很抱歉，您预约的课程「{}」({}) 已被场馆取消，预约已自动取消，次卡课时已退回。",
+        lesson.title,
+        local_time(lesson.start_time, tz)
+    );
+    if let Some(reason) = data.reason.as_deref().filter(|r| !r.is_empty()) {
+        content.push_str(&format!("原因：{}。", reason));
+    }
+
+    let notified = crate::models::notification::enqueue_for_lesson_bookings(
+        data.lesson_id,
+        "lesson_cancelled",
+        "停课通知",
+        &content,
+        &mut transaction,
+    )
+    .await?;
+
+    let cancel_bookings_query = r#"
+        UPDATE bookings
+        SET status = 'cancelled',
+            cancellation_reason = 'studio cancelled',
+            cancelled_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE lesson_id = $1 AND status = 'confirmed'
+        RETURNING id
+    "#;
+    let cancelled_ids = sqlx::query_scalar::<_, i32>(cancel_bookings_query)
+        .bind(data.lesson_id)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    // 预约都已取消，人数按剩余的已确认预约重新计算
+    let recount_query = r#"
+        UPDATE lessons
+        SET current_students = (
+            SELECT COUNT(*) FROM bookings WHERE lesson_id = $1 AND status = 'confirmed'
+        )
+        WHERE id = $1
+    "#;
+    sqlx::query(recount_query)
+        .bind(data.lesson_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    // Count-based card deductions for the cancelled bookings that have not been refunded yet
+    let usage_query = r#"
+        SELECT mcu.user_card_id, mcu.booking_id, mcu.user_id, mcu.classes_consumed,
+               umc.remaining_classes
+        FROM membership_card_usage mcu
+        JOIN user_membership_cards umc ON mcu.user_card_id = umc.id
+        WHERE mcu.booking_id = ANY($1)
+          AND mcu.usage_type = 'booking'
+          AND mcu.classes_consumed > 0
+          AND umc.card_type = 'count_based'
+        ORDER BY mcu.id
+        FOR UPDATE OF umc
+    "#;
+    let usages = sqlx::query_as::<_, RefundableUsage>(usage_query)
+        .bind(&cancelled_ids)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    let refund_card_query = r#"
+        UPDATE user_membership_cards
+        SET remaining_classes = remaining_classes + $2,
+            status = CASE WHEN status = 'used_up' THEN 'active'::membership_card_status ELSE status END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING remaining_classes
+    "#;
+    let refund_usage_query = r#"
+        INSERT INTO membership_card_usage (
+            user_card_id, booking_id, lesson_id, user_id, usage_type, classes_consumed,
+            remaining_classes_before, remaining_classes_after, notes
+        ) VALUES ($1, $2, $3, $4, 'refund', $5, $6, $7, 'studio cancelled')
+    "#;
+
+    let mut refunded_classes = 0;
+    for usage in &usages {
+        let remaining_after = sqlx::query_scalar::<_, Option<i32>>(refund_card_query)
+            .bind(usage.user_card_id)
+            .bind(usage.classes_consumed)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        sqlx::query(refund_usage_query)
+            .bind(usage.user_card_id)
+            .bind(usage.booking_id)
+            .bind(data.lesson_id)
+            .bind(usage.user_id)
+            .bind(-usage.classes_consumed)
+            .bind(usage.remaining_classes)
+            .bind(remaining_after)
+            .execute(&mut *transaction)
+            .await?;
+
+        refunded_classes += usage.classes_consumed;
+    }
+
+    transaction.commit().await?;
+    METRICS
+        .bookings_cancelled_total
+        .with_label_values(&["studio"])
+        .inc_by(cancelled_ids.len() as u64);
+
+    Ok(serde_json::json!({
+        "success": true,
+        "lesson_id": data.lesson_id,
+        "cancelled_bookings": cancelled_ids.len(),
+        "refunded_cards": usages.len(),
+        "refunded_classes": refunded_classes,
+        "notified_members": notified,
+        "message": "Lesson cancelled successfully"
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn notification_time_is_local() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 20, 1, 30, 0).unwrap();
+        assert_eq!(local_time(start, chrono_tz::Asia::Hong_Kong), "2026-10-20 09:30");
+        assert_eq!(local_time(start, chrono_tz::UTC), "2026-10-20 01:30");
+    }
+}
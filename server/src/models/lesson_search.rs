@@ -0,0 +1,194 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, Pool, Postgres};
+
+// 会员搜索课程的条件，列表为空或为 None 时不筛选
+#[derive(Debug)]
+pub struct LessonSearch {
+    // 用于标记已预约的课程和筛选可用会员卡，为 None 时按未登录处理
+    pub openid: Option<String>,
+    pub lesson_types: Vec<String>,
+    pub difficulty_levels: Vec<String>,
+    pub teacher_id: Option<i32>,
+    pub location_id: Option<i32>,
+    // 在 [from, to) 之间开始的课程
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    // 开始时间在当地一天中的 [after, before) 秒之间
+    pub after: Option<u32>,
+    pub before: Option<u32>,
+    pub has_seats: bool,
+    // 只返回会员有可用会员卡的课程
+    pub bookable: bool,
+    // 上一页最后一节课的开始时间和 id
+    pub cursor: Option<LessonCursor>,
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LessonCursor {
+    pub start_time: DateTime<Utc>,
+    pub id: i32,
+}
+
+impl LessonCursor {
+    // 对客户端不透明，原样放在下一页的 cursor 参数中
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}.{}", self.start_time.timestamp_micros(), self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<LessonCursor> {
+        let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let (micros, id) = decoded.split_once('.')?;
+        Some(LessonCursor {
+            start_time: DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct LessonSearchResult {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub lesson_type: String,
+    pub difficulty_level: String,
+    pub teacher_id: Option<i32>,
+    pub teacher_name: Option<String>,
+    pub location_id: Option<i32>,
+    pub location_name: Option<String>,
+    pub start_time: i64, // Unix timestamp
+    pub end_time: i64,   // Unix timestamp
+    pub max_students: i32,
+    pub current_students: i64,
+    pub available_seats: i64,
+    // 已预约会员的 [{nick_name, avatar_url}]，按预约时间排序
+    pub users: Value,
+    pub is_booked: bool,
+    pub booking_id: Option<i32>,
+    // 会员有适用于这节课的会员卡，未登录时为 false
+    pub has_valid_card: bool,
+    #[serde(skip)]
+    pub cursor_time: DateTime<Utc>,
+}
+
+// 按开始时间和 id 排序的一页课程，还有下一页时同时返回下一页的游标。
+// 不含已停用、已删除和场馆已取消的课程
+pub async fn search_lessons(
+    search: &LessonSearch,
+    timezone: &str,
+    sqlx_pool: &Pool<Postgres>,
+) -> Result<(Vec<LessonSearchResult>, Option<String>), sqlx::Error> {
+    let query = r#"
+        SELECT l.id, l.title, l.description,
+               l.lesson_type::TEXT AS lesson_type, l.difficulty_level::TEXT AS difficulty_level,
+               l.teacher_id, t.name AS teacher_name, l.location_id, loc.name AS location_name,
+               extract(epoch from l.start_time)::bigint AS start_time,
+               extract(epoch from l.end_time)::bigint AS end_time,
+               l.max_students,
+               seats.current_students,
+               GREATEST(l.max_students - seats.current_students, 0) AS available_seats,
+               seats.users,
+               mine.id IS NOT NULL AS is_booked,
+               mine.id AS booking_id,
+               card.has_valid_card,
+               l.start_time AS cursor_time
+        FROM lessons l
+        LEFT JOIN teachers t ON l.teacher_id = t.id
+        LEFT JOIN locations loc ON l.location_id = loc.id
+        LEFT JOIN users u ON u.open_id = $1
+        LEFT JOIN bookings mine ON mine.lesson_id = l.id AND mine.user_id = u.id AND mine.status = 'confirmed'
+        CROSS JOIN LATERAL (
+            SELECT COUNT(*) AS current_students,
+                   COALESCE(json_agg(
+                       json_build_object('nick_name', bu.nick_name, 'avatar_url', bu.avatar_url)
+                       ORDER BY b.booking_time
+                   ), '[]'::json) AS users
+            FROM bookings b
+            JOIN users bu ON b.user_id = bu.id
+            WHERE b.lesson_id = l.id AND b.status = 'confirmed'
+        ) seats
+        -- 与预约时检查会员卡的条件一致
+        CROSS JOIN LATERAL (
+            SELECT EXISTS(
+                SELECT 1 FROM user_membership_cards umc
+                WHERE umc.user_id = u.id
+                  AND umc.status = 'active'
+                  AND umc.expires_at > CURRENT_TIMESTAMP
+                  AND (umc.applicable_lesson_types IS NULL OR l.lesson_type = ANY(umc.applicable_lesson_types))
+                  AND (umc.card_type = 'unlimited' OR (umc.card_type = 'count_based' AND umc.remaining_classes > 0))
+            ) AS has_valid_card
+        ) card
+        WHERE l.is_active = true
+          AND l.deleted_at IS NULL
+          AND l.cancelled_at IS NULL
+          AND l.start_time >= $2
+          AND l.start_time < $3
+          AND (cardinality($4::TEXT[]) = 0 OR l.lesson_type::TEXT = ANY($4))
+          AND (cardinality($5::TEXT[]) = 0 OR l.difficulty_level::TEXT = ANY($5))
+          AND ($6::INTEGER IS NULL OR l.teacher_id = $6)
+          AND ($7::INTEGER IS NULL OR l.location_id = $7)
+          AND ($9::INTEGER IS NULL OR extract(epoch from (l.start_time AT TIME ZONE $8)::time) >= $9)
+          AND ($10::INTEGER IS NULL OR extract(epoch from (l.start_time AT TIME ZONE $8)::time) < $10)
+          AND (NOT $11 OR seats.current_students < l.max_students)
+          AND (NOT $12 OR card.has_valid_card)
+          AND ($13::TIMESTAMPTZ IS NULL OR (l.start_time, l.id) > ($13, $14))
+        ORDER BY l.start_time, l.id
+        LIMIT $15
+    "#;
+
+    // 多取一条判断是否还有下一页
+    let mut lessons = sqlx::query_as::<_, LessonSearchResult>(query)
+        .bind(search.openid.as_deref())
+        .bind(search.from)
+        .bind(search.to)
+        .bind(&search.lesson_types)
+        .bind(&search.difficulty_levels)
+        .bind(search.teacher_id)
+        .bind(search.location_id)
+        .bind(timezone)
+        .bind(search.after.map(|secs| secs as i32))
+        .bind(search.before.map(|secs| secs as i32))
+        .bind(search.has_seats)
+        .bind(search.bookable)
+        .bind(search.cursor.map(|cursor| cursor.start_time))
+        .bind(search.cursor.map_or(0, |cursor| cursor.id))
+        .bind(search.limit as i64 + 1)
+        .fetch_all(sqlx_pool)
+        .await?;
+
+    let next_cursor = if lessons.len() > search.limit as usize {
+        lessons.truncate(search.limit as usize);
+        lessons.last().map(|lesson| {
+            LessonCursor {
+                start_time: lesson.cursor_time,
+                id: lesson.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+    Ok((lessons, next_cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = LessonCursor {
+            start_time: Utc.with_ymd_and_hms(2026, 10, 20, 1, 30, 0).unwrap() + chrono::Duration::microseconds(250),
+            id: 42,
+        };
+        assert_eq!(LessonCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(LessonCursor::decode("not a cursor"), None);
+        assert_eq!(LessonCursor::decode(&URL_SAFE_NO_PAD.encode("12.x")), None);
+    }
+}
@@ -20,6 +20,7 @@ pub struct LocationModel {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_active: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -144,7 +145,7 @@ pub async fn get_all_locations(sqlx_pool: &Pool<Postgres>) -> Result<Value, sqlx
             ) ORDER BY floor_number ASC, room_number ASC
         ), '[]'::json) as locations
         FROM locations 
-        WHERE is_active = true
+        WHERE is_active = true AND deleted_at IS NULL
     "#;
 
     sqlx::query_scalar::<_, Value>(query)
@@ -169,7 +170,7 @@ pub async fn get_available_locations(sqlx_pool: &Pool<Postgres>) -> Result<Value
             ) ORDER BY floor_number ASC, room_number ASC
         ), '[]'::json) as locations
         FROM locations 
-        WHERE is_active = true AND booking_enabled = true
+        WHERE is_active = true AND deleted_at IS NULL AND booking_enabled = true
     "#;
 
     sqlx::query_scalar::<_, Value>(query)
@@ -187,7 +188,7 @@ pub async fn check_location_availability(
     let location_query = r#"
         SELECT name, booking_enabled 
         FROM locations 
-        WHERE id = $1 AND is_active = true
+        WHERE id = $1 AND is_active = true AND deleted_at IS NULL
     "#;
 
     let location_info = sqlx::query_as::<_, LocationInfo>(location_query)
@@ -200,7 +201,8 @@ pub async fn check_location_availability(
         None => {
             return Ok(json!({
                 "success": false,
-                "message": "Location not found"
+                "code": "NOT_FOUND",
+                "message": "地点不存在"
             }));
         }
     };
@@ -208,7 +210,7 @@ pub async fn check_location_availability(
     if !location_info.booking_enabled {
         return Ok(json!({
             "success": false,
-            "message": "Location is not available for booking"
+            "message": "该地点不开放预约"
         }));
     }
 
@@ -219,6 +221,7 @@ pub async fn check_location_availability(
         LEFT JOIN teachers t ON l.teacher_id = t.id
         WHERE l.location_id = $1 
           AND l.is_active = true
+          AND l.deleted_at IS NULL
           AND (
             (l.start_time <= $2 AND l.end_time > $2) OR
             (l.start_time < $3 AND l.end_time >= $3) OR
@@ -294,16 +297,19 @@ pub async fn get_location_statistics(location_id: i32, sqlx_pool: &Pool<Postgres
 }
 
 // Admin CRUD operations
-pub async fn get_all_admin_locations(sqlx_pool: &Pool<Postgres>) -> Result<Vec<LocationModel>, sqlx::Error> {
+// `archived` selects archived (soft-deleted) locations instead of current ones
+pub async fn get_all_admin_locations(archived: bool, sqlx_pool: &Pool<Postgres>) -> Result<Vec<LocationModel>, sqlx::Error> {
     let query = r#"
         SELECT id, name, description, capacity, equipment, facilities, floor_number,
                room_number, is_accessible, booking_enabled, hourly_rate, images, is_active,
-               created_at, updated_at
+               created_at, updated_at, deleted_at
         FROM locations
+        WHERE (deleted_at IS NOT NULL) = $1
         ORDER BY floor_number ASC, room_number ASC
     "#;
     
     sqlx::query_as::<_, LocationModel>(query)
+        .bind(archived)
         .fetch_all(sqlx_pool)
         .await
 }
@@ -315,7 +321,7 @@ pub async fn create_location(data: &LocationCreateRequest, sqlx_pool: &Pool<Post
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
         RETURNING id, name, description, capacity, equipment, facilities, floor_number,
                  room_number, is_accessible, booking_enabled, hourly_rate, images, is_active,
-                 created_at, updated_at
+                 created_at, updated_at, deleted_at
     "#;
     
     sqlx::query_as::<_, LocationModel>(query)
@@ -350,10 +356,10 @@ pub async fn update_location(data: &LocationUpdateRequest, sqlx_pool: &Pool<Post
             images = COALESCE($12, images),
             is_active = COALESCE($13, is_active),
             updated_at = CURRENT_TIMESTAMP
-        WHERE id = $1
+        WHERE id = $1 AND deleted_at IS NULL
         RETURNING id, name, description, capacity, equipment, facilities, floor_number,
                  room_number, is_accessible, booking_enabled, hourly_rate, images, is_active,
-                 created_at, updated_at
+                 created_at, updated_at, deleted_at
     "#;
     
     sqlx::query_as::<_, LocationModel>(query)
@@ -374,8 +380,36 @@ pub async fn update_location(data: &LocationUpdateRequest, sqlx_pool: &Pool<Post
         .await
 }
 
+// Locations are archived rather than removed, since past lessons keep
+// referencing them. Locations with upcoming lessons cannot be archived.
 pub async fn delete_location(location_id: i32, sqlx_pool: &Pool<Postgres>) -> Result<Value, sqlx::Error> {
-    let query = "DELETE FROM locations WHERE id = $1";
+    let check_lessons_query = r#"
+        SELECT COUNT(*) FROM lessons
+        WHERE location_id = $1
+          AND deleted_at IS NULL
+          AND cancelled_at IS NULL
+          AND start_time > CURRENT_TIMESTAMP
+    "#;
+
+    let lesson_count = sqlx::query_scalar::<_, i64>(check_lessons_query)
+        .bind(location_id)
+        .fetch_one(sqlx_pool)
+        .await?;
+
+    if lesson_count > 0 {
+        return Ok(json!({
+            "success": false,
+            "code": "HAS_UPCOMING_LESSONS",
+            "message": "Location has upcoming lessons, move or cancel them first",
+            "upcoming_lessons": lesson_count
+        }));
+    }
+
+    let query = r#"
+        UPDATE locations
+        SET deleted_at = CURRENT_TIMESTAMP, is_active = false, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NULL
+    "#;
     
     let result = sqlx::query(query)
         .bind(location_id)
@@ -383,8 +417,33 @@ pub async fn delete_location(location_id: i32, sqlx_pool: &Pool<Postgres>) -> Re
         .await?;
     
     if result.rows_affected() > 0 {
-        Ok(json!({"success": true, "message": "Location deleted successfully"}))
+        Ok(json!({"success": true, "message": "Location archived successfully", "soft_delete": true}))
     } else {
-        Ok(json!({"success": false, "message": "Location not found"}))
+        Ok(json!({"success": false, "code": "NOT_FOUND", "message": "地点不存在"}))
+    }
+}
+
+// Outcome of restoring an archived location
+#[derive(Debug, PartialEq)]
+pub enum LocationRestoreResult {
+    Restored,
+    NotFound,
+    // Another active location already uses this name
+    NameTaken,
+}
+
+pub async fn restore_location(location_id: i32, sqlx_pool: &Pool<Postgres>) -> Result<LocationRestoreResult, sqlx::Error> {
+    let query = r#"
+        UPDATE locations
+        SET deleted_at = NULL, is_active = true, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NOT NULL
+    "#;
+
+    match sqlx::query(query).bind(location_id).execute(sqlx_pool).await {
+        Ok(result) if result.rows_affected() > 0 => Ok(LocationRestoreResult::Restored),
+        Ok(_) => Ok(LocationRestoreResult::NotFound),
+        // 23505: unique violation on the active location name
+        Err(sqlx::Error::Database(error)) if error.code().as_deref() == Some("23505") => Ok(LocationRestoreResult::NameTaken),
+        Err(error) => Err(error),
     }
 }
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, Pool, Postgres};
+
+use crate::utils::media_store::{MediaStore, StoredKey};
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct MediaModel {
+    pub id: i32,
+    pub key: String,
+    pub sha256: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: i32,
+    pub height: i32,
+    pub variants: Value,
+    pub original_filename: Option<String>,
+    pub uploaded_by_admin_id: Option<i32>,
+    pub uploaded_by_user_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaModel {
+    // 原图和各版本在存储中的文件名
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys = vec![self.key.clone()];
+        if let Value::Object(variants) = &self.variants {
+            for variant in variants.values() {
+                if let Some(key) = variant["key"].as_str() {
+                    if !keys.iter().any(|existing| existing == key) {
+                        keys.push(key.to_string());
+                    }
+                }
+            }
+        }
+        keys
+    }
+
+    // 按上传内容的哈希生成 ETag，缩略图加上版本名，不需要读取文件内容。
+    // key 不属于这条记录时返回 None
+    pub fn etag(&self, key: &str) -> Option<String> {
+        if key == self.key {
+            return Some(format!("\"{}\"", self.sha256));
+        }
+        let Value::Object(variants) = &self.variants else {
+            return None;
+        };
+        variants
+            .iter()
+            .find(|(_, variant)| variant["key"].as_str() == Some(key))
+            .map(|(name, _)| format!("\"{}-{}\"", self.sha256, name))
+    }
+}
+
+pub struct NewMedia<'a> {
+    pub key: &'a str,
+    pub sha256: &'a str,
+    pub content_type: &'a str,
+    pub size_bytes: i64,
+    pub width: i32,
+    pub height: i32,
+    pub variants: Value,
+    pub original_filename: Option<&'a str>,
+    pub admin_id: Option<i32>,
+    pub member_openid: Option<&'a str>,
+}
+
+const MEDIA_COLUMNS: &str = r#"
+    id, key, sha256, content_type, size_bytes, width, height, variants, original_filename,
+    uploaded_by_admin_id, uploaded_by_user_id, created_at
+"#;
+
+pub async fn find_by_sha256(sha256: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<MediaModel>, sqlx::Error> {
+    let query = format!("SELECT {} FROM media WHERE sha256 = $1", MEDIA_COLUMNS);
+    sqlx::query_as::<_, MediaModel>(&query)
+        .bind(sha256)
+        .fetch_optional(sqlx_pool)
+        .await
+}
+
+pub async fn find_by_key(key: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<MediaModel>, sqlx::Error> {
+    let query = format!("SELECT {} FROM media WHERE key = $1", MEDIA_COLUMNS);
+    sqlx::query_as::<_, MediaModel>(&query)
+        .bind(key)
+        .fetch_optional(sqlx_pool)
+        .await
+}
+
+// 原图或缩略图所属的记录，原图只保存为 jpg 或 png
+pub async fn find_by_stored_key(key: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<MediaModel>, sqlx::Error> {
+    let group = media_group(key);
+    let originals = vec![format!("{}.jpg", group), format!("{}.png", group)];
+    let query = format!("SELECT {} FROM media WHERE key = ANY($1)", MEDIA_COLUMNS);
+    let media = sqlx::query_as::<_, MediaModel>(&query)
+        .bind(originals)
+        .fetch_optional(sqlx_pool)
+        .await?;
+    Ok(media.filter(|media| media.keys().iter().any(|stored| stored == key)))
+}
+
+// 同样内容的图片已经存在时返回 None
+pub async fn insert_media(media: NewMedia<'_>, sqlx_pool: &Pool<Postgres>) -> Result<Option<MediaModel>, sqlx::Error> {
+    // 上传者只是记录，管理员或会员不存在时记为空
+    let query = format!(
+        r#"
+        INSERT INTO media (
+            key, sha256, content_type, size_bytes, width, height, variants, original_filename,
+            uploaded_by_admin_id, uploaded_by_user_id
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8,
+            (SELECT id FROM admin_users WHERE id = $9),
+            (SELECT id FROM users WHERE open_id = $10)
+        )
+        ON CONFLICT (sha256) DO NOTHING
+        RETURNING {}
+        "#,
+        MEDIA_COLUMNS
+    );
+    sqlx::query_as::<_, MediaModel>(&query)
+        .bind(media.key)
+        .bind(media.sha256)
+        .bind(media.content_type)
+        .bind(media.size_bytes)
+        .bind(media.width)
+        .bind(media.height)
+        .bind(media.variants)
+        .bind(media.original_filename)
+        .bind(media.admin_id)
+        .bind(media.member_openid)
+        .fetch_optional(sqlx_pool)
+        .await
+}
+
+// 按上传时间倒序分页，返回当前页和总数
+pub async fn list_media(limit: i64, offset: i64, sqlx_pool: &Pool<Postgres>) -> Result<(Vec<MediaModel>, i64), sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM media ORDER BY created_at DESC, id DESC LIMIT $1 OFFSET $2",
+        MEDIA_COLUMNS
+    );
+    let media = sqlx::query_as::<_, MediaModel>(&query)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(sqlx_pool)
+        .await?;
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media")
+        .fetch_one(sqlx_pool)
+        .await?;
+    Ok((media, total))
+}
+
+pub async fn delete_media_by_keys(keys: &[String], sqlx_pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM media WHERE key = ANY($1)")
+        .bind(keys)
+        .execute(sqlx_pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// 原图和缩略图共用一个分组：<id>.jpg、<id>_200.jpg、<id>_200.webp 的分组都是 <id>
+pub fn media_group(key: &str) -> &str {
+    let stem = key.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(key);
+    match stem.rsplit_once('_') {
+        Some((group, size)) if !size.is_empty() && size.bytes().all(|b| b.is_ascii_digit()) => group,
+        _ => stem,
+    }
+}
+
+// 业务表中保存的是文件名或完整 URL，取最后一段路径作为存储中的文件名
+pub fn reference_key(reference: &str) -> Option<&str> {
+    let reference = reference.trim();
+    let reference = reference.split(['?', '#']).next().unwrap_or_default();
+    let key = reference.rsplit('/').next().unwrap_or_default();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+// 海报、教师头像、教室图片、按钮图标和会员头像中引用的文件分组。
+// 已归档的教师和教室可能恢复，其图片仍算作引用
+pub async fn referenced_groups(sqlx_pool: &Pool<Postgres>) -> Result<HashSet<String>, sqlx::Error> {
+    let query = r#"
+        SELECT image FROM posters
+        UNION ALL SELECT avatar_url FROM teachers WHERE avatar_url IS NOT NULL
+        UNION ALL SELECT unnest(images) FROM locations WHERE images IS NOT NULL
+        UNION ALL SELECT icon FROM action_buttons WHERE icon IS NOT NULL
+        UNION ALL SELECT avatar_url FROM users WHERE avatar_url IS NOT NULL
+    "#;
+    let references = sqlx::query_scalar::<_, Option<String>>(query)
+        .fetch_all(sqlx_pool)
+        .await?;
+    Ok(references
+        .iter()
+        .flatten()
+        .filter_map(|reference| reference_key(reference))
+        .map(|key| media_group(key).to_string())
+        .collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanScan {
+    // 存储中的文件总数
+    pub scanned: usize,
+    // 没有被引用且超过最短保留时间的文件
+    pub orphans: Vec<StoredKey>,
+    pub orphan_bytes: u64,
+    // 没有被引用但上传不久的文件，可能是刚上传还没保存到业务表
+    pub recent: usize,
+}
+
+// 找出存储中没有被任何业务表引用的文件。上传时间取存储中的修改时间，没有时取 media 表的记录时间，
+// 两者都没有时不当作孤立文件
+pub async fn scan_orphans(store: &dyn MediaStore, min_age: Duration, sqlx_pool: &Pool<Postgres>) -> Result<OrphanScan, String> {
+    let stored = store.list().await?;
+    let referenced = referenced_groups(sqlx_pool).await.map_err(|error| error.to_string())?;
+    let uploaded_at: HashMap<String, DateTime<Utc>> = sqlx::query_as::<_, (String, DateTime<Utc>)>(
+        "SELECT key, created_at FROM media WHERE created_at IS NOT NULL",
+    )
+    .fetch_all(sqlx_pool)
+    .await
+    .map_err(|error| error.to_string())?
+    .into_iter()
+    .map(|(key, created_at)| (media_group(&key).to_string(), created_at))
+    .collect();
+
+    let cutoff = Utc::now() - min_age;
+    let scanned = stored.len();
+    let mut orphans = Vec::new();
+    let mut recent = 0;
+    for key in stored {
+        let group = media_group(&key.key);
+        if referenced.contains(group) {
+            continue;
+        }
+        match key.modified_at.or_else(|| uploaded_at.get(group).copied()) {
+            Some(time) if time <= cutoff => orphans.push(key),
+            _ => recent += 1,
+        }
+    }
+    orphans.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(OrphanScan {
+        scanned,
+        orphan_bytes: orphans.iter().map(|key| key.size).sum(),
+        orphans,
+        recent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups() {
+        assert_eq!(media_group("0b7c.jpg"), "0b7c");
+        assert_eq!(media_group("0b7c_200.jpg"), "0b7c");
+        assert_eq!(media_group("0b7c_800.webp"), "0b7c");
+        assert_eq!(media_group("poster_home.png"), "poster_home");
+        assert_eq!(media_group("a_.png"), "a_");
+        assert_eq!(media_group("noext"), "noext");
+    }
+
+    #[test]
+    fn etags() {
+        let media = MediaModel {
+            id: 1,
+            key: "0b7c.jpg".to_string(),
+            sha256: "ab12".to_string(),
+            content_type: "image/jpeg".to_string(),
+            size_bytes: 10,
+            width: 400,
+            height: 200,
+            variants: serde_json::json!({
+                "original": {"key": "0b7c.jpg"},
+                "200": {"key": "0b7c_200.jpg"},
+                "200_webp": {"key": "0b7c_200.webp"}
+            }),
+            original_filename: None,
+            uploaded_by_admin_id: None,
+            uploaded_by_user_id: None,
+            created_at: Utc::now(),
+        };
+        assert_eq!(media.etag("0b7c.jpg").as_deref(), Some("\"ab12\""));
+        assert_eq!(media.etag("0b7c_200.jpg").as_deref(), Some("\"ab12-200\""));
+        assert_eq!(media.etag("0b7c_200.webp").as_deref(), Some("\"ab12-200_webp\""));
+        assert_eq!(media.etag("0b7c_800.jpg"), None);
+    }
+
+    #[test]
+    fn reference_keys() {
+        assert_eq!(reference_key("0b7c.jpg"), Some("0b7c.jpg"));
+        assert_eq!(reference_key("http://127.0.0.1:8002/api/images/0b7c_200.webp"), Some("0b7c_200.webp"));
+        assert_eq!(reference_key("https://cdn.example.com/media/0b7c.jpg?x=1#top"), Some("0b7c.jpg"));
+        assert_eq!(reference_key("https://cdn.example.com/media/"), None);
+        assert_eq!(reference_key("  "), None);
+    }
+}
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Pool, Postgres};
 use serde_json::{json, Value};
 use rust_decimal::Decimal;
+use crate::utils::metrics::METRICS;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct MembershipPlanModel {
@@ -215,7 +216,7 @@ pub async fn purchase_membership_card(
     let user_id = match user_row {
         Some(row) => row.id,
         None => {
-            return Ok(json!({"success": false, "message": "User not found"}));
+            return Ok(json!({"success": false, "code": "USER_NOT_FOUND", "message": "User not found"}));
         }
     };
     
@@ -229,7 +230,7 @@ pub async fn purchase_membership_card(
     let plan = match plan {
         Some(plan) => plan,
         None => {
-            return Ok(json!({"success": false, "message": "套餐不存在或已下架"}));
+            return Ok(json!({"success": false, "code": "PLAN_NOT_FOUND", "message": "套餐不存在或已下架"}));
         }
     };
     
@@ -269,6 +270,7 @@ pub async fn purchase_membership_card(
         .await?;
     
     transaction.commit().await?;
+    METRICS.card_purchases_total.inc();
     
     Ok(json!({
         "success": true, 
@@ -342,4 +344,16 @@ pub async fn get_card_usage(
     };
     
     Ok(result.result)
-}
\ No newline at end of file
+}
+
+// 将已过期但仍为 active 的会员卡标记为 expired，返回更新条数
+pub async fn expire_membership_cards(sqlx_pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    let query = r#"
+        UPDATE user_membership_cards
+        SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'active' AND expires_at < CURRENT_TIMESTAMP
+    "#;
+
+    let result = sqlx::query(query).execute(sqlx_pool).await?;
+    Ok(result.rows_affected())
+}
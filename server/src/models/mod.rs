@@ -1,11 +1,15 @@
 pub mod action_button;
 pub mod lession;
+pub mod lesson_search;
 pub mod admin_user;
 pub mod booking;
+pub mod calendar_token;
 pub mod debug;
 pub mod index;
 pub mod location;
+pub mod media;
 pub mod membership;
+pub mod notification;
 pub mod settings;
 pub mod teacher;
 pub mod user;
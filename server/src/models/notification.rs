@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct NotificationModel {
+    pub id: i32,
+    pub user_id: i32,
+    pub lesson_id: Option<i32>,
+    pub booking_id: Option<i32>,
+    pub notification_type: String,
+    pub title: String,
+    pub content: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+// Helper structs for database operations
+#[derive(FromRow)]
+pub struct JsonResult {
+    pub result: Option<Value>,
+}
+
+// Database operations
+
+// 为某节课所有已确认预约的会员写入一条待发送通知，返回写入条数
+pub async fn enqueue_for_lesson_bookings(
+    lesson_id: i32,
+    notification_type: &str,
+    title: &str,
+    content: &str,
+    conn: &mut PgConnection,
+) -> Result<u64, sqlx::Error> {
+    let query = r#"
+        INSERT INTO notifications (user_id, lesson_id, booking_id, notification_type, title, content)
+        SELECT b.user_id, b.lesson_id, b.id, $2, $3, $4
+        FROM bookings b
+        WHERE b.lesson_id = $1 AND b.status = 'confirmed'
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(lesson_id)
+        .bind(notification_type)
+        .bind(title)
+        .bind(content)
+        .execute(conn)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_user_notifications(openid: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<Value>, sqlx::Error> {
+    let query = r#"
+        SELECT json_agg(
+            jsonb_build_object(
+                'id', n.id,
+                'lesson_id', n.lesson_id,
+                'booking_id', n.booking_id,
+                'notification_type', n.notification_type,
+                'title', n.title,
+                'content', n.content,
+                'created_at', extract(epoch from n.created_at)::bigint,
+                'is_read', n.read_at IS NOT NULL
+            ) ORDER BY n.created_at DESC
+        ) as result
+        FROM notifications n
+        JOIN users u ON n.user_id = u.id
+        WHERE u.open_id = $1
+    "#;
+
+    let row = sqlx::query_as::<_, JsonResult>(query)
+        .bind(openid)
+        .fetch_one(sqlx_pool)
+        .await?;
+
+    Ok(row.result)
+}
@@ -18,6 +18,7 @@ pub struct TeacherModel {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_active: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -108,7 +109,8 @@ pub struct TeacherRatingCreateRequest {
 // }
 
 // Database operations
-pub async fn get_all_teachers(sqlx_pool: &Pool<Postgres>) -> Result<Vec<TeacherModel>, sqlx::Error> {
+// `archived` selects archived (soft-deleted) teachers instead of current ones
+pub async fn get_all_teachers(archived: bool, sqlx_pool: &Pool<Postgres>) -> Result<Vec<TeacherModel>, sqlx::Error> {
     let query = r#"
         SELECT 
             t.id, 
@@ -123,15 +125,18 @@ pub async fn get_all_teachers(sqlx_pool: &Pool<Postgres>) -> Result<Vec<TeacherM
             COUNT(tr.id) as total_ratings,
             t.is_active, 
             t.created_at,
-            t.updated_at
+            t.updated_at,
+            t.deleted_at
         FROM teachers t
         LEFT JOIN teacher_ratings tr ON t.id = tr.teacher_id
+        WHERE (t.deleted_at IS NOT NULL) = $1
         GROUP BY t.id, t.name, t.description, t.avatar_url, t.bio, t.certifications, 
                  t.specialties, t.experience_years, t.is_active, t.created_at
         ORDER BY t.is_active DESC, COALESCE(AVG(tr.rating), 0.0) DESC, t.experience_years DESC
     "#;
     
     sqlx::query_as::<_, TeacherModel>(query)
+        .bind(archived)
         .fetch_all(sqlx_pool)
         .await
 }
@@ -192,9 +197,17 @@ pub async fn update_teacher(data: &TeacherUpdateRequest, sqlx_pool: &Pool<Postgr
     Ok(result.rows_affected() > 0)
 }
 
+// Teachers are archived rather than removed, since past lessons and ratings
+// keep referencing them. Teachers with upcoming lessons cannot be archived
+// until those lessons are reassigned or cancelled.
 pub async fn delete_teacher(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<serde_json::Value, sqlx::Error> {
-    // Check if teacher has any lessons assigned
-    let check_lessons_query = "SELECT COUNT(*) as count FROM lessons WHERE teacher_id = $1 AND is_active = true";
+    let check_lessons_query = r#"
+        SELECT COUNT(*) as count FROM lessons
+        WHERE teacher_id = $1
+          AND deleted_at IS NULL
+          AND cancelled_at IS NULL
+          AND start_time > CURRENT_TIMESTAMP
+    "#;
     
     let (lesson_count,) = sqlx::query_as::<_, (i64,)>(check_lessons_query)
         .bind(id)
@@ -202,59 +215,65 @@ pub async fn delete_teacher(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<serde
         .await?;
     
     if lesson_count > 0 {
-        // Teacher has active lessons, perform soft delete
-        let soft_delete_query = "UPDATE teachers SET is_active = false, updated_at = CURRENT_TIMESTAMP WHERE id = $1";
-        
-        let result = sqlx::query(soft_delete_query)
-            .bind(id)
-            .execute(sqlx_pool)
-            .await?;
-        
-        if result.rows_affected() > 0 {
-            Ok(json!({
-                "success": true, 
-                "message": "Teacher deactivated successfully (has active lessons)",
-                "soft_delete": true
-            }))
-        } else {
-            Ok(json!({
-                "success": false,
-                "message": "Teacher not found"
-            }))
-        }
+        return Ok(json!({
+            "success": false,
+            "code": "HAS_UPCOMING_LESSONS",
+            "message": "Teacher has upcoming lessons, reassign or cancel them first",
+            "upcoming_lessons": lesson_count
+        }));
+    }
+
+    let archive_query = r#"
+        UPDATE teachers
+        SET deleted_at = CURRENT_TIMESTAMP, is_active = false, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NULL
+    "#;
+    
+    let result = sqlx::query(archive_query)
+        .bind(id)
+        .execute(sqlx_pool)
+        .await?;
+    
+    if result.rows_affected() > 0 {
+        Ok(json!({
+            "success": true, 
+            "message": "Teacher archived successfully",
+            "soft_delete": true
+        }))
     } else {
-        // Teacher has no active lessons, safe to hard delete
-        let hard_delete_query = "DELETE FROM teachers WHERE id = $1";
-        
-        let result = sqlx::query(hard_delete_query)
-            .bind(id)
-            .execute(sqlx_pool)
-            .await?;
-        
-        if result.rows_affected() > 0 {
-            Ok(json!({
-                "success": true, 
-                "message": "Teacher deleted successfully",
-                "soft_delete": false
-            }))
-        } else {
-            Ok(json!({
-                "success": false,
-                "message": "Teacher not found"
-            }))
-        }
+        Ok(json!({
+            "success": false,
+            "code": "NOT_FOUND",
+            "message": "老师不存在"
+        }))
     }
 }
 
+pub async fn restore_teacher(id: i32, sqlx_pool: &Pool<Postgres>) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        UPDATE teachers
+        SET deleted_at = NULL, is_active = true, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NOT NULL
+    "#;
+    
+    let result = sqlx::query(query)
+        .bind(id)
+        .execute(sqlx_pool)
+        .await?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
 pub async fn get_teacher_lessons(
     start_time: i32,
     end_time: i32,
     open_id: String,
     class_type: i32,
     teacher_id: i32,
+    timezone: &str,
     sqlx_pool: &Pool<Postgres>
 ) -> Result<serde_json::Value, sqlx::Error> {
-    let query = "select * from fn_teacher_lessons($1,$2,$3,$4,$5)";
+    let query = "select * from fn_teacher_lessons($1,$2,$3,$4,$5,$6)";
     
     sqlx::query_scalar::<_, serde_json::Value>(query)
         .bind(start_time)
@@ -262,6 +281,7 @@ pub async fn get_teacher_lessons(
         .bind(open_id)
         .bind(class_type)
         .bind(teacher_id)
+        .bind(timezone)
         .fetch_one(sqlx_pool)
         .await
 }
\ No newline at end of file
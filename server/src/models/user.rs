@@ -101,6 +101,35 @@ pub async fn create_or_update_user(data: Value, sqlx_pool: &Pool<Postgres>) -> R
     Ok(row.id)
 }
 
+pub enum AvatarUpdate {
+    // 原来的头像地址
+    Updated(Option<String>),
+    UserNotFound,
+}
+
+// 设置已有会员的头像，不会创建会员
+pub async fn set_avatar_url(openid: &str, avatar_url: &str, sqlx_pool: &Pool<Postgres>) -> Result<AvatarUpdate, sqlx::Error> {
+    // 锁住会员，同时上传的两个头像依次替换，原来的头像都能被清理
+    let query = r#"
+        UPDATE users u SET
+            avatar_url = $2,
+            updated_at = CURRENT_TIMESTAMP
+        FROM (SELECT id, avatar_url FROM users WHERE open_id = $1 FOR UPDATE) previous
+        WHERE u.id = previous.id
+        RETURNING previous.avatar_url
+    "#;
+    let previous = sqlx::query_scalar::<_, Option<String>>(query)
+        .bind(openid)
+        .bind(avatar_url)
+        .fetch_optional(sqlx_pool)
+        .await?;
+
+    Ok(match previous {
+        Some(previous) => AvatarUpdate::Updated(previous),
+        None => AvatarUpdate::UserNotFound,
+    })
+}
+
 pub async fn get_user_booking_statistics(openid: &str, sqlx_pool: &Pool<Postgres>) -> Result<Option<Value>, sqlx::Error> {
     let query = r#"
         SELECT row_to_json(t) as result
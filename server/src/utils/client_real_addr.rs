@@ -1,5 +1,7 @@
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
+use ipnet::IpNet;
+
 use rocket::{
     outcome::Outcome,
     request::{self, FromRequest, Request},
@@ -12,38 +14,117 @@ pub struct ClientRealAddr {
     pub ip: IpAddr,
 }
 
+/// Reverse proxies whose forwarding headers are believed, from `server.trusted_proxies`.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn new(proxies: &[String]) -> Result<Self, String> {
+        proxies.iter().map(|proxy| parse_proxy(proxy)).collect::<Result<Vec<_>, _>>().map(TrustedProxies)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Parse a trusted proxy entry, either a CIDR block or a single address.
+pub fn parse_proxy(proxy: &str) -> Result<IpNet, String> {
+    let proxy = proxy.trim();
+    proxy
+        .parse::<IpNet>()
+        .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+        .map(|net| net.trunc())
+        .map_err(|_| format!("\"{}\" is not an IP address or CIDR block", proxy))
+}
+
+/// Parse one node of a forwarding header: a bare IP, `ip:port`, or `[ipv6]:port`, optionally quoted.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Some(rest) = node.strip_prefix('[') {
+        let (ip, port) = rest.split_once(']')?;
+        if !port.is_empty() && port.strip_prefix(':').is_none_or(|port| port.parse::<u16>().is_err()) {
+            return None;
+        }
+        return ip.parse::<Ipv6Addr>().ok().map(IpAddr::V6);
+    }
+    let (ip, port) = node.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    ip.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+}
+
+/// The `for=` nodes of an RFC 7239 `Forwarded` header, client first. Unparseable nodes such as
+/// `for=unknown` or `for=_hidden` are kept as `None`.
+fn forwarded_nodes(header: &str) -> Vec<Option<IpAddr>> {
+    header
+        .split(',')
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                key.trim().eq_ignore_ascii_case("for").then(|| parse_node(value))
+            })
+        })
+        .collect()
+}
+
+/// The addresses listed in `X-Forwarded-For` headers, client first.
+fn x_forwarded_for_nodes<'a, I: IntoIterator<Item = &'a str>>(headers: I) -> Vec<Option<IpAddr>> {
+    headers
+        .into_iter()
+        .flat_map(|header| header.split(','))
+        .filter(|node| !node.trim().is_empty())
+        .map(parse_node)
+        .collect()
+}
+
+/// Walk the forwarding chain from the connecting peer towards the client, skipping trusted proxies.
+/// The first untrusted address is the client. A malformed hop cannot be trusted, so the walk stops
+/// at the last proxy that reported it.
+fn resolve(peer: IpAddr, hops: &[Option<IpAddr>], trusted: &TrustedProxies) -> IpAddr {
+    let mut client = peer;
+    for hop in hops.iter().rev() {
+        if !trusted.contains(&client) {
+            break;
+        }
+        match hop {
+            Some(ip) => client = ip.to_canonical(),
+            None => break,
+        }
+    }
+    client
+}
+
 fn from_request(request: &Request<'_>) -> Option<ClientRealAddr> {
-    match request.real_ip() {
-        Some(ip) => Some(ClientRealAddr {
-            ip,
-        }),
-        None => {
-            let forwarded_for_ip: Option<&str> = request.headers().get("x-forwarded-for").next(); // Only fetch the first one.
-
-            match forwarded_for_ip {
-                Some(forwarded_for_ip) => {
-                    let forwarded_for_ip = forwarded_for_ip.split(',').next(); // Only fetch the first one.
-
-                    match forwarded_for_ip {
-                        Some(forwarded_for_ip) => match forwarded_for_ip.trim().parse::<IpAddr>() {
-                            Ok(ip) => Some(ClientRealAddr {
-                                ip,
-                            }),
-                            Err(_) => request.remote().map(|addr| ClientRealAddr {
-                                ip: addr.ip()
-                            }),
-                        },
-                        None => request.remote().map(|addr| ClientRealAddr {
-                            ip: addr.ip()
-                        }),
-                    }
-                },
-                None => request.remote().map(|addr| ClientRealAddr {
-                    ip: addr.ip()
-                }),
-            }
-        },
+    let peer = request.remote()?.ip().to_canonical();
+    let trusted = match request.rocket().state::<TrustedProxies>() {
+        Some(trusted) => trusted,
+        None => return Some(ClientRealAddr { ip: peer }),
+    };
+    if !trusted.contains(&peer) {
+        // 客户端直连时转发头都可以伪造，直接使用连接地址
+        return Some(ClientRealAddr { ip: peer });
     }
+
+    let headers = request.headers();
+    // 优先使用标准的 Forwarded，其次是 X-Forwarded-For，最后是只有一个地址的 X-Real-IP
+    let forwarded: Vec<&str> = headers.get("forwarded").collect();
+    let hops = if !forwarded.is_empty() {
+        forwarded.iter().flat_map(|header| forwarded_nodes(header)).collect()
+    } else if headers.contains("x-forwarded-for") {
+        x_forwarded_for_nodes(headers.get("x-forwarded-for"))
+    } else if let Some(real_ip) = headers.get_one("x-real-ip") {
+        vec![parse_node(real_ip)]
+    } else {
+        Vec::new()
+    };
+
+    Some(ClientRealAddr {
+        ip: resolve(peer, &hops, trusted),
+    })
 }
 
 #[rocket::async_trait]
@@ -104,4 +185,108 @@ impl ClientRealAddr {
             IpAddr::V6(ipv6) => ipv6.to_string(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trusted(proxies: &[&str]) -> TrustedProxies {
+        TrustedProxies::new(&proxies.iter().map(|proxy| proxy.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_proxy_entries() {
+        assert_eq!(parse_proxy("10.0.0.0/8").unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!(parse_proxy("10.1.2.3/8").unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!(parse_proxy("127.0.0.1").unwrap().to_string(), "127.0.0.1/32");
+        assert_eq!(parse_proxy("::1").unwrap().to_string(), "::1/128");
+        assert_eq!(parse_proxy("fd00::/8").unwrap().to_string(), "fd00::/8");
+        assert!(parse_proxy("10.0.0.0/33").is_err());
+        assert!(parse_proxy("proxy.local").is_err());
+        assert!(parse_proxy("").is_err());
+    }
+
+    #[test]
+    fn parses_nodes() {
+        assert_eq!(parse_node(" 203.0.113.7 "), Some(ip("203.0.113.7")));
+        assert_eq!(parse_node("203.0.113.7:8080"), Some(ip("203.0.113.7")));
+        assert_eq!(parse_node("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("\"[2001:db8:cafe::17]:4711\""), Some(ip("2001:db8:cafe::17")));
+        assert_eq!(parse_node("[2001:db8::1]"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("[2001:db8::1]x"), None);
+        assert_eq!(parse_node("203.0.113.7:http"), None);
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_hidden"), None);
+        assert_eq!(parse_node(""), None);
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_headers() {
+        let hops = x_forwarded_for_nodes(["1.1.1.1"]);
+        assert_eq!(resolve(ip("203.0.113.7"), &hops, &trusted(&["10.0.0.0/8"])), ip("203.0.113.7"));
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &TrustedProxies::default()), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn x_forwarded_for_stops_at_first_untrusted_hop() {
+        let proxies = trusted(&["10.0.0.0/8", "192.168.1.1"]);
+        // 客户端伪造了 1.1.1.1，真实地址由第一层代理追加
+        let hops = x_forwarded_for_nodes(["1.1.1.1, 203.0.113.7", "192.168.1.1"]);
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn all_trusted_hops_resolve_to_leftmost() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let hops = x_forwarded_for_nodes(["10.0.0.9, 10.0.0.8"]);
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("10.0.0.9"));
+        assert_eq!(resolve(ip("10.0.0.2"), &[], &proxies), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn malformed_hop_stops_the_walk() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let hops = x_forwarded_for_nodes(["1.1.1.1, not-an-ip, 10.0.0.5"]);
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("10.0.0.5"));
+        let hops = x_forwarded_for_nodes(["1.1.1.1, garbage"]);
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn ipv6_chain() {
+        let proxies = trusted(&["fd00::/8", "::1"]);
+        let hops = x_forwarded_for_nodes(["2001:db8::7, fd00::2"]);
+        assert_eq!(resolve(ip("::1"), &hops, &proxies), ip("2001:db8::7"));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_ranges() {
+        let proxies = trusted(&["127.0.0.1"]);
+        let hops = x_forwarded_for_nodes(["::ffff:203.0.113.7"]);
+        assert_eq!(resolve(ip("::ffff:127.0.0.1").to_canonical(), &hops, &proxies), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn forwarded_header() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let hops = forwarded_nodes(
+            "for=1.1.1.1, For=\"[2001:db8:cafe::17]:4711\";proto=https;by=10.0.0.3, for=10.0.0.4",
+        );
+        assert_eq!(hops, vec![Some(ip("1.1.1.1")), Some(ip("2001:db8:cafe::17")), Some(ip("10.0.0.4"))]);
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("2001:db8:cafe::17"));
+    }
+
+    #[test]
+    fn forwarded_header_with_obfuscated_node() {
+        let proxies = trusted(&["10.0.0.0/8"]);
+        let hops = forwarded_nodes("for=203.0.113.7, for=_hidden, for=10.0.0.4");
+        assert_eq!(resolve(ip("10.0.0.2"), &hops, &proxies), ip("10.0.0.4"));
+        // 没有 for= 的元素不算一跳
+        assert_eq!(forwarded_nodes("proto=https;by=10.0.0.3"), vec![]);
+    }
+}
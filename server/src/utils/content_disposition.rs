@@ -1,32 +1,39 @@
-use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::Header;
-use rocket::{Request, Response};
+use std::io::Cursor;
 
-use super::string::StringExt;
+use rocket::http::{ContentType, Header};
+use rocket::response::{self, Responder, Response};
+use rocket::Request;
 
-pub struct ContentDisposition;
+// 作为附件下载的响应，浏览器按 filename 保存
+pub struct Attachment {
+    bytes: Vec<u8>,
+    content_type: ContentType,
+    filename: String,
+}
 
-#[rocket::async_trait]
-impl Fairing for ContentDisposition {
-    fn info(&self) -> Info {
-        Info {
-            name: "Attaching ContentDisposition headers to responses",
-            kind: Kind::Response,
+impl Attachment {
+    pub fn new(bytes: Vec<u8>, content_type: ContentType, filename: impl Into<String>) -> Attachment {
+        Attachment {
+            bytes,
+            content_type,
+            filename: filename.into(),
         }
     }
+}
 
-    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        // https://api.rocket.rs/v0.4/rocket/struct.Request.html#method.uri
-        let p = request.uri().path();
-        if p=="/yoga/admin/schedule" {
-            // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Disposition
-            response.set_header(Header::new(
-                "Content-Disposition",
-                format!(
-                    "attachment; filename=\"{}\"",
-                    "1.png"
-                ),
-            ));
-        }
+impl<'r> Responder<'r, 'static> for Attachment {
+    fn respond_to(self, _request: &'r Request<'_>) -> response::Result<'static> {
+        // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Disposition
+        // 文件名只保留 ASCII 字母、数字和 -_.，避免引号和换行破坏响应头
+        let filename: String = self
+            .filename
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+            .collect();
+        Response::build()
+            .header(self.content_type)
+            .header(Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+            .sized_body(self.bytes.len(), Cursor::new(self.bytes))
+            .ok()
     }
 }
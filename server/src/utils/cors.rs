@@ -1,8 +1,50 @@
+use std::io::Cursor;
+
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::Header;
+use rocket::http::{Header, Method, Status};
 use rocket::{Request, Response};
 
-pub struct CORS;
+use crate::models::settings::CorsSettings;
+
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+// 前端需要读取的响应头
+const EXPOSED_HEADERS: &str = "X-Request-Id, Retry-After, Content-Disposition";
+
+// 只给 cors.allowed_origins 中的来源返回跨域响应头。
+// 没有路由处理 OPTIONS，预检请求在这里直接改写为 204。
+pub struct CORS {
+    allowed_origins: Vec<String>,
+    allow_credentials: bool,
+    max_age_secs: u32,
+}
+
+impl CORS {
+    pub fn new(settings: &CorsSettings) -> Self {
+        CORS {
+            allowed_origins: settings.allowed_origins.iter().map(|origin| normalize_origin(origin)).collect(),
+            allow_credentials: settings.allow_credentials,
+            max_age_secs: settings.max_age_secs,
+        }
+    }
+
+    fn any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    fn is_allowed(&self, origin: &str) -> bool {
+        let origin = normalize_origin(origin);
+        self.any_origin() || self.allowed_origins.contains(&origin)
+    }
+}
+
+// scheme 和主机名不区分大小写，去掉末尾的 /
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+fn is_preflight(request: &Request<'_>) -> bool {
+    request.method() == Method::Options && request.headers().contains("Access-Control-Request-Method")
+}
 
 #[rocket::async_trait]
 impl Fairing for CORS {
@@ -13,10 +55,151 @@ impl Fairing for CORS {
         }
     }
 
-    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
-        response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Methods", "POST, GET, PATCH, OPTIONS"));
-        response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(origin) = request.headers().get_one("Origin") else {
+            return;
+        };
+        let preflight = is_preflight(request);
+        if !self.is_allowed(origin) {
+            if preflight {
+                tracing::warn!(origin = origin, path = %request.uri().path(), "CORS preflight from origin not in cors.allowed_origins");
+                response.set_status(Status::Forbidden);
+                response.remove_header("Content-Type");
+                response.set_sized_body(0, Cursor::new(""));
+            }
+            return;
+        }
+
+        if self.any_origin() {
+            response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+        } else {
+            response.set_header(Header::new("Access-Control-Allow-Origin", origin.to_string()));
+            response.adjoin_header(Header::new("Vary", "Origin"));
+            if self.allow_credentials {
+                response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+            }
+        }
+
+        if preflight {
+            response.set_status(Status::NoContent);
+            response.remove_header("Content-Type");
+            response.set_sized_body(0, Cursor::new(""));
+            response.set_header(Header::new("Access-Control-Allow-Methods", ALLOWED_METHODS));
+            if let Some(headers) = request.headers().get_one("Access-Control-Request-Headers") {
+                response.set_header(Header::new("Access-Control-Allow-Headers", headers.to_string()));
+            }
+            response.set_header(Header::new("Access-Control-Max-Age", self.max_age_secs.to_string()));
+        } else {
+            response.set_header(Header::new("Access-Control-Expose-Headers", EXPOSED_HEADERS));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::blocking::Client;
+
+    #[rocket::put("/api/admin/notices/<id>")]
+    fn update_notice(id: i32) -> String {
+        id.to_string()
+    }
+
+    fn client(allowed_origins: &[&str], allow_credentials: bool) -> Client {
+        let settings = CorsSettings {
+            allowed_origins: allowed_origins.iter().map(|origin| origin.to_string()).collect(),
+            allow_credentials,
+            max_age_secs: 600,
+        };
+        let rocket = rocket::build()
+            .attach(CORS::new(&settings))
+            .mount("/", rocket::routes![update_notice]);
+        Client::tracked(rocket).expect("valid rocket")
+    }
+
+    #[test]
+    fn preflight_from_allowed_origin() {
+        let client = client(&["https://admin.example.com/"], true);
+        let response = client
+            .options("/api/admin/notices/1")
+            .header(Header::new("Origin", "https://Admin.example.com"))
+            .header(Header::new("Access-Control-Request-Method", "PUT"))
+            .header(Header::new("Access-Control-Request-Headers", "authorization, content-type"))
+            .dispatch();
+        let headers = response.headers();
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(headers.get_one("Access-Control-Allow-Origin"), Some("https://Admin.example.com"));
+        assert_eq!(headers.get_one("Access-Control-Allow-Credentials"), Some("true"));
+        assert_eq!(headers.get_one("Access-Control-Allow-Methods"), Some(ALLOWED_METHODS));
+        assert_eq!(headers.get_one("Access-Control-Allow-Headers"), Some("authorization, content-type"));
+        assert_eq!(headers.get_one("Access-Control-Max-Age"), Some("600"));
+        assert_eq!(headers.get_one("Vary"), Some("Origin"));
+        assert!(response.into_string().unwrap_or_default().is_empty());
+    }
+
+    #[test]
+    fn preflight_from_other_origin_is_rejected() {
+        let client = client(&["https://admin.example.com"], true);
+        let response = client
+            .options("/api/admin/notices/1")
+            .header(Header::new("Origin", "https://evil.example.com"))
+            .header(Header::new("Access-Control-Request-Method", "DELETE"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+    }
+
+    #[test]
+    fn actual_request_from_allowed_origin() {
+        let client = client(&["https://admin.example.com"], true);
+        let response = client
+            .put("/api/admin/notices/7")
+            .header(Header::new("Origin", "https://admin.example.com"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), Some("https://admin.example.com"));
+        assert_eq!(response.headers().get_one("Access-Control-Expose-Headers"), Some(EXPOSED_HEADERS));
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Methods"), None);
+        assert_eq!(response.into_string().as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn actual_request_from_other_origin_has_no_cors_headers() {
+        let client = client(&["https://admin.example.com"], true);
+        let response = client
+            .put("/api/admin/notices/7")
+            .header(Header::new("Origin", "https://evil.example.com"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Credentials"), None);
+    }
+
+    #[test]
+    fn request_without_origin_is_untouched() {
+        let client = client(&["https://admin.example.com"], true);
+        let response = client.put("/api/admin/notices/7").dispatch();
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+
+        // 不是预检的 OPTIONS 请求照常返回 404
+        let response = client
+            .options("/api/admin/notices/7")
+            .header(Header::new("Origin", "https://admin.example.com"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn wildcard_origin_never_sends_credentials() {
+        let client = client(&["*"], false);
+        let response = client
+            .options("/api/admin/notices/1")
+            .header(Header::new("Origin", "https://anything.example.com"))
+            .header(Header::new("Access-Control-Request-Method", "POST"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Credentials"), None);
+        assert_eq!(response.headers().get_one("Vary"), None);
     }
-}
\ No newline at end of file
+}
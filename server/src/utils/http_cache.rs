@@ -0,0 +1,376 @@
+use std::convert::Infallible;
+use std::io::Cursor;
+
+use chrono::{DateTime, Utc};
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use sha2::{Digest, Sha256};
+
+// 图片等不常变化的内容：使用调用方提供的强 ETag，处理 If-None-Match、If-Modified-Since 返回 304，
+// 支持单个字节范围的 Range 请求（206 / 416），多个范围时返回完整内容。
+
+// 文件名中带有唯一 id 的文件内容不会变化，客户端可以一直使用缓存
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+// 其他文件每次使用前向服务器确认，没有变化时返回 304
+const REVALIDATE: &str = "public, no-cache";
+
+// 带有校验信息的存储文件，body 为 None 时调用方已确认客户端缓存有效，直接返回 304
+pub struct CachedObject {
+    body: Option<(Vec<u8>, ContentType)>,
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+    immutable: bool,
+}
+
+impl CachedObject {
+    pub fn new(
+        bytes: Vec<u8>,
+        content_type: ContentType,
+        etag: String,
+        modified_at: Option<DateTime<Utc>>,
+        immutable: bool,
+    ) -> Self {
+        CachedObject {
+            body: Some((bytes, content_type)),
+            ..CachedObject::not_modified(etag, modified_at, immutable)
+        }
+    }
+
+    pub fn not_modified(etag: String, modified_at: Option<DateTime<Utc>>, immutable: bool) -> Self {
+        CachedObject {
+            body: None,
+            etag,
+            // HTTP 日期只精确到秒
+            last_modified: modified_at.and_then(|modified_at| DateTime::from_timestamp(modified_at.timestamp(), 0)),
+            immutable,
+        }
+    }
+
+    fn cached_by_client(&self, request: &Request<'_>) -> bool {
+        // 同时带有两者时只看 If-None-Match
+        if let Some(if_none_match) = request.headers().get_one("If-None-Match") {
+            return etag_matches(if_none_match, &self.etag, false);
+        }
+        match (
+            request.headers().get_one("If-Modified-Since").and_then(parse_http_date),
+            self.last_modified,
+        ) {
+            (Some(since), Some(last_modified)) => last_modified <= since,
+            _ => false,
+        }
+    }
+
+    // If-Range 与当前内容不一致时忽略 Range，返回完整内容
+    fn range_applies(&self, request: &Request<'_>) -> bool {
+        match request.headers().get_one("If-Range") {
+            None => true,
+            Some(if_range) => match parse_http_date(if_range) {
+                Some(date) => Some(date) == self.last_modified,
+                None => etag_matches(if_range, &self.etag, true),
+            },
+        }
+    }
+}
+
+// 请求中的 If-None-Match，已知 ETag 时可以在读取内容之前判断是否返回 304
+pub struct IfNoneMatch(Option<String>);
+
+impl IfNoneMatch {
+    pub fn matches(&self, etag: &str) -> bool {
+        self.0.as_deref().is_some_and(|header| etag_matches(header, etag, false))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        Outcome::Success(IfNoneMatch(request.headers().get_one("If-None-Match").map(str::to_string)))
+    }
+}
+
+// 没有媒体库记录的文件按内容计算 ETag
+pub fn strong_etag(bytes: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(Sha256::digest(bytes)))
+}
+
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim()).ok().map(|time| time.with_timezone(&Utc))
+}
+
+// If-None-Match 使用弱比较（忽略 W/），If-Range 使用强比较（弱 ETag 不匹配）
+fn etag_matches(header: &str, etag: &str, strong: bool) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        if candidate == "*" {
+            return !strong;
+        }
+        match candidate.strip_prefix("W/") {
+            Some(weak) => !strong && weak == etag,
+            None => candidate == etag,
+        }
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    // 没有 Range、格式不对或有多个范围，返回完整内容
+    Full,
+    // 包含两端
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+pub fn parse_range(header: &str, len: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // bytes=-500：最后 500 个字节
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial {
+                start: len.saturating_sub(suffix),
+                end: len - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.min(len - 1),
+    }
+}
+
+impl<'r> Responder<'r, 'static> for CachedObject {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.header(Header::new("ETag", self.etag.clone()));
+        response.header(Header::new("Cache-Control", if self.immutable { IMMUTABLE } else { REVALIDATE }));
+        response.header(Header::new("Accept-Ranges", "bytes"));
+        if let Some(last_modified) = self.last_modified {
+            response.header(Header::new("Last-Modified", http_date(last_modified)));
+        }
+
+        let cached = self.cached_by_client(request);
+        let range_applies = self.range_applies(request);
+        let (bytes, content_type) = match self.body {
+            Some(body) if !cached => body,
+            _ => return response.status(Status::NotModified).ok(),
+        };
+
+        let len = bytes.len() as u64;
+        let range = match request.headers().get_one("Range") {
+            Some(range) if range_applies => parse_range(range, len),
+            _ => ByteRange::Full,
+        };
+        match range {
+            ByteRange::Full => response
+                .header(content_type)
+                .sized_body(bytes.len(), Cursor::new(bytes))
+                .ok(),
+            ByteRange::Partial { start, end } => {
+                let body = bytes[start as usize..=end as usize].to_vec();
+                response
+                    .status(Status::PartialContent)
+                    .header(content_type)
+                    .header(Header::new("Content-Range", format!("bytes {}-{}/{}", start, end, len)))
+                    .sized_body(body.len(), Cursor::new(body))
+                    .ok()
+            }
+            ByteRange::Unsatisfiable => response
+                .status(Status::RangeNotSatisfiable)
+                .header(Header::new("Content-Range", format!("bytes */{}", len)))
+                .ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rocket::local::blocking::Client;
+
+    const BODY: &[u8] = b"0123456789";
+
+    fn modified_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 1, 8, 30, 15).unwrap()
+    }
+
+    #[rocket::get("/hashed")]
+    fn hashed() -> CachedObject {
+        CachedObject::new(BODY.to_vec(), ContentType::PNG, strong_etag(BODY), Some(modified_at()), true)
+    }
+
+    #[rocket::get("/named")]
+    fn named() -> CachedObject {
+        CachedObject::new(BODY.to_vec(), ContentType::PNG, strong_etag(BODY), Some(modified_at()), false)
+    }
+
+    // 与 serve_image 相同：ETag 已知时先判断 If-None-Match，不匹配才读取内容
+    #[rocket::get("/known")]
+    fn known(if_none_match: IfNoneMatch) -> CachedObject {
+        let etag = "\"known\"".to_string();
+        if if_none_match.matches(&etag) {
+            return CachedObject::not_modified(etag, Some(modified_at()), true);
+        }
+        CachedObject::new(BODY.to_vec(), ContentType::PNG, etag, Some(modified_at()), true)
+    }
+
+    fn client() -> Client {
+        Client::tracked(rocket::build().mount("/", rocket::routes![hashed, named, known])).expect("valid rocket")
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("bytes=0-3", 10), ByteRange::Partial { start: 0, end: 3 });
+        assert_eq!(parse_range("bytes=4-", 10), ByteRange::Partial { start: 4, end: 9 });
+        assert_eq!(parse_range("bytes=5-100", 10), ByteRange::Partial { start: 5, end: 9 });
+        assert_eq!(parse_range("bytes=-3", 10), ByteRange::Partial { start: 7, end: 9 });
+        assert_eq!(parse_range("bytes=-30", 10), ByteRange::Partial { start: 0, end: 9 });
+        assert_eq!(parse_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=5-2", 10), ByteRange::Full);
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 10), ByteRange::Full);
+        assert_eq!(parse_range("bytes=a-b", 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn etags() {
+        let etag = strong_etag(BODY);
+        assert!(etag_matches(&etag, &etag, true));
+        assert!(etag_matches(&format!("\"other\", W/{}", etag), &etag, false));
+        assert!(!etag_matches(&format!("W/{}", etag), &etag, true));
+        assert!(etag_matches("*", &etag, false));
+        assert!(!etag_matches("\"other\"", &etag, false));
+    }
+
+    #[test]
+    fn http_dates() {
+        assert_eq!(http_date(modified_at()), "Thu, 01 Oct 2026 08:30:15 GMT");
+        assert_eq!(parse_http_date("Thu, 01 Oct 2026 08:30:15 GMT"), Some(modified_at()));
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+
+    #[test]
+    fn full_response_has_validators() {
+        let client = client();
+        let response = client.get("/hashed").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("ETag"), Some(strong_etag(BODY).as_str()));
+        assert_eq!(response.headers().get_one("Cache-Control"), Some(IMMUTABLE));
+        assert_eq!(response.headers().get_one("Last-Modified"), Some("Thu, 01 Oct 2026 08:30:15 GMT"));
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+        assert_eq!(response.into_bytes(), Some(BODY.to_vec()));
+
+        let response = client.get("/named").dispatch();
+        assert_eq!(response.headers().get_one("Cache-Control"), Some(REVALIDATE));
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let client = client();
+        let response = client
+            .get("/named")
+            .header(Header::new("If-None-Match", strong_etag(BODY)))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("ETag"), Some(strong_etag(BODY).as_str()));
+        assert!(response.into_bytes().unwrap_or_default().is_empty());
+
+        let response = client
+            .get("/named")
+            .header(Header::new("If-Modified-Since", "Thu, 01 Oct 2026 08:30:15 GMT"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+
+        let response = client
+            .get("/named")
+            .header(Header::new("If-Modified-Since", "Wed, 30 Sep 2026 08:30:15 GMT"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        // If-None-Match 不匹配时忽略 If-Modified-Since
+        let response = client
+            .get("/named")
+            .header(Header::new("If-None-Match", "\"stale\""))
+            .header(Header::new("If-Modified-Since", "Thu, 01 Oct 2026 08:30:15 GMT"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn known_etag() {
+        let client = client();
+        let response = client.get("/known").header(Header::new("If-None-Match", "W/\"known\"")).dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("ETag"), Some("\"known\""));
+        assert_eq!(response.headers().get_one("Cache-Control"), Some(IMMUTABLE));
+        assert_eq!(response.headers().get_one("Last-Modified"), Some("Thu, 01 Oct 2026 08:30:15 GMT"));
+        assert!(response.into_bytes().unwrap_or_default().is_empty());
+
+        let response = client.get("/known").header(Header::new("If-None-Match", "\"stale\"")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_bytes(), Some(BODY.to_vec()));
+    }
+
+    #[test]
+    fn range_requests() {
+        let client = client();
+        let response = client.get("/hashed").header(Header::new("Range", "bytes=2-5")).dispatch();
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 2-5/10"));
+        assert_eq!(response.into_bytes(), Some(b"2345".to_vec()));
+
+        let response = client.get("/hashed").header(Header::new("Range", "bytes=20-")).dispatch();
+        assert_eq!(response.status(), Status::RangeNotSatisfiable);
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes */10"));
+
+        let response = client
+            .get("/hashed")
+            .header(Header::new("Range", "bytes=2-5"))
+            .header(Header::new("If-Range", "\"stale\""))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_bytes(), Some(BODY.to_vec()));
+
+        let response = client
+            .get("/hashed")
+            .header(Header::new("Range", "bytes=-2"))
+            .header(Header::new("If-Range", strong_etag(BODY)))
+            .dispatch();
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.into_bytes(), Some(b"89".to_vec()));
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+
+// iCalendar（RFC 5545）日历，供手机和电脑上的日历应用订阅
+pub struct Calendar {
+    pub name: String,
+    // 日历应用显示时间使用的时区，如 Asia/Hong_Kong
+    pub timezone: String,
+    // 建议日历应用刷新订阅的间隔，单位为分钟
+    pub refresh_minutes: Option<u32>,
+    pub events: Vec<CalendarEvent>,
+}
+
+pub struct CalendarEvent {
+    // 同一活动在每次生成时保持不变，日历应用据此更新而不是重复添加
+    pub uid: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: String,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    // 活动最后修改的时间，没有时取生成日历的时间
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Calendar {
+    pub fn to_ics(&self) -> String {
+        let now = Utc::now();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//YogaServer//Schedule//ZH".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:PUBLISH".to_string(),
+            format!("X-WR-CALNAME:{}", escape(&self.name)),
+            format!("X-WR-TIMEZONE:{}", escape(&self.timezone)),
+        ];
+        if let Some(minutes) = self.refresh_minutes {
+            lines.push(format!("REFRESH-INTERVAL;VALUE=DURATION:PT{}M", minutes));
+            lines.push(format!("X-PUBLISHED-TTL:PT{}M", minutes));
+        }
+        for event in &self.events {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", escape(&event.uid)));
+            lines.push(format!("DTSTAMP:{}", date_time(event.updated_at.unwrap_or(now))));
+            lines.push(format!("DTSTART:{}", date_time(event.start)));
+            lines.push(format!("DTEND:{}", date_time(event.end)));
+            lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+            if let Some(location) = event.location.as_deref().filter(|location| !location.is_empty()) {
+                lines.push(format!("LOCATION:{}", escape(location)));
+            }
+            if let Some(description) = event.description.as_deref().filter(|description| !description.is_empty()) {
+                lines.push(format!("DESCRIPTION:{}", escape(description)));
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut ics = String::new();
+        for line in lines {
+            fold(&line, &mut ics);
+        }
+        ics
+    }
+}
+
+// 活动 UID 的域名部分，取服务地址中的主机名
+pub fn uid_domain(server_url: &str) -> &str {
+    let host = server_url.split_once("://").map(|(_, rest)| rest).unwrap_or(server_url);
+    host.split(['/', ':']).next().unwrap_or(host)
+}
+
+fn date_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// TEXT 类型中的反斜杠、分号、逗号和换行需要转义
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 每行不超过 75 个字节，超出时换行并以空格开头续行，不拆开 UTF-8 字符
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("流瑜伽; 初级, 周一\n\\"), "流瑜伽\\; 初级\\, 周一\\n\\\\");
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut out = String::new();
+        let line = format!("SUMMARY:{}", "瑜".repeat(40));
+        fold(&line, &mut out);
+        let lines: Vec<&str> = out.split("\r\n").filter(|line| !line.is_empty()).collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        let unfolded: String = out.replace("\r\n ", "").trim_end().to_string();
+        assert_eq!(unfolded, line);
+    }
+
+    #[test]
+    fn uid_domains() {
+        assert_eq!(uid_domain("https://yoga.example.com/api"), "yoga.example.com");
+        assert_eq!(uid_domain("http://127.0.0.1:8002"), "127.0.0.1");
+    }
+
+    #[test]
+    fn calendar() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 20, 1, 0, 0).unwrap();
+        let calendar = Calendar {
+            name: "课表".to_string(),
+            timezone: "Asia/Hong_Kong".to_string(),
+            refresh_minutes: Some(60),
+            events: vec![CalendarEvent {
+                uid: "lesson-7@yoga".to_string(),
+                start,
+                end: start + chrono::Duration::hours(1),
+                summary: "流瑜伽".to_string(),
+                location: Some("一号教室".to_string()),
+                description: None,
+                updated_at: Some(start),
+            }],
+        };
+        let ics = calendar.to_ics();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20261020T010000Z\r\nDTEND:20261020T020000Z\r\n"));
+        assert!(ics.contains("\r\nLOCATION:一号教室\r\n"));
+        assert!(!ics.contains("DESCRIPTION"));
+        assert!(ics.contains("\r\nREFRESH-INTERVAL;VALUE=DURATION:PT60M\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+}
@@ -0,0 +1,370 @@
+use std::fmt;
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use image::{ColorType, DynamicImage, ImageEncoder, ImageFormat};
+use rocket::http::ContentType;
+
+// 上传图片的处理：按文件头识别格式（不信任客户端的 Content-Type），检查像素数，
+// 解码后按 EXIF 方向摆正再重新编码，EXIF 等元数据不会写入新文件。
+// JPEG 仍保存为 JPEG，其余格式保存为 PNG；GIF 只保留第一帧。
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug)]
+pub enum ImageError {
+    // 不是 JPEG、PNG、GIF、WebP
+    Unsupported,
+    TooManyPixels { width: u32, height: u32, max_pixels: u64 },
+    Corrupt(String),
+    Encode(String),
+}
+
+impl ImageError {
+    pub fn message(&self) -> String {
+        match self {
+            ImageError::Unsupported => "Only image files are allowed (JPEG, PNG, GIF, WebP)".to_string(),
+            ImageError::TooManyPixels { width, height, max_pixels } => format!(
+                "Image is too large ({}x{}), at most {} pixels are allowed",
+                width, height, max_pixels
+            ),
+            ImageError::Corrupt(_) => "Image file is corrupt or truncated".to_string(),
+            ImageError::Encode(_) => "Failed to process image".to_string(),
+        }
+    }
+}
+
+// 写入日志的说明，包含解码或编码失败的具体原因
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Unsupported => write!(f, "unsupported image format"),
+            ImageError::TooManyPixels { width, height, max_pixels } => {
+                write!(f, "image is {}x{}, more than {} pixels", width, height, max_pixels)
+            }
+            ImageError::Corrupt(detail) => write!(f, "corrupt image: {}", detail),
+            ImageError::Encode(detail) => write!(f, "failed to encode image: {}", detail),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::WebP => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        match self {
+            OutputFormat::Jpeg => ContentType::JPEG,
+            OutputFormat::Png => ContentType::PNG,
+            OutputFormat::WebP => ContentType::WEBP,
+        }
+    }
+}
+
+pub struct EncodedImage {
+    // "original"，缩略图为 "200"、"200_webp" 这样的尺寸加格式
+    pub name: String,
+    pub format: OutputFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+// 识别并检查上传的图片，返回原图和各尺寸缩略图，第一个为原图
+pub fn process_upload(bytes: &[u8], max_pixels: u64, thumbnail_sizes: &[u32]) -> Result<Vec<EncodedImage>, ImageError> {
+    let image = decode(bytes, max_pixels)?;
+    encode_with_thumbnails(&image, output_format(bytes), thumbnail_sizes)
+}
+
+// 头像：居中裁剪为正方形，边长不超过 size，再按 thumbnail_sizes 生成缩略图
+pub fn process_avatar(bytes: &[u8], max_pixels: u64, size: u32, thumbnail_sizes: &[u32]) -> Result<Vec<EncodedImage>, ImageError> {
+    let image = crop_square(decode(bytes, max_pixels)?);
+    let image = if image.width() > size {
+        image.resize_exact(size, size, FilterType::CatmullRom)
+    } else {
+        image
+    };
+    let thumbnail_sizes: Vec<u32> = thumbnail_sizes.iter().copied().filter(|&thumbnail| thumbnail < image.width()).collect();
+    encode_with_thumbnails(&image, output_format(bytes), &thumbnail_sizes)
+}
+
+fn crop_square(image: DynamicImage) -> DynamicImage {
+    let side = image.width().min(image.height());
+    image.crop_imm((image.width() - side) / 2, (image.height() - side) / 2, side, side)
+}
+
+fn output_format(bytes: &[u8]) -> OutputFormat {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Jpeg) => OutputFormat::Jpeg,
+        _ => OutputFormat::Png,
+    }
+}
+
+fn encode_with_thumbnails(image: &DynamicImage, format: OutputFormat, thumbnail_sizes: &[u32]) -> Result<Vec<EncodedImage>, ImageError> {
+    let mut images = vec![encode("original".to_string(), image, format)?];
+    for &size in thumbnail_sizes {
+        // 不放大比缩略图还小的图片
+        let thumbnail = if image.width() > size || image.height() > size {
+            image.resize(size, size, FilterType::CatmullRom)
+        } else {
+            image.clone()
+        };
+        images.push(encode(size.to_string(), &thumbnail, format)?);
+        images.push(encode(format!("{}_webp", size), &thumbnail, OutputFormat::WebP)?);
+    }
+    Ok(images)
+}
+
+// 按文件头识别格式并解码，解码前先从文件头读取尺寸，避免解码超大图片
+pub fn decode(bytes: &[u8], max_pixels: u64) -> Result<DynamicImage, ImageError> {
+    let format = match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP)) => format,
+        _ => return Err(ImageError::Unsupported),
+    };
+    let (width, height) = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|error| ImageError::Corrupt(error.to_string()))?;
+    if width as u64 * height as u64 > max_pixels {
+        return Err(ImageError::TooManyPixels { width, height, max_pixels });
+    }
+    let image = ImageReader::with_format(Cursor::new(bytes), format)
+        .decode()
+        .map_err(|error| ImageError::Corrupt(error.to_string()))?;
+    Ok(apply_orientation(image, exif_orientation(bytes)))
+}
+
+// EXIF Orientation，没有 EXIF 或读取失败时为 1（不旋转）
+fn exif_orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+pub fn encode(name: String, image: &DynamicImage, format: OutputFormat) -> Result<EncodedImage, ImageError> {
+    let (width, height) = (image.width(), image.height());
+    let mut bytes = Vec::new();
+    let result = match format {
+        OutputFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).write_image(
+            image.to_rgb8().as_raw(),
+            width,
+            height,
+            ColorType::Rgb8,
+        ),
+        OutputFormat::Png if image.color().has_alpha() => {
+            PngEncoder::new(&mut bytes).write_image(image.to_rgba8().as_raw(), width, height, ColorType::Rgba8)
+        }
+        OutputFormat::Png => PngEncoder::new(&mut bytes).write_image(image.to_rgb8().as_raw(), width, height, ColorType::Rgb8),
+        // image 只提供无损 WebP 编码
+        OutputFormat::WebP if image.color().has_alpha() => {
+            WebPEncoder::new_lossless(&mut bytes).write_image(image.to_rgba8().as_raw(), width, height, ColorType::Rgba8)
+        }
+        OutputFormat::WebP => {
+            WebPEncoder::new_lossless(&mut bytes).write_image(image.to_rgb8().as_raw(), width, height, ColorType::Rgb8)
+        }
+    };
+    result.map_err(|error| ImageError::Encode(error.to_string()))?;
+    Ok(EncodedImage {
+        name,
+        format,
+        width,
+        height,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // 左半边红色、右半边蓝色的图片
+    fn halves(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        }))
+    }
+
+    fn encoded(image: &DynamicImage, format: OutputFormat) -> Vec<u8> {
+        encode("original".to_string(), image, format).unwrap().bytes
+    }
+
+    // 在 JPEG 的 SOI 之后插入只有 Orientation 一项的 EXIF（APP1）段
+    fn with_orientation(jpeg: &[u8], orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1]);
+        bytes.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        bytes.extend_from_slice(&exif);
+        bytes.extend_from_slice(&jpeg[2..]);
+        bytes
+    }
+
+    fn is_red(pixel: Rgb<u8>) -> bool {
+        pixel[0] > 200 && pixel[2] < 60
+    }
+
+    #[test]
+    fn sniffs_format_from_content() {
+        let image = halves(8, 4);
+        let jpeg = encoded(&image, OutputFormat::Jpeg);
+        let png = encoded(&image, OutputFormat::Png);
+        let webp = encoded(&image, OutputFormat::WebP);
+        assert_eq!(output_format(&jpeg), OutputFormat::Jpeg);
+        assert_eq!(output_format(&png), OutputFormat::Png);
+        // 除 JPEG 外都保存为 PNG
+        assert_eq!(output_format(&webp), OutputFormat::Png);
+        for bytes in [&jpeg, &png, &webp] {
+            let decoded = decode(bytes, 1_000).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (8, 4));
+        }
+
+        assert!(matches!(decode(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>", 1_000), Err(ImageError::Unsupported)));
+        assert!(matches!(decode(b"GIF89a", 1_000), Err(ImageError::Corrupt(_))));
+        assert!(matches!(decode(&png[..png.len() / 2], 1_000), Err(ImageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_too_many_pixels() {
+        let png = encoded(&halves(40, 30), OutputFormat::Png);
+        assert!(decode(&png, 1_200).is_ok());
+        match decode(&png, 1_199) {
+            Err(ImageError::TooManyPixels { width: 40, height: 30, max_pixels: 1_199 }) => {}
+            other => panic!("unexpected result {:?}", other.map(|image| (image.width(), image.height()))),
+        }
+    }
+
+    #[test]
+    fn applies_exif_orientation() {
+        let jpeg = encoded(&halves(16, 8), OutputFormat::Jpeg);
+        assert_eq!(exif_orientation(&jpeg), 1);
+
+        // 6：顺时针旋转 90°，左半边的红色转到上半部分
+        let rotated = with_orientation(&jpeg, 6);
+        assert_eq!(exif_orientation(&rotated), 6);
+        let image = decode(&rotated, 1_000).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (8, 16));
+        assert!(is_red(*image.get_pixel(4, 2)));
+        assert!(!is_red(*image.get_pixel(4, 13)));
+
+        // 重新编码后不再带有 EXIF，不会被再次旋转
+        let reencoded = encoded(&DynamicImage::ImageRgb8(image), OutputFormat::Jpeg);
+        assert_eq!(exif_orientation(&reencoded), 1);
+
+        // 3：旋转 180°，红色转到右边
+        let image = decode(&with_orientation(&jpeg, 3), 1_000).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (16, 8));
+        assert!(is_red(*image.get_pixel(13, 4)));
+    }
+
+    #[test]
+    fn thumbnail_sizes() {
+        let png = encoded(&halves(400, 200), OutputFormat::Png);
+        let images = process_upload(&png, 1_000_000, &[100, 800]).unwrap();
+        let summary: Vec<_> = images
+            .iter()
+            .map(|image| (image.name.as_str(), image.format, image.width, image.height))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("original", OutputFormat::Png, 400, 200),
+                ("100", OutputFormat::Png, 100, 50),
+                ("100_webp", OutputFormat::WebP, 100, 50),
+                // 不放大比缩略图小的图片
+                ("800", OutputFormat::Png, 400, 200),
+                ("800_webp", OutputFormat::WebP, 400, 200),
+            ]
+        );
+        for image in &images {
+            let decoded = image::load_from_memory(&image.bytes).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (image.width, image.height));
+        }
+    }
+
+    #[test]
+    fn crop_square_keeps_the_center() {
+        // 中间一段为绿色，两边为红色
+        let green = |inside: bool| if inside { Rgb([0, 255, 0]) } else { Rgb([255, 0, 0]) };
+        let wide = DynamicImage::ImageRgb8(RgbImage::from_fn(30, 10, |x, _| green((10..20).contains(&x))));
+        let tall = DynamicImage::ImageRgb8(RgbImage::from_fn(10, 30, |_, y| green((10..20).contains(&y))));
+        for image in [wide, tall] {
+            let cropped = crop_square(image).to_rgb8();
+            assert_eq!(cropped.dimensions(), (10, 10));
+            assert!(cropped.pixels().all(|pixel| *pixel == Rgb([0, 255, 0])));
+        }
+
+        let square = crop_square(halves(12, 12));
+        assert_eq!((square.width(), square.height()), (12, 12));
+    }
+
+    #[test]
+    fn avatar_sizes() {
+        let jpeg = encoded(&halves(600, 300), OutputFormat::Jpeg);
+        let images = process_avatar(&jpeg, 1_000_000, 256, &[100, 400]).unwrap();
+        let summary: Vec<_> = images
+            .iter()
+            .map(|image| (image.name.as_str(), image.format, image.width, image.height))
+            .collect();
+        // 不生成不小于头像边长的缩略图
+        assert_eq!(
+            summary,
+            [
+                ("original", OutputFormat::Jpeg, 256, 256),
+                ("100", OutputFormat::Jpeg, 100, 100),
+                ("100_webp", OutputFormat::WebP, 100, 100),
+            ]
+        );
+        // 裁剪居中，左右仍各为一种颜色
+        let avatar = image::load_from_memory(&images[0].bytes).unwrap().to_rgb8();
+        assert!(is_red(*avatar.get_pixel(20, 128)));
+        assert!(!is_red(*avatar.get_pixel(235, 128)));
+
+        // 比头像边长小的图片只裁剪不放大
+        let png = encoded(&halves(60, 80), OutputFormat::Png);
+        let images = process_avatar(&png, 1_000_000, 256, &[100]).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].format, images[0].width, images[0].height), (OutputFormat::Png, 60, 60));
+
+        assert!(matches!(process_avatar(&png, 4_799, 256, &[]), Err(ImageError::TooManyPixels { .. })));
+    }
+}
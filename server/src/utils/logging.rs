@@ -0,0 +1,30 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::models::settings::{LogFormat, LoggingSettings};
+
+// Rocket 自带的启动信息和逐条请求日志由 RequestLogger 代替，只保留错误
+const QUIET_TARGETS: [&str; 2] = ["rocket=error", "rocket::server::_=off"];
+
+pub fn env_filter(level: &str) -> Result<EnvFilter, String> {
+    let mut filter = EnvFilter::try_new(level).map_err(|error| error.to_string())?;
+    // 用户自己配置了 rocket 的级别时不再覆盖
+    for directive in QUIET_TARGETS {
+        let target = directive.split([':', '=']).next().unwrap_or_default();
+        if !level.contains(target) {
+            filter = filter.add_directive(directive.parse().map_err(|error| format!("{}", error))?);
+        }
+    }
+    Ok(filter)
+}
+
+// 需在 rocket::build() 之前调用，Rocket 和 sqlx 通过 log 输出的内容也会转到这里
+pub fn init(settings: &LoggingSettings) {
+    let level = std::env::var("RUST_LOG").unwrap_or_else(|_| settings.level.clone());
+    let filter = env_filter(&level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match settings.format {
+        // 请求 span 的 request_id、method、route 放在 span 字段中
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).with_span_list(false).init(),
+        LogFormat::Text => builder.init(),
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+use sqlx::{Pool, Postgres};
+
+use crate::utils::request_log::RequestContext;
+
+// 所有指标注册在同一个 Registry 中，由 GET /metrics 输出 Prometheus 文本格式。
+// 业务计数在 models 中直接调用，不需要经过 State 传递。
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub db_pool_connections: IntGauge,
+    pub db_pool_idle_connections: IntGauge,
+    pub db_pool_max_connections: IntGauge,
+    pub bookings_created_total: IntCounter,
+    // source: member（会员自己取消）或 studio（停课批量取消）
+    pub bookings_cancelled_total: IntCounterVec,
+    pub lessons_full_total: IntCounter,
+    pub card_purchases_total: IntCounter,
+    // reason: request（请求失败）或 errcode（微信返回错误码）
+    pub wechat_call_failures_total: IntCounterVec,
+    pub rate_limited_total: IntCounterVec,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("yoga".to_string()), None).expect("valid metrics prefix");
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route")
+                .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let db_pool_connections =
+            IntGauge::new("db_pool_connections", "Open database connections").expect("valid metric");
+        let db_pool_idle_connections =
+            IntGauge::new("db_pool_idle_connections", "Idle database connections").expect("valid metric");
+        let db_pool_max_connections =
+            IntGauge::new("db_pool_max_connections", "Configured maximum database connections").expect("valid metric");
+        let bookings_created_total =
+            IntCounter::new("bookings_created_total", "Bookings created by members").expect("valid metric");
+        let bookings_cancelled_total = IntCounterVec::new(
+            Opts::new("bookings_cancelled_total", "Bookings cancelled"),
+            &["source"],
+        )
+        .expect("valid metric");
+        let lessons_full_total =
+            IntCounter::new("lessons_full_total", "Booking attempts rejected because the lesson was full")
+                .expect("valid metric");
+        let card_purchases_total =
+            IntCounter::new("card_purchases_total", "Membership cards purchased").expect("valid metric");
+        let wechat_call_failures_total = IntCounterVec::new(
+            Opts::new("wechat_call_failures_total", "Failed calls to the WeChat API"),
+            &["api", "reason"],
+        )
+        .expect("valid metric");
+
+        let rate_limited_total = IntCounterVec::new(
+            Opts::new("rate_limited_total", "Requests rejected by the rate limiter"),
+            &["route"],
+        )
+        .expect("valid metric");
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(http_requests_total.clone()),
+            Box::new(http_request_duration_seconds.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(db_pool_idle_connections.clone()),
+            Box::new(db_pool_max_connections.clone()),
+            Box::new(bookings_created_total.clone()),
+            Box::new(bookings_cancelled_total.clone()),
+            Box::new(lessons_full_total.clone()),
+            Box::new(card_purchases_total.clone()),
+            Box::new(wechat_call_failures_total.clone()),
+            Box::new(rate_limited_total.clone()),
+        ];
+        for collector in collectors {
+            registry.register(collector).expect("metric registered once");
+        }
+
+        Metrics {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_pool_connections,
+            db_pool_idle_connections,
+            db_pool_max_connections,
+            bookings_created_total,
+            bookings_cancelled_total,
+            lessons_full_total,
+            card_purchases_total,
+            wechat_call_failures_total,
+            rate_limited_total,
+        }
+    }
+
+    pub fn observe_pool(&self, pool: &Pool<Postgres>) {
+        self.db_pool_connections.set(pool.size() as i64);
+        self.db_pool_idle_connections.set(pool.num_idle() as i64);
+        self.db_pool_max_connections.set(pool.options().get_max_connections() as i64);
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(error) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %error, "failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+pub struct MetricsFairing;
+
+#[rocket::async_trait]
+impl Fairing for MetricsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "HTTP request metrics",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        // 按路由模板而不是实际路径统计，未匹配的请求归到 "unmatched"，避免标签无限增长
+        let route = request
+            .route()
+            .map(|route| route.uri.to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        if route == "/metrics" {
+            return;
+        }
+        let method = request.method().as_str();
+        let elapsed = RequestContext::of(request).started_at.elapsed().as_secs_f64();
+        METRICS
+            .http_requests_total
+            .with_label_values(&[method, route.as_str(), &response.status().code.to_string()])
+            .inc();
+        METRICS
+            .http_request_duration_seconds
+            .with_label_values(&[method, route.as_str()])
+            .observe(elapsed);
+    }
+}
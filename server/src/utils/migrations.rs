@@ -0,0 +1,95 @@
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::{Pool, Postgres};
+
+// server/migrations 下的所有迁移，编译时打包进程序
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+// 数据库与程序中迁移的对比结果
+#[derive(Debug)]
+pub struct SchemaStatus {
+    // 数据库已执行的最高版本
+    pub applied_version: Option<i64>,
+    // 程序中最高的迁移版本
+    pub expected_version: Option<i64>,
+    // 程序中有而数据库还没有执行的版本
+    pub pending: Vec<i64>,
+    // 已执行但 SQL 与程序中文件不一致的版本
+    pub modified: Vec<i64>,
+    // 执行到一半失败的版本
+    pub dirty: Option<i64>,
+}
+
+impl SchemaStatus {
+    pub fn is_current(&self) -> bool {
+        self.pending.is_empty() && self.modified.is_empty() && self.dirty.is_none()
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(version) = self.dirty {
+            return format!("migration {} failed part way through, fix it by hand and re-run", version);
+        }
+        if !self.modified.is_empty() {
+            return format!("applied migrations {:?} were edited after they ran", self.modified);
+        }
+        if self.applied_version.is_none() && !self.pending.is_empty() {
+            return format!("database has not been migrated, pending migrations {:?}", self.pending);
+        }
+        if !self.pending.is_empty() {
+            return format!(
+                "database is at version {:?} but the server expects {:?}, pending migrations {:?}",
+                self.applied_version, self.expected_version, self.pending
+            );
+        }
+        format!("database is at version {:?}", self.applied_version)
+    }
+}
+
+// 执行所有未执行的迁移，多个实例同时执行时由 sqlx 内部的 Postgres advisory lock 排队
+pub async fn run(pool: &Pool<Postgres>) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
+
+// 对比数据库与程序中的迁移，不写入任何内容，可以在就绪检查中调用。
+// 从未迁移过的数据库没有 _sqlx_migrations 表，所有迁移都算未执行
+pub async fn status(pool: &Pool<Postgres>) -> Result<SchemaStatus, sqlx::Error> {
+    let table_exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let applied: Vec<(i64, Vec<u8>, bool)> = if table_exists {
+        sqlx::query_as("SELECT version, checksum, success FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let mut pending = Vec::new();
+    let mut modified = Vec::new();
+    for migration in MIGRATOR.iter().filter(|m| m.migration_type.is_up_migration()) {
+        match applied.iter().find(|(version, _, _)| *version == migration.version) {
+            Some((_, checksum, _)) if *checksum != *migration.checksum => modified.push(migration.version),
+            Some(_) => {}
+            None => pending.push(migration.version),
+        }
+    }
+
+    Ok(SchemaStatus {
+        applied_version: applied.iter().filter(|(_, _, success)| *success).map(|(version, _, _)| *version).max(),
+        expected_version: MIGRATOR.iter().map(|m| m.version).max(),
+        pending,
+        modified,
+        dirty: applied.iter().find(|(_, _, success)| !success).map(|(version, _, _)| *version),
+    })
+}
+
+// 数据库的迁移与程序中的不完全一致时返回错误
+pub async fn ensure_current(pool: &Pool<Postgres>) -> Result<(), String> {
+    let status = status(pool)
+        .await
+        .map_err(|error| format!("failed to read migration status: {}", error))?;
+    if status.is_current() {
+        Ok(())
+    } else {
+        Err(status.describe())
+    }
+}
@@ -1,5 +1,19 @@
 pub mod data;
+pub mod database;
 pub mod client_real_addr;
 pub mod content_disposition;
 pub mod string;
-pub mod cors;
\ No newline at end of file
+pub mod time_of_day;
+pub mod cors;
+pub mod migrations;
+pub mod logging;
+pub mod request_log;
+pub mod metrics;
+pub mod supervisor;
+pub mod rate_limit;
+pub mod images;
+pub mod media_store;
+pub mod http_cache;
+pub mod poster;
+pub mod pdf;
+pub mod icalendar;
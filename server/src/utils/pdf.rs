@@ -0,0 +1,125 @@
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::RgbaImage;
+
+// A4 纸的宽高，单位为点（1/72 英寸）
+const A4: (f32, f32) = (595.28, 841.89);
+// 页边距 10mm
+const MARGIN: f32 = 28.35;
+
+// 单页 PDF，图片按比例缩放后居中放在 A4 纸上，横图使用横向页面。
+// 透明部分按白色合成，像素用 Flate 无损压缩
+pub fn image_pdf(image: &RgbaImage, title: &str) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let (page_width, page_height) = if width > height { (A4.1, A4.0) } else { A4 };
+    let scale = ((page_width - MARGIN * 2.0) / width as f32).min((page_height - MARGIN * 2.0) / height as f32);
+    let (draw_width, draw_height) = (width as f32 * scale, height as f32 * scale);
+    let (x, y) = ((page_width - draw_width) / 2.0, (page_height - draw_height) / 2.0);
+
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        for channel in [r, g, b] {
+            rgb.push(((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8);
+        }
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // 写入内存不会失败
+    let _ = encoder.write_all(&rgb);
+    let pixels = encoder.finish().unwrap_or_default();
+    let contents = format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im0 Do Q", draw_width, draw_height, x, y);
+
+    let objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>",
+            page_width, page_height
+        )
+        .into_bytes(),
+        stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode",
+                width, height
+            ),
+            &pixels,
+        ),
+        stream("", contents.as_bytes()),
+        format!("<< /Title {} /Producer (YogaServer) >>", text_string(title)).into_bytes(),
+    ];
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            objects.len(),
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+fn stream(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+// 非 ASCII 的标题用 UTF-16BE 十六进制字符串
+fn text_string(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn cross_reference_offsets() {
+        let image = RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]));
+        let pdf = image_pdf(&image, "课表");
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/MediaBox [0 0 841.89 595.28]"));
+        assert!(text.contains("/Title <FEFF8BFE8868>"));
+
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref\n0 7\n"));
+        let entries = String::from_utf8_lossy(&pdf[startxref..]).lines().skip(3).take(6).map(str::to_string).collect::<Vec<_>>();
+        for (index, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn portrait_page() {
+        let image = RgbaImage::new(10, 20);
+        let text = String::from_utf8_lossy(&image_pdf(&image, "")).to_string();
+        assert!(text.contains("/MediaBox [0 0 595.28 841.89]"));
+    }
+}
@@ -0,0 +1,942 @@
+use std::fmt::Write;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Weekday;
+use chrono_tz::Tz;
+use figment::providers::{Format, Yaml};
+use figment::Figment;
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use rusttype::{point, Font, Scale};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::models::settings::Settings;
+use crate::utils::time_of_day::parse_time_of_day;
+
+// 内置的课表底图、字体和模板在编译时打包进程序
+pub const PATTERN: &[u8] = include_bytes!("../handlers/pattern.png");
+pub const FONT: &[u8] = include_bytes!("../handlers/PingFang.ttf");
+pub const TEMPLATE: &str = include_str!("../handlers/schedule.yml");
+
+const ELLIPSIS: char = '…';
+
+// 课表海报模板，字段说明见 handlers/schedule.yml
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PosterTemplate {
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    // SVG 中的字体，查看的设备上没有时依次使用后面的字体
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+    #[serde(default = "default_text_color")]
+    pub text_color: String,
+    pub columns: ColumnLayout,
+    pub header: HeaderLayout,
+    pub rows: Vec<RowLayout>,
+    pub lesson: LessonLayout,
+    #[serde(default)]
+    pub overflow: OverflowLayout,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnLayout {
+    pub x: i32,
+    pub spacing: i32,
+    pub width: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderLayout {
+    pub y: i32,
+    pub height: i32,
+    pub size: f32,
+    #[serde(default = "default_labels")]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowLayout {
+    pub start: String,
+    pub end: String,
+    pub y: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub fill: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LessonLayout {
+    pub title: TextStyle,
+    pub subtitle: TextStyle,
+    #[serde(default)]
+    pub gap: f32,
+    #[serde(default)]
+    pub padding: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextStyle {
+    pub size: f32,
+    #[serde(default)]
+    pub min_size: Option<f32>,
+    #[serde(default)]
+    pub color: Option<String>,
+    // 一行放不下时最多换成几行
+    #[serde(default = "default_max_lines")]
+    pub max_lines: usize,
+    // 内容为空、或字体中没有其中任何一个字符时显示的文字，不填时不画
+    #[serde(default)]
+    pub placeholder: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverflowLayout {
+    pub max_per_cell: usize,
+    // {count} 替换为没有画出的课程数
+    pub text: String,
+}
+
+impl Default for OverflowLayout {
+    fn default() -> Self {
+        OverflowLayout {
+            max_per_cell: 3,
+            text: "+{count}".to_string(),
+        }
+    }
+}
+
+fn default_font_family() -> String {
+    "PingFang SC, Hiragino Sans GB, Microsoft YaHei, sans-serif".to_string()
+}
+
+fn default_text_color() -> String {
+    "#FFFFFF".to_string()
+}
+
+fn default_max_lines() -> usize {
+    1
+}
+
+fn default_labels() -> Vec<String> {
+    ["周一", "周二", "周三", "周四", "周五", "周六", "周日"]
+        .iter()
+        .map(|label| label.to_string())
+        .collect()
+}
+
+impl TextStyle {
+    fn min_size(&self) -> f32 {
+        self.min_size.unwrap_or(self.size)
+    }
+}
+
+impl PosterTemplate {
+    pub fn parse(source: &str) -> Result<PosterTemplate, String> {
+        Figment::from(Yaml::string(source))
+            .extract()
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let colors = std::iter::once(("text_color", Some(&self.text_color)))
+            .chain([
+                ("header.color", self.header.color.as_ref()),
+                ("lesson.title.color", self.lesson.title.color.as_ref()),
+                ("lesson.subtitle.color", self.lesson.subtitle.color.as_ref()),
+            ])
+            .chain(self.rows.iter().map(|row| ("rows[].fill", row.fill.as_ref())));
+        for (name, color) in colors {
+            if let Some(color) = color.filter(|color| parse_color(color).is_none()) {
+                errors.push(format!("{} must look like #RRGGBB or #RRGGBBAA, got \"{}\"", name, color));
+            }
+        }
+        if let Some(timezone) = self.timezone.as_ref().filter(|timezone| timezone.parse::<Tz>().is_err()) {
+            errors.push(format!("timezone \"{}\" is not a valid IANA time zone", timezone));
+        }
+
+        if self.columns.width <= 0 || self.columns.spacing <= 0 {
+            errors.push("columns.width and columns.spacing must be greater than 0".to_string());
+        }
+        if self.header.labels.len() != 7 {
+            errors.push(format!(
+                "header.labels must have 7 entries (Monday to Sunday), got {}",
+                self.header.labels.len()
+            ));
+        }
+        if self.header.height <= 0 || self.header.size <= 0.0 {
+            errors.push("header.height and header.size must be greater than 0".to_string());
+        }
+
+        if self.rows.is_empty() {
+            errors.push("rows must not be empty".to_string());
+        }
+        for (index, row) in self.rows.iter().enumerate() {
+            match (parse_time_of_day(&row.start), parse_time_of_day(&row.end)) {
+                (Some(start), Some(end)) if start < end => {}
+                (Some(_), Some(_)) => errors.push(format!("rows[{}].start must be before rows[{}].end", index, index)),
+                _ => errors.push(format!(
+                    "rows[{}].start and rows[{}].end must be HH:MM between 00:00 and 24:00",
+                    index, index
+                )),
+            }
+            if row.height <= 0 {
+                errors.push(format!("rows[{}].height must be greater than 0", index));
+            }
+        }
+
+        for (name, style) in [("lesson.title", &self.lesson.title), ("lesson.subtitle", &self.lesson.subtitle)] {
+            if style.size <= 0.0 || style.min_size() <= 0.0 || style.min_size() > style.size {
+                errors.push(format!("{}.size and {}.min_size must be greater than 0 and min_size <= size", name, name));
+            }
+            if style.max_lines == 0 {
+                errors.push(format!("{}.max_lines must be greater than 0", name));
+            }
+        }
+        if self.lesson.gap < 0.0 || self.lesson.padding < 0 || self.lesson.padding * 2 >= self.columns.width {
+            errors.push("lesson.gap and lesson.padding must not be negative, and padding must fit in columns.width".to_string());
+        }
+        if self.overflow.max_per_cell == 0 {
+            errors.push("overflow.max_per_cell must be greater than 0".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// #RRGGBB 或 #RRGGBBAA
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let hex = value.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut rgba = [255u8; 4];
+    for (index, chunk) in hex.as_bytes().chunks(2).enumerate() {
+        rgba[index] = u8::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    Some(rgba)
+}
+
+// 一节要画在海报上的课程，weekday 和 start_secs 已按海报的时区换算
+#[derive(Debug, Clone)]
+pub struct PosterLesson {
+    pub weekday: Weekday,
+    // 距当天零点的秒数
+    pub start_secs: u32,
+    pub title: String,
+    pub subtitle: String,
+}
+
+pub struct Poster {
+    template: PosterTemplate,
+    // 模板文件路径，使用内置模板时为空
+    source: Option<PathBuf>,
+    background: RgbaImage,
+    font: Font<'static>,
+    tz: Tz,
+    // 与 template.rows 一一对应的 [start, end) 秒数
+    slots: Vec<(u32, u32)>,
+}
+
+impl Poster {
+    // 加载 schedule.template 指定的模板及其底图和字体，没有指定时使用内置模板
+    pub fn load(settings: &Settings) -> Result<Poster, Vec<String>> {
+        let (template, source) = match &settings.schedule.template {
+            Some(path) => {
+                if !Path::new(path).is_file() {
+                    return Err(vec![format!("schedule.template \"{}\" does not exist", path)]);
+                }
+                let template = Figment::from(Yaml::file(path))
+                    .extract::<PosterTemplate>()
+                    .map_err(|error| vec![format!("schedule.template \"{}\": {}", path, error)])?;
+                (template, Some(PathBuf::from(path)))
+            }
+            None => (PosterTemplate::parse(TEMPLATE).map_err(|error| vec![error])?, None),
+        };
+        template.validate()?;
+
+        let base = source.as_deref().and_then(Path::parent).unwrap_or(Path::new("."));
+        let background = match &template.background {
+            Some(path) => std::fs::read(base.join(path)).map_err(|error| vec![format!("background \"{}\": {}", path, error)])?,
+            None => PATTERN.to_vec(),
+        };
+        let background = ImageReader::new(Cursor::new(background))
+            .with_guessed_format()
+            .map_err(|error| error.to_string())
+            .and_then(|reader| reader.decode().map_err(|error| error.to_string()))
+            .map_err(|error| vec![format!("background: {}", error)])?
+            .to_rgba8();
+        let font = match &template.font {
+            Some(path) => {
+                let bytes = std::fs::read(base.join(path)).map_err(|error| vec![format!("font \"{}\": {}", path, error)])?;
+                Font::try_from_vec(bytes).ok_or_else(|| vec![format!("font \"{}\": not a valid font", path)])?
+            }
+            None => Font::try_from_bytes(FONT).ok_or_else(|| vec!["PingFang.ttf: not a valid font".to_string()])?,
+        };
+        let mut poster = Poster::new(template, background, font, settings.tz())?;
+        poster.source = source;
+        Ok(poster)
+    }
+
+    // 用已经读入的底图和字体创建海报，tz 为模板没有指定 timezone 时使用的时区
+    pub fn new(template: PosterTemplate, background: RgbaImage, font: Font<'static>, tz: Tz) -> Result<Poster, Vec<String>> {
+        template.validate()?;
+        let tz = template
+            .timezone
+            .as_deref()
+            .and_then(|timezone| timezone.parse().ok())
+            .unwrap_or(tz);
+        // validate() 已经检查过时间格式
+        let slots = template
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    parse_time_of_day(&row.start).unwrap_or_default(),
+                    parse_time_of_day(&row.end).unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        Ok(Poster {
+            template,
+            source: None,
+            background,
+            font,
+            tz,
+            slots,
+        })
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    pub fn source(&self) -> String {
+        match &self.source {
+            Some(path) => path.display().to_string(),
+            None => "builtin".to_string(),
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.background.dimensions()
+    }
+
+    // 在底图上画出星期标题和一周的课程
+    pub fn render(&self, lessons: &[PosterLesson]) -> RgbaImage {
+        let layout = self.layout(lessons);
+        let mut image = self.background.clone();
+        for rect in &layout.rects {
+            let area = Rect::at(rect.x, rect.y).of_size(rect.width, rect.height);
+            draw_filled_rect_mut(&mut image, area, rect.color);
+        }
+        for text in &layout.texts {
+            draw_text_mut(
+                &mut image,
+                text.color,
+                text.x.round() as i32,
+                text.y.round() as i32,
+                Scale::uniform(text.size),
+                &self.font,
+                &text.text,
+            );
+        }
+        image
+    }
+
+    // 与 render() 相同的版式，底图以 PNG 内嵌，文字为矢量。
+    // 文字按列居中对齐，查看的设备没有模板的字体时用 font_family 中的其他字体
+    pub fn render_svg(&self, lessons: &[PosterLesson]) -> Result<String, String> {
+        let layout = self.layout(lessons);
+        let (width, height) = self.dimensions();
+        let mut background = Vec::new();
+        DynamicImage::ImageRgba8(self.background.clone())
+            .write_to(&mut Cursor::new(&mut background), ImageOutputFormat::Png)
+            .map_err(|error| error.to_string())?;
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = width,
+            h = height
+        );
+        let _ = write!(
+            svg,
+            r#"<image width="{}" height="{}" xlink:href="data:image/png;base64,{}"/>"#,
+            width,
+            height,
+            STANDARD.encode(&background)
+        );
+        for rect in &layout.rects {
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                svg_color(rect.color)
+            );
+        }
+        let _ = write!(
+            svg,
+            r#"<g font-family="{}" text-anchor="middle">"#,
+            escape_xml(&self.template.font_family)
+        );
+        for text in &layout.texts {
+            let _ = write!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="{}">{}</text>"#,
+                text.center_x,
+                text.baseline,
+                text.size,
+                svg_color(text.color),
+                escape_xml(&text.text)
+            );
+        }
+        svg.push_str("</g></svg>");
+        Ok(svg)
+    }
+
+    // 计算星期标题、格子填充和每行文字的位置
+    pub fn layout(&self, lessons: &[PosterLesson]) -> PosterLayout {
+        let template = &self.template;
+        let mut layout = PosterLayout::default();
+
+        let header_color = color_or(&template.text_color, template.header.color.as_deref());
+        for (column, label) in template.header.labels.iter().enumerate() {
+            let (label, size) = self.fit(&self.sanitize(label), template.header.size, template.header.size, self.inner_width());
+            let y = template.header.y as f32 + (template.header.height as f32 - self.line_height(size)) / 2.0;
+            if !label.is_empty() {
+                layout.texts.push(self.line(header_color, column, y, label, size));
+            }
+        }
+
+        for (index, row) in template.rows.iter().enumerate() {
+            if let Some(fill) = row.fill.as_deref().and_then(parse_color) {
+                for column in 0..7 {
+                    layout.rects.push(PosterRect {
+                        x: self.column_x(column),
+                        y: row.y,
+                        width: template.columns.width as u32,
+                        height: row.height as u32,
+                        color: Rgba(fill),
+                    });
+                }
+            }
+            for (column, weekday) in WEEKDAYS.iter().enumerate() {
+                let cell: Vec<&PosterLesson> = lessons
+                    .iter()
+                    .filter(|lesson| lesson.weekday == *weekday && self.slot_of(lesson) == Some(index))
+                    .collect();
+                self.layout_cell(&mut layout, column, row, &cell);
+            }
+        }
+
+        let unplaced = lessons.iter().filter(|lesson| self.slot_of(lesson).is_none()).count();
+        if unplaced > 0 {
+            debug!(unplaced = unplaced, "lessons outside every poster row were skipped");
+        }
+        layout
+    }
+
+    fn slot_of(&self, lesson: &PosterLesson) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|(start, end)| (*start..*end).contains(&lesson.start_secs))
+    }
+
+    fn column_x(&self, column: usize) -> i32 {
+        self.template.columns.x + self.template.columns.spacing * column as i32
+    }
+
+    fn inner_width(&self) -> f32 {
+        (self.template.columns.width - self.template.lesson.padding * 2) as f32
+    }
+
+    // 一格内的课程平分格子高度；超过 max_per_cell 时最后一行显示剩余数量
+    fn layout_cell(&self, layout: &mut PosterLayout, column: usize, row: &RowLayout, lessons: &[&PosterLesson]) {
+        if lessons.is_empty() {
+            return;
+        }
+        let style = &self.template.lesson;
+        let max = self.template.overflow.max_per_cell;
+        let (shown, hidden) = if lessons.len() > max {
+            (&lessons[..max - 1], lessons.len() - (max - 1))
+        } else {
+            (lessons, 0)
+        };
+        let entries = shown.len() + usize::from(hidden > 0);
+        let block = row.height as f32 / entries as f32;
+        let title_color = color_or(&self.template.text_color, style.title.color.as_deref());
+        let subtitle_color = color_or(&self.template.text_color, style.subtitle.color.as_deref());
+
+        for (index, lesson) in shown.iter().enumerate() {
+            let top = row.y as f32 + block * index as f32;
+            let title = self.display_text(&lesson.title, &style.title);
+            let subtitle = self.display_text(&lesson.subtitle, &style.subtitle);
+            // 格子不够高时课程名、老师名和间距按比例缩小，缩小后小于 min_size 时只画课程名
+            let natural = self.line_height(style.title.size) + style.gap + self.line_height(style.subtitle.size);
+            let ratio = (block / natural).min(1.0);
+            let has_subtitle = !subtitle.is_empty()
+                && style.title.size * ratio >= style.title.min_size()
+                && style.subtitle.size * ratio >= style.subtitle.min_size();
+            // 课程名可以换行，占用老师名以外的高度
+            let (ratio, title_height) = if has_subtitle {
+                (ratio, block - style.gap * ratio - self.line_height(style.subtitle.size * ratio))
+            } else {
+                ((block / self.line_height(style.title.size)).min(1.0), block)
+            };
+            let (title_lines, title_size) = self.fit_lines(
+                &title,
+                style.title.size * ratio,
+                style.title.min_size(),
+                self.inner_width(),
+                style.title.max_lines,
+                title_height,
+            );
+            let gap = if title_lines.is_empty() { 0.0 } else { style.gap * ratio };
+            let mut height = self.line_height(title_size) * title_lines.len() as f32;
+            let subtitle = if has_subtitle {
+                let (subtitle, size) = self.fit(&subtitle, style.subtitle.size * ratio, style.subtitle.min_size(), self.inner_width());
+                height += gap + self.line_height(size);
+                Some((subtitle, size))
+            } else {
+                None
+            };
+
+            let mut y = top + (block - height) / 2.0;
+            for title in title_lines {
+                layout.texts.push(self.line(title_color, column, y, title, title_size));
+                y += self.line_height(title_size);
+            }
+            if let Some((subtitle, size)) = subtitle {
+                layout.texts.push(self.line(subtitle_color, column, y + gap, subtitle, size));
+            }
+        }
+
+        if hidden > 0 {
+            let text = self.sanitize(&self.template.overflow.text.replace("{count}", &hidden.to_string()));
+            let size = style.subtitle.size.min(block / self.line_height(1.0));
+            let (text, size) = self.fit(&text, size, style.subtitle.min_size().min(size), self.inner_width());
+            let top = row.y as f32 + block * shown.len() as f32;
+            layout.texts.push(self.line(subtitle_color, column, top + (block - self.line_height(size)) / 2.0, text, size));
+        }
+    }
+
+    // 空白和控制字符合并为一个空格，去掉字体中没有字形的字符（如表情符号），
+    // 否则会画成方框
+    fn sanitize(&self, text: &str) -> String {
+        let mut clean = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c.is_whitespace() || c.is_control() {
+                if !clean.is_empty() && !clean.ends_with(' ') {
+                    clean.push(' ');
+                }
+            } else if self.font.glyph(c).id().0 != 0 {
+                clean.push(c);
+            }
+        }
+        clean.truncate(clean.trim_end().len());
+        clean
+    }
+
+    // 清理后为空时使用模板中的占位文字
+    fn display_text(&self, text: &str, style: &TextStyle) -> String {
+        let text = self.sanitize(text);
+        match &style.placeholder {
+            Some(placeholder) if text.is_empty() => self.sanitize(placeholder),
+            _ => text,
+        }
+    }
+
+    // 单行文字，先缩小字号到 min_size，仍超出宽度时截断并加上省略号
+    fn fit(&self, text: &str, size: f32, min_size: f32, max_width: f32) -> (String, f32) {
+        let (lines, size) = self.fit_lines(text, size, min_size, max_width, 1, f32::INFINITY);
+        (lines.into_iter().next().unwrap_or_default(), size)
+    }
+
+    // 按宽度换行，行数超过 max_lines 或总高度超过 max_height 时缩小字号；
+    // 缩小到 min_size 仍放不下时，截断最后一行并加上省略号
+    fn fit_lines(
+        &self,
+        text: &str,
+        size: f32,
+        min_size: f32,
+        max_width: f32,
+        max_lines: usize,
+        max_height: f32,
+    ) -> (Vec<String>, f32) {
+        if text.is_empty() {
+            return (Vec::new(), size);
+        }
+        let min_size = min_size.min(size);
+        let mut size = size;
+        loop {
+            let lines = self.wrap(text, size, max_width);
+            // 至少保留一行，格子再矮也画出课程名
+            let allowed = max_lines.min((max_height / self.line_height(size)) as usize).max(1);
+            if lines.len() <= allowed && lines.iter().all(|&(start, end)| self.text_width(&text[start..end], size) <= max_width) {
+                return (lines.iter().map(|&(start, end)| text[start..end].to_string()).collect(), size);
+            }
+            if size <= min_size {
+                let last = allowed.min(lines.len()) - 1;
+                let mut fitted: Vec<String> = lines[..last].iter().map(|&(start, end)| text[start..end].to_string()).collect();
+                fitted.push(self.truncate(&text[lines[last].0..], size, max_width));
+                return (fitted, size);
+            }
+            size = (size - 1.0).max(min_size);
+        }
+    }
+
+    // 贪心换行，返回每行在 text 中的字节区间。连续的拉丁字母、数字和标点作为一个词，
+    // 词比一行还宽时才逐字拆开；汉字等其他字符之间都可以换行
+    fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<(usize, usize)> {
+        let mut lines = Vec::new();
+        let mut line: Option<(usize, usize)> = None;
+        for (start, end) in words(text) {
+            let pieces: Vec<(usize, usize)> = if self.text_width(&text[start..end], size) > max_width {
+                text[start..end]
+                    .char_indices()
+                    .map(|(index, c)| (start + index, start + index + c.len_utf8()))
+                    .collect()
+            } else {
+                vec![(start, end)]
+            };
+            for (start, end) in pieces {
+                line = match line {
+                    Some((line_start, _)) if self.text_width(&text[line_start..end], size) <= max_width => Some((line_start, end)),
+                    Some(current) => {
+                        lines.push(current);
+                        Some((start, end))
+                    }
+                    None => Some((start, end)),
+                };
+            }
+        }
+        lines.extend(line);
+        lines
+    }
+
+    fn truncate(&self, text: &str, size: f32, max_width: f32) -> String {
+        if self.text_width(text, size) <= max_width {
+            return text.to_string();
+        }
+        let chars: Vec<char> = text.chars().collect();
+        for end in (0..chars.len()).rev() {
+            let kept: String = chars[..end].iter().collect();
+            let truncated = format!("{}{}", kept.trim_end(), ELLIPSIS);
+            if self.text_width(&truncated, size) <= max_width {
+                return truncated;
+            }
+        }
+        ELLIPSIS.to_string()
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        self.font
+            .layout(text, Scale::uniform(size), point(0.0, 0.0))
+            .last()
+            .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+            .unwrap_or(0.0)
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        let metrics = self.font.v_metrics(Scale::uniform(size));
+        metrics.ascent - metrics.descent
+    }
+
+    // 在第 column 列水平居中的一行文字，y 为行顶部
+    fn line(&self, color: Rgba<u8>, column: usize, y: f32, text: String, size: f32) -> PosterText {
+        let center_x = self.column_x(column) as f32 + self.template.columns.width as f32 / 2.0;
+        PosterText {
+            x: center_x - self.text_width(&text, size) / 2.0,
+            center_x,
+            y,
+            baseline: y + self.font.v_metrics(Scale::uniform(size)).ascent,
+            size,
+            color,
+            text,
+        }
+    }
+}
+
+// 海报的布局，PNG 和 SVG 按同样的位置绘制
+#[derive(Debug, Default)]
+pub struct PosterLayout {
+    pub rects: Vec<PosterRect>,
+    pub texts: Vec<PosterText>,
+}
+
+#[derive(Debug)]
+pub struct PosterRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub color: Rgba<u8>,
+}
+
+#[derive(Debug)]
+pub struct PosterText {
+    pub text: String,
+    // 左边界、列中心、行顶部和基线
+    pub x: f32,
+    pub center_x: f32,
+    pub y: f32,
+    pub baseline: f32,
+    pub size: f32,
+    pub color: Rgba<u8>,
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+fn color_or(default: &str, color: Option<&str>) -> Rgba<u8> {
+    // validate() 已经检查过颜色格式
+    Rgba(
+        color
+            .and_then(parse_color)
+            .or_else(|| parse_color(default))
+            .unwrap_or([255, 255, 255, 255]),
+    )
+}
+
+fn svg_color(color: Rgba<u8>) -> String {
+    let [r, g, b, a] = color.0;
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("rgba({},{},{},{:.3})", r, g, b, a as f32 / 255.0)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 按空格和汉字等字符切分可以换行的位置，返回每个词的字节区间
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut word: Option<(usize, usize)> = None;
+    for (index, c) in text.char_indices() {
+        let end = index + c.len_utf8();
+        if c == ' ' {
+            words.extend(word.take());
+        } else if (c as u32) < 0x2E80 {
+            // 0x2E80 以下是拉丁、希腊、西里尔等用空格分词的文字
+            word = Some((word.map_or(index, |(start, _)| start), end));
+        } else {
+            words.extend(word.take());
+            words.push((index, end));
+        }
+    }
+    words.extend(word);
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/poster")
+    }
+
+    // 快照测试使用的海报，字体和模板都在 tests/fixtures/poster 中，与本机安装的字体无关
+    fn fixture_poster() -> Poster {
+        let template = PosterTemplate::parse(&std::fs::read_to_string(fixtures().join("template.yml")).unwrap()).unwrap();
+        let font = Font::try_from_vec(std::fs::read(fixtures().join("DejaVuSansMono.ttf")).unwrap()).unwrap();
+        let background = RgbaImage::from_pixel(680, 216, Rgba([245, 240, 232, 255]));
+        Poster::new(template, background, font, chrono_tz::Asia::Hong_Kong).unwrap()
+    }
+
+    fn lesson(weekday: Weekday, time: &str, title: &str, subtitle: &str) -> PosterLesson {
+        PosterLesson {
+            weekday,
+            start_secs: parse_time_of_day(time).unwrap(),
+            title: title.to_string(),
+            subtitle: subtitle.to_string(),
+        }
+    }
+
+    // 空白、空名字、缺字、长名字、周日和一格多节课
+    fn edge_case_week() -> Vec<PosterLesson> {
+        let mut lessons = vec![
+            lesson(Weekday::Mon, "09:00", "Hatha Flow", "Alice"),
+            lesson(Weekday::Mon, "19:00", "  Yin \t Yoga\n", ""),
+            lesson(Weekday::Tue, "08:00", "", "Bob"),
+            lesson(Weekday::Tue, "18:00", "\u{1F9D8} Vinyasa \u{1F525}", "Carol \u{1F338}"),
+            lesson(Weekday::Wed, "10:00", "Restorative Yoga for Beginners and Seniors", "Dominique-Alexandra"),
+            lesson(Weekday::Wed, "20:00", "Supercalifragilisticexpialidocious", "Ed"),
+            lesson(Weekday::Fri, "12:00", "流瑜伽", "李老师"),
+            lesson(Weekday::Sat, "23:30", "Late Night", "Zoe"),
+            lesson(Weekday::Sun, "06:00", "Sunrise", "Sam"),
+            lesson(Weekday::Sun, "21:00", "Sunday Sound Bath", "Ravi"),
+        ];
+        for title in ["Pilates", "Barre", "Core", "Stretch", "HIIT"] {
+            lessons.push(lesson(Weekday::Thu, "07:00", title, "Kim"));
+        }
+        lessons
+    }
+
+    // 与 tests/fixtures/poster/<name>.png 比较，UPDATE_GOLDEN=1 时重新生成
+    fn assert_golden(name: &str, image: &RgbaImage) {
+        let path = fixtures().join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            image.save(&path).unwrap();
+            return;
+        }
+        let golden = image::open(&path)
+            .unwrap_or_else(|error| panic!("{}: {}, run with UPDATE_GOLDEN=1 to create it", path.display(), error))
+            .to_rgba8();
+        let differing = if golden.dimensions() == image.dimensions() {
+            golden
+                .pixels()
+                .zip(image.pixels())
+                .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > 2))
+                .count()
+        } else {
+            usize::MAX
+        };
+        if differing > 0 {
+            let actual = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/poster-snapshots");
+            std::fs::create_dir_all(&actual).unwrap();
+            let actual = actual.join(format!("{}.png", name));
+            image.save(&actual).unwrap();
+            panic!(
+                "{} differs from the golden image ({} pixels), actual output written to {}",
+                path.display(),
+                differing,
+                actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn edge_case_week_snapshot() {
+        assert_golden("edge_case_week", &fixture_poster().render(&edge_case_week()));
+    }
+
+    #[test]
+    fn empty_week_snapshot() {
+        assert_golden("empty_week", &fixture_poster().render(&[]));
+    }
+
+    #[test]
+    fn builtin_poster_renders_edge_cases() {
+        let template = PosterTemplate::parse(TEMPLATE).unwrap();
+        let background = image::load_from_memory(PATTERN).unwrap().to_rgba8();
+        let poster = Poster::new(template, background, Font::try_from_bytes(FONT).unwrap(), chrono_tz::Asia::Hong_Kong).unwrap();
+        let lessons = edge_case_week();
+        assert_eq!(poster.render(&lessons).dimensions(), poster.dimensions());
+        assert!(poster.render_svg(&lessons).unwrap().ends_with("</g></svg>"));
+    }
+
+    #[test]
+    fn placeholders_and_glyphs() {
+        let poster = fixture_poster();
+        assert_eq!(poster.sanitize(" a\tb\n\u{1F9D8} c "), "a b c");
+        assert_eq!(poster.sanitize("流瑜伽"), "");
+        let texts: Vec<String> = poster
+            .layout(&[lesson(Weekday::Sun, "10:00", "\u{1F9D8}", " ")])
+            .texts
+            .into_iter()
+            .map(|text| text.text)
+            .collect();
+        assert_eq!(&texts[7..], ["Untitled", "TBA"]);
+    }
+
+    #[test]
+    fn wraps_words_and_characters() {
+        assert_eq!(words("Yin  Yoga 流瑜伽"), [(0, 3), (5, 9), (10, 13), (13, 16), (16, 19)]);
+
+        let poster = fixture_poster();
+        let width = poster.text_width("Yoga Flow", 16.0);
+        let (lines, size) = poster.fit_lines("Yoga Flow Yoga", 16.0, 10.0, width, 2, f32::INFINITY);
+        assert_eq!((lines, size), (vec!["Yoga Flow".to_string(), "Yoga".to_string()], 16.0));
+
+        // 两行仍放不下时缩小字号，到 min_size 后截断
+        let (lines, size) = poster.fit_lines(&"Yoga Flow ".repeat(6), 16.0, 10.0, width, 2, f32::INFINITY);
+        assert_eq!(size, 10.0);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(ELLIPSIS));
+        assert!(lines.iter().all(|line| poster.text_width(line, size) <= width));
+
+        // 比一行还宽的词逐字拆开
+        let (lines, _) = poster.fit_lines("Supercalifragilistic", 16.0, 16.0, width, 3, f32::INFINITY);
+        assert_eq!(lines.concat(), "Supercalifragilistic");
+        assert!(lines.len() > 1);
+    }
+
+    #[test]
+    fn builtin_template_is_valid() {
+        let template = PosterTemplate::parse(TEMPLATE).unwrap();
+        assert_eq!(template.validate(), Ok(()));
+        assert_eq!(template.rows.len(), 2);
+        assert_eq!(template.header.labels.len(), 7);
+    }
+
+    #[test]
+    fn json_template() {
+        let template = PosterTemplate::parse(
+            r##"{"columns": {"x": 0, "spacing": 100, "width": 90},
+                "header": {"y": 0, "height": 40, "size": 20},
+                "rows": [{"start": "06:00", "end": "24:00", "y": 50, "height": 300, "fill": "#00000080"}],
+                "lesson": {"title": {"size": 20}, "subtitle": {"size": 16, "min_size": 12}}}"##,
+        )
+        .unwrap();
+        assert_eq!(template.validate(), Ok(()));
+        assert_eq!(template.overflow.max_per_cell, 3);
+        assert_eq!(template.text_color, "#FFFFFF");
+    }
+
+    #[test]
+    fn invalid_template() {
+        let mut template = PosterTemplate::parse(TEMPLATE).unwrap();
+        template.text_color = "white".to_string();
+        template.rows[0].end = "25:00".to_string();
+        template.rows[1].start = "24:00".to_string();
+        template.header.labels.pop();
+        template.overflow.max_per_cell = 0;
+        template.timezone = Some("Mars/Base".to_string());
+        let errors = template.validate().unwrap_err();
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use sqlx::{Pool, Postgres};
+
+use crate::models::settings::{RateLimitBackend, RateLimitRule, RateLimitSettings};
+use crate::utils::client_real_addr::ClientRealAddr;
+use crate::utils::metrics::METRICS;
+
+// 令牌桶限流。需要限流的接口加上 `_limit: RateLimit` 参数即可，规则按路由路径在
+// rate_limit.routes 中配置。每个请求按客户端 IP 计数，请求带有 openid 时再按会员计数，
+// 任一超限都返回 429，Retry-After 为需要等待的秒数。
+
+// 内存中的桶超过此数量时清理已经补满的桶
+const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+// 每个桶记下自己的规则，清理时按各自的容量和补充速度判断是否已补满
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl Bucket {
+    fn tokens_at(&self, now: Instant) -> f64 {
+        (self.tokens + now.duration_since(self.updated_at).as_secs_f64() * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        RateLimiter {
+            settings,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 取到令牌返回 Ok，否则返回需要等待的时间
+    pub async fn take(&self, key: &str, rule: RateLimitRule, pool: &Pool<Postgres>) -> Result<(), Duration> {
+        let capacity = rule.burst as f64;
+        let refill_per_sec = rule.per_minute as f64 / 60.0;
+        let wait_secs = match self.settings.backend {
+            RateLimitBackend::Memory => self.take_memory(key, capacity, refill_per_sec),
+            RateLimitBackend::Postgres => {
+                match sqlx::query_scalar::<_, f64>("SELECT fn_rate_limit_take($1, $2, $3)")
+                    .bind(key)
+                    .bind(capacity)
+                    .bind(refill_per_sec)
+                    .fetch_one(pool)
+                    .await
+                {
+                    Ok(wait_secs) => wait_secs,
+                    Err(error) => {
+                        // 数据库不可用时不限流，避免限流本身导致接口不可用
+                        tracing::warn!(key = key, error = %error, "rate limit check failed, request allowed");
+                        0.0
+                    }
+                }
+            }
+        };
+        if wait_secs > 0.0 {
+            Err(Duration::from_secs_f64(wait_secs))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn take_memory(&self, key: &str, capacity: f64, refill_per_sec: f64) -> f64 {
+        // 桶只是计数，其他线程持锁时 panic 也可以继续使用
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        if buckets.len() >= MAX_MEMORY_BUCKETS {
+            buckets.retain(|_, bucket| bucket.tokens_at(now) < bucket.capacity);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            capacity,
+            refill_per_sec,
+        });
+        // 修改配置后按新的规则计算
+        bucket.capacity = capacity;
+        bucket.refill_per_sec = refill_per_sec;
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            0.0
+        } else {
+            (1.0 - bucket.tokens) / refill_per_sec
+        }
+    }
+}
+
+// 删除一小时内没有更新过的桶，这些桶早已补满，删除后等同于新桶
+pub async fn prune_buckets(pool: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM rate_limit_buckets WHERE updated_at < CURRENT_TIMESTAMP - INTERVAL '1 hour'")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Request guard that takes a token for the matched route, failing with 429 when none is left.
+pub struct RateLimit;
+
+/// How long a rate limited client should wait, read by the 429 catcher for `Retry-After`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryAfter(pub Duration);
+
+impl RetryAfter {
+    pub fn of(request: &Request<'_>) -> Option<Duration> {
+        request.local_cache(|| None::<RetryAfter>).map(|retry_after| retry_after.0)
+    }
+
+    // 向上取整，至少 1 秒
+    pub fn header_value(wait: Duration) -> String {
+        wait.as_secs_f64().ceil().max(1.0).to_string()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimit {
+    type Error = Duration;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let (Some(limiter), Some(pool)) = (
+            request.rocket().state::<RateLimiter>(),
+            request.rocket().state::<Pool<Postgres>>(),
+        ) else {
+            return Outcome::Success(RateLimit);
+        };
+        if !limiter.settings.enabled {
+            return Outcome::Success(RateLimit);
+        }
+        let route = request.route().map(|route| route.uri.path().to_string()).unwrap_or_default();
+        let rule = limiter.settings.rule_for(&route);
+
+        let mut keys = Vec::new();
+        if let Outcome::Success(client_addr) = request.guard::<&ClientRealAddr>().await {
+            keys.push(format!("{}|ip:{}", route, client_addr.ip));
+        }
+        let member = request
+            .query_value::<String>("openid")
+            .or_else(|| request.query_value::<String>("open_id"))
+            .and_then(Result::ok)
+            .filter(|member| !member.is_empty());
+        if let Some(member) = member {
+            keys.push(format!("{}|member:{}", route, member));
+        }
+
+        for key in keys {
+            if let Err(wait) = limiter.take(&key, rule, pool).await {
+                tracing::warn!(key = %key, retry_after_secs = wait.as_secs_f64(), "rate limit exceeded");
+                METRICS.rate_limited_total.with_label_values(&[route.as_str()]).inc();
+                request.local_cache(|| Some(RetryAfter(wait)));
+                return Outcome::Failure((Status::TooManyRequests, wait));
+            }
+        }
+        Outcome::Success(RateLimit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitSettings::default())
+    }
+
+    #[test]
+    fn memory_buckets_refill() {
+        let limiter = limiter();
+        assert_eq!(limiter.take_memory("a", 2.0, 1.0), 0.0);
+        assert_eq!(limiter.take_memory("a", 2.0, 1.0), 0.0);
+        let wait = limiter.take_memory("a", 2.0, 1.0);
+        assert!(wait > 0.9 && wait <= 1.0, "{}", wait);
+        // 其他键不受影响
+        assert_eq!(limiter.take_memory("b", 2.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn prune_uses_each_bucket_rule() {
+        let limiter = limiter();
+        let now = Instant::now();
+        {
+            let mut buckets = limiter.buckets.lock().unwrap();
+            // 宽松规则下还没补满的桶
+            buckets.insert(
+                "lenient".to_string(),
+                Bucket { tokens: 2.0, updated_at: now - Duration::from_secs(3), capacity: 10.0, refill_per_sec: 1.0 },
+            );
+            for index in 1..MAX_MEMORY_BUCKETS {
+                buckets.insert(
+                    format!("full{}", index),
+                    Bucket { tokens: 5.0, updated_at: now, capacity: 5.0, refill_per_sec: 1.0 },
+                );
+            }
+        }
+        // 严格规则的请求触发清理，只删除按各自规则已补满的桶
+        limiter.take_memory("strict", 1.0, 1.0 / 60.0);
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets["lenient"].tokens < 10.0);
+    }
+
+    #[test]
+    fn poisoned_lock_still_limits() {
+        let limiter = limiter();
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _buckets = limiter.buckets.lock().unwrap();
+            panic!("poison the lock");
+        }));
+        assert!(limiter.buckets.is_poisoned());
+        assert_eq!(limiter.take_memory("a", 1.0, 1.0), 0.0);
+        assert!(limiter.take_memory("a", 1.0, 1.0) > 0.0);
+    }
+}
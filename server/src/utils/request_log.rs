@@ -0,0 +1,137 @@
+use std::time::Instant;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Orbit, Request, Response, Rocket, Route};
+use tracing::Instrument;
+use uuid::Uuid;
+
+// 每个请求的 id 和开始时间，保存在 request.local_cache 中
+pub struct RequestContext {
+    pub id: String,
+    pub started_at: Instant,
+}
+
+impl RequestContext {
+    pub fn of<'r>(request: &'r Request<'_>) -> &'r RequestContext {
+        request.local_cache(|| RequestContext {
+            id: Uuid::new_v4().simple().to_string(),
+            started_at: Instant::now(),
+        })
+    }
+}
+
+pub struct RequestLogger;
+
+// 在请求的 span 中执行路由，处理过程中的日志都带上 request_id。
+// Rocket 的 fairing 无法包住 handler 的执行，所以在挂载路由时替换 handler
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        // 用 error 级别的 span，日志级别调高到 warn 或 error 时错误日志仍带有 request_id
+        let span = tracing::error_span!(
+            "request",
+            request_id = %RequestContext::of(request).id,
+            method = %request.method(),
+            route = request.route().map(|route| route.uri.to_string()).as_deref().unwrap_or("-"),
+        );
+        self.0.handle(request, data).instrument(span).await
+    }
+}
+
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
+
+// 沿用上游传来的 X-Request-Id，方便和网关日志对应
+fn incoming_request_id(request: &Request<'_>) -> Option<String> {
+    request
+        .headers()
+        .get_one("X-Request-Id")
+        .filter(|id| !id.is_empty() && id.len() <= 64)
+        .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .map(|id| id.to_string())
+}
+
+// 小程序接口以 openid/open_id 参数识别会员，管理后台以 admin_<id>_<时间戳> 形式的 token 识别管理员
+pub(crate) fn member(request: &Request<'_>) -> Option<String> {
+    request
+        .query_value::<String>("openid")
+        .or_else(|| request.query_value::<String>("open_id"))
+        .and_then(|value| value.ok())
+}
+
+pub(crate) fn admin(request: &Request<'_>) -> Option<i32> {
+    let token = request
+        .headers()
+        .get_one("Authorization")
+        .map(|value| value.trim_start_matches("Bearer ").to_string())
+        .or_else(|| request.query_value::<String>("token").and_then(|value| value.ok()))?;
+    token.strip_prefix("admin_")?.split('_').next()?.parse().ok()
+}
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request id and access log",
+            kind: Kind::Request | Kind::Response | Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let config = rocket.config();
+        tracing::info!(address = %config.address, port = config.port, "server started");
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let id = incoming_request_id(request).unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+        request.local_cache(|| RequestContext {
+            id,
+            started_at: Instant::now(),
+        });
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let context = RequestContext::of(request);
+        let duration_ms = (context.started_at.elapsed().as_secs_f64() * 100_000.0).round() / 100.0;
+        let status = response.status().code;
+        let route = request.route().map(|route| route.uri.to_string());
+        response.set_header(Header::new("X-Request-Id", context.id.clone()));
+
+        let member = member(request);
+        let admin = admin(request);
+        macro_rules! access_log {
+            ($level:ident) => {
+                tracing::$level!(
+                    request_id = %context.id,
+                    method = %request.method(),
+                    path = %request.uri().path(),
+                    route = route.as_deref().unwrap_or("-"),
+                    status,
+                    duration_ms,
+                    member = member.as_deref(),
+                    admin,
+                    "request completed"
+                )
+            };
+        }
+        if status >= 500 {
+            access_log!(error);
+        } else if status >= 400 {
+            access_log!(warn);
+        } else {
+            access_log!(info);
+        }
+    }
+}
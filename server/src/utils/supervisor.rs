@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket, Shutdown};
+use serde::Serialize;
+use sqlx::{Pool, Postgres};
+use tokio::task::JoinHandle;
+
+// 后台定时任务。每个任务按固定间隔加随机抖动执行，执行前用 Postgres advisory lock
+// 抢占，多副本部署时同一时刻只有一个实例在跑同一个任务。Rocket 关闭时不再开始
+// 新的执行，正在执行的任务会等它结束。
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+type JobFn = Arc<dyn Fn(Pool<Postgres>) -> JobFuture + Send + Sync>;
+
+// 关闭时最多等待正在执行的任务这么久
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+struct Job {
+    name: &'static str,
+    interval: Duration,
+    run: JobFn,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: &'static str,
+    pub interval_secs: u64,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    // 锁被其他实例持有而跳过的次数
+    pub skipped: u64,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    // ok / error / skipped
+    pub last_outcome: Option<&'static str>,
+    pub last_message: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Default)]
+pub struct JobStatuses(Arc<Mutex<BTreeMap<&'static str, JobStatus>>>);
+
+impl JobStatuses {
+    pub fn snapshot(&self) -> Vec<JobStatus> {
+        match self.0.lock() {
+            Ok(statuses) => statuses.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn update(&self, name: &'static str, f: impl FnOnce(&mut JobStatus)) {
+        if let Ok(mut statuses) = self.0.lock() {
+            if let Some(status) = statuses.get_mut(name) {
+                f(status);
+            }
+        }
+    }
+}
+
+pub struct Supervisor {
+    jobs: Vec<Job>,
+    jitter: Duration,
+    statuses: JobStatuses,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Supervisor {
+    pub fn new(jitter: Duration) -> Self {
+        Supervisor {
+            jobs: Vec::new(),
+            jitter,
+            statuses: JobStatuses::default(),
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    // 注册任务，job 返回的字符串记录在任务状态中，如 "expired 3 cards"
+    pub fn every<F, Fut>(mut self, name: &'static str, interval: Duration, job: F) -> Self
+    where
+        F: Fn(Pool<Postgres>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        if let Ok(mut statuses) = self.statuses.0.lock() {
+            statuses.insert(
+                name,
+                JobStatus {
+                    name,
+                    interval_secs: interval.as_secs(),
+                    running: false,
+                    runs: 0,
+                    failures: 0,
+                    skipped: 0,
+                    last_started_at: None,
+                    last_finished_at: None,
+                    last_outcome: None,
+                    last_message: None,
+                    next_run_at: None,
+                },
+            );
+        }
+        self.jobs.push(Job {
+            name,
+            interval,
+            run: Arc::new(move |pool| Box::pin(job(pool))),
+        });
+        self
+    }
+
+    pub fn statuses(&self) -> JobStatuses {
+        self.statuses.clone()
+    }
+}
+
+async fn run_loop(job: Job, jitter: Duration, pool: Pool<Postgres>, statuses: JobStatuses, mut shutdown: Shutdown) {
+    // 首次执行只等抖动时间，之后每次等 interval + 抖动
+    let mut delay = random_delay(jitter);
+    loop {
+        let next_run_at = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+        statuses.update(job.name, |status| status.next_run_at = Some(next_run_at));
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = &mut shutdown => break,
+        }
+        run_once(&job, &pool, &statuses).await;
+        delay = job.interval + random_delay(jitter);
+    }
+    statuses.update(job.name, |status| status.next_run_at = None);
+    tracing::info!(job = job.name, "background job stopped");
+}
+
+fn random_delay(jitter: Duration) -> Duration {
+    let millis = jitter.as_millis() as u64;
+    if millis == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+}
+
+async fn run_once(job: &Job, pool: &Pool<Postgres>, statuses: &JobStatuses) {
+    // advisory lock 属于会话，加锁和解锁必须在同一个连接上
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(error) => {
+            tracing::error!(job = job.name, error = %error, "failed to acquire connection for background job");
+            statuses.update(job.name, |status| {
+                status.failures += 1;
+                status.last_outcome = Some("error");
+                status.last_message = Some(error.to_string());
+            });
+            return;
+        }
+    };
+    let lock_key = format!("yoga-job:{}", job.name);
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock(hashtextextended($1, 0))")
+        .bind(&lock_key)
+        .fetch_one(&mut *conn)
+        .await;
+    match locked {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::debug!(job = job.name, "background job is running on another instance");
+            statuses.update(job.name, |status| {
+                status.skipped += 1;
+                status.last_outcome = Some("skipped");
+                status.last_message = Some("locked by another instance".to_string());
+            });
+            return;
+        }
+        Err(error) => {
+            tracing::error!(job = job.name, error = %error, "failed to take background job lock");
+            statuses.update(job.name, |status| {
+                status.failures += 1;
+                status.last_outcome = Some("error");
+                status.last_message = Some(error.to_string());
+            });
+            return;
+        }
+    }
+
+    let started_at = Utc::now();
+    statuses.update(job.name, |status| {
+        status.running = true;
+        status.last_started_at = Some(started_at);
+    });
+    // 在单独的任务中执行，任务 panic 时这里仍能记录失败并释放锁
+    let result = match tokio::spawn((job.run)(pool.clone())).await {
+        Ok(result) => Some(result),
+        Err(error) => {
+            tracing::error!(job = job.name, error = %error, "background job panicked");
+            None
+        }
+    };
+    let duration_ms = (Utc::now() - started_at).num_milliseconds();
+
+    let result = match result {
+        Some(result) => {
+            if let Err(error) = sqlx::query("SELECT pg_advisory_unlock(hashtextextended($1, 0))")
+                .bind(&lock_key)
+                .execute(&mut *conn)
+                .await
+            {
+                // 解锁失败时关闭连接，会话结束后锁自动释放
+                tracing::warn!(job = job.name, error = %error, "failed to release background job lock");
+                let _ = conn.detach();
+            }
+            result
+        }
+        // panic 时不再解锁，直接关闭连接，会话结束后锁自动释放
+        None => {
+            let _ = conn.detach();
+            Err("job panicked".to_string())
+        }
+    };
+
+    match &result {
+        Ok(message) => tracing::info!(job = job.name, duration_ms, result = %message, "background job finished"),
+        Err(error) => tracing::error!(job = job.name, duration_ms, error = %error, "background job failed"),
+    }
+    statuses.update(job.name, |status| {
+        status.running = false;
+        status.runs += 1;
+        status.last_finished_at = Some(Utc::now());
+        match result {
+            Ok(message) => {
+                status.last_outcome = Some("ok");
+                status.last_message = Some(message);
+            }
+            Err(error) => {
+                status.failures += 1;
+                status.last_outcome = Some("error");
+                status.last_message = Some(error);
+            }
+        }
+    });
+}
+
+#[rocket::async_trait]
+impl Fairing for Supervisor {
+    fn info(&self) -> Info {
+        Info {
+            name: "Background job supervisor",
+            kind: Kind::Liftoff | Kind::Shutdown,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(pool) = rocket.state::<Pool<Postgres>>() else {
+            tracing::error!("database pool is not managed, background jobs are not started");
+            return;
+        };
+        let mut handles = Vec::new();
+        for job in &self.jobs {
+            tracing::info!(job = job.name, interval_secs = job.interval.as_secs(), "background job scheduled");
+            handles.push(tokio::spawn(run_loop(
+                job.clone(),
+                self.jitter,
+                pool.clone(),
+                self.statuses.clone(),
+                rocket.shutdown(),
+            )));
+        }
+        if let Ok(mut current) = self.handles.lock() {
+            current.extend(handles);
+        }
+    }
+
+    async fn on_shutdown(&self, _rocket: &Rocket<Orbit>) {
+        let handles = match self.handles.lock() {
+            Ok(mut handles) => std::mem::take(&mut *handles),
+            Err(_) => return,
+        };
+        let all = await_all(handles);
+        if tokio::time::timeout(SHUTDOWN_GRACE, all).await.is_err() {
+            tracing::warn!("background jobs did not stop within the grace period");
+        }
+    }
+}
+
+// 依次等待所有任务结束，任务 panic 或被取消时忽略
+async fn await_all(handles: Vec<JoinHandle<()>>) {
+    for handle in handles {
+        let _ = handle.await;
+    }
+}
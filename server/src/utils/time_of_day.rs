@@ -0,0 +1,27 @@
+// 一天中的时间 HH:MM，换算为当天的秒数，允许 24:00 表示当天结束。
+// 课表海报模板的时段和课程搜索的 after/before 参数使用
+pub fn parse_time_of_day(value: &str) -> Option<u32> {
+    let (hour, minute) = value.split_once(':')?;
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    if hour > 24 || minute >= 60 || (hour == 24 && minute > 0) {
+        return None;
+    }
+    Some(hour * 3600 + minute * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_of_day() {
+        assert_eq!(parse_time_of_day("00:00"), Some(0));
+        assert_eq!(parse_time_of_day("09:30"), Some(9 * 3600 + 30 * 60));
+        assert_eq!(parse_time_of_day("24:00"), Some(24 * 3600));
+        assert_eq!(parse_time_of_day("24:01"), None);
+        assert_eq!(parse_time_of_day("12:60"), None);
+        assert_eq!(parse_time_of_day("9"), None);
+        assert_eq!(parse_time_of_day("7pm"), None);
+    }
+}